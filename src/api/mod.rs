@@ -10,3 +10,13 @@ pub(crate) const API_BASE_URL: &str = "https://paper-api.alpaca.markets";
 pub(crate) const HDR_KEY_ID: &str = "APCA-API-KEY-ID";
 /// The HTTP header representing the secret key.
 pub(crate) const HDR_SECRET: &str = "APCA-API-SECRET-KEY";
+/// The HTTP header reporting the maximum number of requests per minute.
+pub(crate) const HDR_RATE_LIMIT_LIMIT: &str = "X-RateLimit-Limit";
+/// The HTTP header reporting the number of requests remaining in the
+/// current rate limit window.
+pub(crate) const HDR_RATE_LIMIT_REMAINING: &str = "X-RateLimit-Remaining";
+/// The HTTP header reporting the point in time (in seconds since the
+/// Unix epoch) at which the rate limit gets reset.
+pub(crate) const HDR_RATE_LIMIT_RESET: &str = "X-RateLimit-Reset";
+/// The HTTP header containing the ID Alpaca assigned to a request.
+pub(crate) const HDR_REQUEST_ID: &str = "X-Request-ID";
@@ -541,6 +541,50 @@ mod tests {
     assert_eq!(non_trade.per_share_amount, Some(Num::new(108783, 1000000)));
   }
 
+  #[test]
+  fn parse_option_activities() {
+    let response = r#"{
+  "activity_type": "FILL",
+  "cum_qty": "2",
+  "id": "20240102150405123::8efc7b9a-8b2b-4000-9955-d36e7db0df74",
+  "leaves_qty": "0",
+  "price": "3.4",
+  "qty": "2",
+  "side": "sell",
+  "symbol": "AAPL240119C00190000",
+  "transaction_time": "2024-01-02T15:04:05.123Z",
+  "order_id": "904837e3-3b76-47ec-b432-046db621571b",
+  "type": "fill"
+}"#;
+
+    let trade = from_json::<Activity>(response)
+      .unwrap()
+      .into_trade()
+      .unwrap();
+    assert_eq!(trade.symbol, "AAPL240119C00190000");
+    assert_eq!(trade.side, Side::Sell);
+    assert_eq!(trade.quantity, Num::from(2));
+    assert_eq!(trade.price, Num::new(34, 10));
+
+    let response = r#"{
+  "activity_type": "OPEXP",
+  "id": "20240119000000000::0b6a0c0d-4b7e-4b0e-9c4d-3c1f3e1c2b0a",
+  "date": "2024-01-19",
+  "net_amount": "0",
+  "description": "Option Expiry",
+  "symbol": "AAPL240119C00190000",
+  "qty": "2"
+}"#;
+
+    let non_trade = from_json::<Activity>(response)
+      .unwrap()
+      .into_non_trade()
+      .unwrap();
+    assert_eq!(non_trade.type_, ActivityType::OptionExpiration);
+    assert_eq!(non_trade.symbol, Some("AAPL240119C00190000".into()));
+    assert_eq!(non_trade.quantity, Some(Num::from(2)));
+  }
+
   #[test(tokio::test)]
   async fn retrieve_some_activities() {
     let api_info = ApiInfo::from_env().unwrap();
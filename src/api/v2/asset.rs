@@ -15,6 +15,7 @@ use serde::Serializer;
 use uuid::Error as UuidError;
 use uuid::Uuid;
 
+use crate::data::v1beta1::options::OptionSymbol;
 use crate::Str;
 
 
@@ -42,6 +43,9 @@ pub enum Class {
   /// Crypto currencies.
   #[serde(rename = "crypto")]
   Crypto,
+  /// US options.
+  #[serde(rename = "us_option")]
+  UsOption,
   /// Any other asset class that we have not accounted for.
   ///
   /// Note that having any such unknown asset class should be considered
@@ -57,6 +61,7 @@ impl AsRef<str> for Class {
     match *self {
       Class::UsEquity => "us_equity",
       Class::Crypto => "crypto",
+      Class::UsOption => "us_option",
       Class::Unknown => "unknown",
     }
   }
@@ -78,6 +83,8 @@ impl FromStr for Class {
       Ok(Class::UsEquity)
     } else if s == Class::Crypto.as_ref() {
       Ok(Class::Crypto)
+    } else if s == Class::UsOption.as_ref() {
+      Ok(Class::UsOption)
     } else {
       // Note that we do not support creating the `Unknown` variant
       // here. This variant is really only meant to cover
@@ -195,6 +202,10 @@ impl FromStr for Symbol {
       [sym] => {
         if let Ok(id) = Uuid::parse_str(sym) {
           Self::Id(Id(id))
+        } else if OptionSymbol::from_str(sym).is_ok() {
+          // OCC option symbols contain digits, which we otherwise
+          // reject.
+          Self::Sym((*sym).to_string())
         } else {
           let invalid = sym.as_bytes().iter().try_fold((), |(), c| {
             if !c.is_ascii_alphabetic() || !c.is_ascii_uppercase() {
@@ -411,6 +422,10 @@ mod tests {
     );
 
     assert_eq!(Symbol::from_str("SPY").unwrap(), Symbol::Sym("SPY".into()));
+    assert_eq!(
+      Symbol::from_str("AAPL240119C00190000").unwrap(),
+      Symbol::Sym("AAPL240119C00190000".into()),
+    );
 
     assert_eq!(
       Symbol::from_str("SPY:NYSE").unwrap(),
@@ -434,6 +449,10 @@ mod tests {
       Symbol::from_str("Z%&Y"),
       Err(ParseSymbolError::InvalidSymbol('%')),
     );
+    assert_eq!(
+      Symbol::from_str("AAPL240119X00190000"),
+      Err(ParseSymbolError::InvalidSymbol('2')),
+    );
     assert_eq!(
       Symbol::from_str("A:B:C:"),
       Err(ParseSymbolError::InvalidFormat),
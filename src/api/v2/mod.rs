@@ -19,12 +19,19 @@ pub mod calendar;
 pub mod clock;
 /// Definitions surrounding orders.
 pub mod order;
+/// Definitions surrounding option contracts.
+pub mod option_contract;
+/// Functionality for listing option contracts.
+pub mod option_contracts;
 /// Functionality for listing orders.
 pub mod orders;
 /// Definitions surrounding open positions.
 pub mod position;
 /// Functionality for listing open positions.
 pub mod positions;
+/// Definitions for streaming order updates that survives connection
+/// loss.
+pub mod reconnect;
 /// Definitions for trade related updates.
 pub mod updates;
 /// Definitions surrounding watchlists.
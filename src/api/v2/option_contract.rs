@@ -0,0 +1,224 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::NaiveDate;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::api::v2::asset;
+use crate::data::v1beta1::options::OptionType;
+use crate::Str;
+
+
+/// The exercise style of an option contract.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum Style {
+  /// The contract can be exercised at any time up to its expiration.
+  #[serde(rename = "american")]
+  American,
+  /// The contract can only be exercised at its expiration.
+  #[serde(rename = "european")]
+  European,
+}
+
+
+/// The representation of an option contract as used by Alpaca.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OptionContract {
+  /// The contract's ID.
+  ///
+  /// This ID is the asset ID used by orders and positions referencing
+  /// the contract.
+  #[serde(rename = "id")]
+  pub id: asset::Id,
+  /// The contract's OCC symbol, e.g., `AAPL240119C00190000`.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// A human readable name of the contract.
+  #[serde(rename = "name")]
+  pub name: String,
+  /// The contract's status.
+  #[serde(rename = "status")]
+  pub status: asset::Status,
+  /// Whether the contract is tradable on Alpaca or not.
+  #[serde(rename = "tradable")]
+  pub tradable: bool,
+  /// The date at which the contract expires.
+  #[serde(rename = "expiration_date")]
+  pub expiration_date: NaiveDate,
+  /// The root symbol of the contract.
+  #[serde(rename = "root_symbol")]
+  pub root_symbol: String,
+  /// The symbol of the underlying asset.
+  #[serde(rename = "underlying_symbol")]
+  pub underlying_symbol: String,
+  /// The ID of the underlying asset.
+  #[serde(rename = "underlying_asset_id")]
+  pub underlying_asset_id: asset::Id,
+  /// The type of the contract.
+  #[serde(rename = "type")]
+  pub type_: OptionType,
+  /// The exercise style of the contract.
+  #[serde(rename = "style")]
+  pub style: Style,
+  /// The strike price of the contract.
+  #[serde(rename = "strike_price")]
+  pub strike_price: Num,
+  /// The number of shares of the underlying a single contract
+  /// controls.
+  #[serde(rename = "multiplier")]
+  pub multiplier: Num,
+  /// The size of the contract.
+  #[serde(rename = "size")]
+  pub size: Num,
+  /// The number of contracts outstanding, if known.
+  #[serde(rename = "open_interest")]
+  pub open_interest: Option<Num>,
+  /// The date `open_interest` refers to.
+  #[serde(rename = "open_interest_date")]
+  pub open_interest_date: Option<NaiveDate>,
+  /// The most recent closing price of the contract, if any.
+  #[serde(rename = "close_price")]
+  pub close_price: Option<Num>,
+  /// The date `close_price` refers to.
+  #[serde(rename = "close_price_date")]
+  pub close_price_date: Option<NaiveDate>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v2/options/contracts/{symbol} endpoint.
+  ///
+  /// The contract can be identified by its OCC symbol or its ID.
+  pub Get(asset::Symbol),
+  Ok => OptionContract, [
+    /// The option contract for the given symbol was retrieved
+    /// successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// No option contract was found for the given symbol.
+    /* 404 */ NOT_FOUND => NotFound,
+  ]
+
+  #[inline]
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/options/contracts/{input}").into()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use serde_json::from_str as from_json;
+  use serde_json::to_string as to_json;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  /// Check that we can parse and serialize a reference option
+  /// contract object.
+  #[test]
+  fn parse_reference_contract() {
+    let response = r#"{
+  "id": "6e58f870-fe73-4583-81e4-b9a37892c36f",
+  "symbol": "AAPL240119C00190000",
+  "name": "AAPL Jan 19 2024 190 Call",
+  "status": "active",
+  "tradable": true,
+  "expiration_date": "2024-01-19",
+  "root_symbol": "AAPL",
+  "underlying_symbol": "AAPL",
+  "underlying_asset_id": "b0b6dd9d-8b9b-48a9-ba46-b9d54906e415",
+  "type": "call",
+  "style": "american",
+  "strike_price": "190",
+  "multiplier": "100",
+  "size": "100",
+  "open_interest": "23871",
+  "open_interest_date": "2024-01-02",
+  "close_price": "3.4",
+  "close_price_date": "2024-01-02"
+}"#;
+
+    let contract = from_json::<OptionContract>(response).unwrap();
+    let contract = from_json::<OptionContract>(&to_json(&contract).unwrap()).unwrap();
+    assert_eq!(contract.symbol, "AAPL240119C00190000");
+    assert_eq!(contract.status, asset::Status::Active);
+    assert_eq!(
+      contract.expiration_date,
+      NaiveDate::from_ymd_opt(2024, 1, 19).unwrap()
+    );
+    assert_eq!(contract.underlying_symbol, "AAPL");
+    assert_eq!(contract.type_, OptionType::Call);
+    assert_eq!(contract.style, Style::American);
+    assert_eq!(contract.strike_price, Num::from(190));
+    assert_eq!(contract.multiplier, Num::from(100));
+    assert_eq!(contract.open_interest, Some(Num::from(23871)));
+    assert_eq!(contract.close_price, Some(Num::new(34, 10)));
+  }
+
+  /// Check that we can parse an option contract for which no open
+  /// interest or close price is known.
+  #[test]
+  fn parse_contract_without_statistics() {
+    let response = r#"{
+  "id": "6e58f870-fe73-4583-81e4-b9a37892c36f",
+  "symbol": "SPY250321P00412500",
+  "name": "SPY Mar 21 2025 412.5 Put",
+  "status": "active",
+  "tradable": true,
+  "expiration_date": "2025-03-21",
+  "root_symbol": "SPY",
+  "underlying_symbol": "SPY",
+  "underlying_asset_id": "b28f4066-5c6d-479b-a2af-85dc1a8f16fb",
+  "type": "put",
+  "style": "american",
+  "strike_price": "412.5",
+  "multiplier": "100",
+  "size": "100",
+  "open_interest": null,
+  "open_interest_date": null,
+  "close_price": null,
+  "close_price_date": null
+}"#;
+
+    let contract = from_json::<OptionContract>(response).unwrap();
+    assert_eq!(contract.type_, OptionType::Put);
+    assert_eq!(contract.strike_price, Num::new(4125, 10));
+    assert_eq!(contract.open_interest, None);
+    assert_eq!(contract.close_price_date, None);
+  }
+
+  /// Check that we get the expected error when requesting a
+  /// non-existent option contract.
+  #[test(tokio::test)]
+  async fn request_non_existent_contract() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let symbol = asset::Symbol::from_str("ABC991231C00001000").unwrap();
+    let err = client.issue::<Get>(&symbol).await.unwrap_err();
+    match err {
+      RequestError::Endpoint(GetError::NotFound(_)) => (),
+      _ => panic!("Received unexpected error: {err:?}"),
+    };
+  }
+}
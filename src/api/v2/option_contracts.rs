@@ -0,0 +1,244 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::NaiveDate;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::api::v2::asset;
+use crate::api::v2::option_contract::OptionContract;
+use crate::api::v2::option_contract::Style;
+use crate::data::v1beta1::options::OptionType;
+use crate::util::string_slice_to_str;
+use crate::Paginated;
+use crate::Str;
+
+
+/// A GET request to be made to the /v2/options/contracts endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ListReq {
+  /// The symbols of the underlyings to list contracts for.
+  #[serde(
+    rename = "underlying_symbols",
+    serialize_with = "string_slice_to_str",
+    skip_serializing_if = "Vec::is_empty"
+  )]
+  pub underlying_symbols: Vec<String>,
+  /// The status of contracts to list.
+  ///
+  /// The API defaults to active contracts.
+  #[serde(rename = "status", skip_serializing_if = "Option::is_none")]
+  pub status: Option<asset::Status>,
+  /// Filter contracts expiring on this date.
+  #[serde(rename = "expiration_date", skip_serializing_if = "Option::is_none")]
+  pub expiration_date: Option<NaiveDate>,
+  /// Filter contracts expiring on or after this date.
+  #[serde(
+    rename = "expiration_date_gte",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub expiration_date_gte: Option<NaiveDate>,
+  /// Filter contracts expiring on or before this date.
+  #[serde(
+    rename = "expiration_date_lte",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub expiration_date_lte: Option<NaiveDate>,
+  /// Filter contracts by root symbol.
+  #[serde(rename = "root_symbol", skip_serializing_if = "Option::is_none")]
+  pub root_symbol: Option<String>,
+  /// Filter contracts by type.
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub type_: Option<OptionType>,
+  /// Filter contracts by exercise style.
+  #[serde(rename = "style", skip_serializing_if = "Option::is_none")]
+  pub style: Option<Style>,
+  /// Filter contracts with a strike price equal to or above this
+  /// value.
+  #[serde(rename = "strike_price_gte", skip_serializing_if = "Option::is_none")]
+  pub strike_price_gte: Option<Num>,
+  /// Filter contracts with a strike price equal to or below this
+  /// value.
+  #[serde(rename = "strike_price_lte", skip_serializing_if = "Option::is_none")]
+  pub strike_price_lte: Option<Num>,
+  /// The maximum number of contracts to be returned for each page.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided we will pass a page token to continue where we left
+  /// off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A collection of option contracts as returned by the API. This is
+/// one page of contracts.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct OptionContracts {
+  /// The list of returned contracts.
+  #[serde(rename = "option_contracts")]
+  pub contracts: Vec<OptionContract>,
+  /// The token to provide to a request to get the next page of
+  /// contracts for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/options/contracts
+  /// endpoint.
+  pub List(ListReq),
+  Ok => OptionContracts, [
+    /// The list of option contracts was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => ListError, [
+    /// A query parameter was invalid.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidInput,
+  ]
+
+  #[inline]
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/options/contracts".into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+impl Paginated for List {
+  type Item = OptionContract;
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.contracts, output.next_page_token)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures::TryStreamExt as _;
+
+  use http::Request;
+  use http::Response;
+
+  use http_endpoint::Endpoint as _;
+
+  use hyper::body::Bytes;
+  use hyper::Body;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  /// Check that we create the expected query for a request.
+  #[test]
+  fn serialize_request() {
+    let req = ListReq::default();
+    assert_eq!(List::query(&req).unwrap().unwrap(), "");
+
+    let req = ListReq {
+      underlying_symbols: vec!["AAPL".to_string(), "SPY".to_string()],
+      status: Some(asset::Status::Active),
+      expiration_date_lte: Some(NaiveDate::from_ymd_opt(2024, 2, 16).unwrap()),
+      type_: Some(OptionType::Call),
+      strike_price_gte: Some(Num::new(1875, 10)),
+      limit: Some(100),
+      ..Default::default()
+    };
+
+    let query = List::query(&req).unwrap().unwrap();
+    let expected = "underlying_symbols=AAPL%2CSPY&status=active&expiration_date_lte=2024-02-16&type=call&strike_price_gte=187.5&limit=100";
+    assert_eq!(query, expected);
+  }
+
+  /// Check that we can page through option contracts.
+  #[test(tokio::test)]
+  async fn paginate_contracts() {
+    let transport = |request: Request<Bytes>| {
+      assert_eq!(request.uri().path(), "/v2/options/contracts");
+
+      let query = request.uri().query().unwrap_or_default();
+      let (strike, next) = if query.contains("page_token=next") {
+        (195, "null")
+      } else {
+        (190, r#""next""#)
+      };
+      let body = format!(
+        r#"{{"option_contracts":[{{"id":"6e58f870-fe73-4583-81e4-b9a37892c36f","symbol":"AAPL240119C00{strike}000","name":"AAPL Jan 19 2024 {strike} Call","status":"active","tradable":true,"expiration_date":"2024-01-19","root_symbol":"AAPL","underlying_symbol":"AAPL","underlying_asset_id":"b0b6dd9d-8b9b-48a9-ba46-b9d54906e415","type":"call","style":"american","strike_price":"{strike}","multiplier":"100","size":"100","open_interest":null,"open_interest_date":null,"close_price":null,"close_price_date":null}}],"next_page_token":{next}}}"#
+      );
+      Response::new(Body::from(body))
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+
+    let req = ListReq {
+      underlying_symbols: vec!["AAPL".to_string()],
+      ..Default::default()
+    };
+    let contracts = client
+      .paginate::<List>(req)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(
+      contracts
+        .iter()
+        .map(|contract| contract.symbol.as_str())
+        .collect::<Vec<_>>(),
+      vec!["AAPL240119C00190000", "AAPL240119C00195000"]
+    );
+    assert_eq!(contracts[1].strike_price, Num::from(195));
+  }
+
+  /// Check that we can list option contracts of an underlying.
+  #[test(tokio::test)]
+  async fn list_contracts() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = ListReq {
+      underlying_symbols: vec!["SPY".to_string()],
+      type_: Some(OptionType::Put),
+      limit: Some(5),
+      ..Default::default()
+    };
+    let contracts = client.issue::<List>(&req).await.unwrap();
+    assert!(contracts.contracts.len() <= 5);
+    assert!(contracts
+      .contracts
+      .iter()
+      .all(|contract| contract.underlying_symbol == "SPY" && contract.type_ == OptionType::Put));
+  }
+}
@@ -3,6 +3,7 @@
 
 use std::ops::Deref;
 use std::ops::Not;
+use std::str::FromStr as _;
 
 use chrono::DateTime;
 use chrono::Utc;
@@ -16,13 +17,17 @@ use serde::de::IntoDeserializer;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
 use serde_json::from_slice as from_json;
 use serde_json::to_vec as to_json;
 use serde_urlencoded::to_string as to_query;
 
+use thiserror::Error;
+
 use uuid::Uuid;
 
 use crate::api::v2::asset;
+use crate::data::v1beta1::options::OptionSymbol;
 use crate::util::vec_from_str;
 use crate::Str;
 
@@ -162,6 +167,39 @@ impl Not for Side {
 }
 
 
+/// The intent of an order with respect to the position it affects.
+///
+/// Position intents are primarily relevant for option orders, where
+/// they disambiguate between opening and closing a position.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum PositionIntent {
+  /// Buy to open a new or increase an existing long position.
+  #[serde(rename = "buy_to_open")]
+  BuyToOpen,
+  /// Buy to close (reduce) an existing short position.
+  #[serde(rename = "buy_to_close")]
+  BuyToClose,
+  /// Sell to open a new or increase an existing short position.
+  #[serde(rename = "sell_to_open")]
+  SellToOpen,
+  /// Sell to close (reduce) an existing long position.
+  #[serde(rename = "sell_to_close")]
+  SellToClose,
+}
+
+impl PositionIntent {
+  /// Retrieve the order side implied by the position intent.
+  #[inline]
+  pub fn side(self) -> Side {
+    match self {
+      Self::BuyToOpen | Self::BuyToClose => Side::Buy,
+      Self::SellToOpen | Self::SellToClose => Side::Sell,
+    }
+  }
+}
+
+
 /// The class an order belongs to.
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[non_exhaustive]
@@ -186,6 +224,13 @@ pub enum Class {
   /// entry order.
   #[serde(rename = "oto")]
   OneTriggersOther,
+  /// A multi-leg order, comprised of up to four option (or underlying)
+  /// legs that are executed atomically.
+  ///
+  /// Multi-leg orders are created by means of the [`CreateMultiLeg`]
+  /// endpoint.
+  #[serde(rename = "mleg")]
+  MultiLeg,
 }
 
 impl Default for Class {
@@ -408,6 +453,8 @@ pub struct CreateReqInit {
   pub extended_hours: bool,
   /// See `CreateReq::client_order_id`.
   pub client_order_id: Option<String>,
+  /// See `CreateReq::position_intent`.
+  pub position_intent: Option<PositionIntent>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   pub _non_exhaustive: (),
@@ -439,6 +486,7 @@ impl CreateReqInit {
       client_order_id: self.client_order_id,
       trail_price: self.trail_price,
       trail_percent: self.trail_percent,
+      position_intent: self.position_intent,
       _non_exhaustive: (),
     }
   }
@@ -499,6 +547,12 @@ pub struct CreateReq {
   /// The documented maximum length is 48 characters.
   #[serde(rename = "client_order_id")]
   pub client_order_id: Option<String>,
+  /// The intent of the order with respect to the affected position.
+  ///
+  /// This member is mostly relevant for option orders, e.g., to sell
+  /// a covered call (`PositionIntent::SellToOpen`).
+  #[serde(rename = "position_intent")]
+  pub position_intent: Option<PositionIntent>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   #[serde(skip)]
@@ -506,6 +560,248 @@ pub struct CreateReq {
 }
 
 
+/// The maximum number of legs a multi-leg order may have.
+const MAX_LEGS: usize = 4;
+
+
+/// Serialize a `u32` as a string.
+fn u32_to_str<S>(value: &u32, serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  serializer.collect_str(value)
+}
+
+
+/// Calculate the greatest common divisor of two numbers.
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+  while b != 0 {
+    let r = a % b;
+    a = b;
+    b = r;
+  }
+  a
+}
+
+
+/// A helper for initializing [`Leg`] objects.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LegInit {
+  /// See `Leg::position_intent`.
+  pub position_intent: Option<PositionIntent>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl LegInit {
+  /// Create a [`Leg`] from a `LegInit`.
+  #[inline]
+  pub fn init<S>(self, symbol: S, ratio: u32, side: Side) -> Leg
+  where
+    S: Into<String>,
+  {
+    Leg {
+      symbol: symbol.into(),
+      ratio,
+      side,
+      position_intent: self.position_intent,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// A single leg of a multi-leg order.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Leg {
+  /// The symbol of the option contract (or of the underlying) to trade.
+  #[serde(rename = "symbol")]
+  pub symbol: String,
+  /// The ratio of the leg's quantity to the quantity of the overall
+  /// order.
+  #[serde(rename = "ratio_qty", serialize_with = "u32_to_str")]
+  pub ratio: u32,
+  /// The side the leg is on.
+  #[serde(rename = "side")]
+  pub side: Side,
+  /// The intent of the leg with respect to the affected position.
+  #[serde(rename = "position_intent", skip_serializing_if = "Option::is_none")]
+  pub position_intent: Option<PositionIntent>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+impl Leg {
+  /// Retrieve the symbol of the underlying this leg refers to.
+  ///
+  /// For option contracts that is the underlying of the contract, for
+  /// any other symbol it is the symbol itself.
+  fn underlying(&self) -> String {
+    match OptionSymbol::from_str(&self.symbol) {
+      Ok(symbol) => symbol.underlying().to_string(),
+      Err(_) => self.symbol.clone(),
+    }
+  }
+}
+
+
+/// An enumeration of the possible errors when validating the legs of a
+/// multi-leg order.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[non_exhaustive]
+pub enum MultiLegError {
+  /// Fewer than two legs were provided.
+  #[error("a multi-leg order requires at least two legs")]
+  TooFewLegs,
+  /// More than four legs were provided.
+  #[error("a multi-leg order supports at most {MAX_LEGS} legs, but {0} were provided")]
+  TooManyLegs(usize),
+  /// The leg at the given index has a ratio of zero.
+  #[error("leg {0} has a ratio of zero")]
+  ZeroRatio(usize),
+  /// The ratios of the legs are not coprime, i.e., they share a common
+  /// divisor greater than one.
+  #[error("the leg ratios are not coprime")]
+  RatiosNotCoprime,
+  /// The legs refer to more than one underlying.
+  #[error("legs refer to different underlyings ({0} and {1})")]
+  MixedUnderlyings(String, String),
+  /// The position intent of the leg at the given index contradicts its
+  /// side.
+  #[error("the position intent of leg {0} contradicts its side")]
+  IntentSideMismatch(usize),
+}
+
+
+/// A helper for initializing [`MultiLegReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MultiLegReqInit {
+  /// See `MultiLegReq::type_`.
+  pub type_: Type,
+  /// See `MultiLegReq::time_in_force`.
+  pub time_in_force: TimeInForce,
+  /// See `MultiLegReq::limit_price`.
+  pub limit_price: Option<Num>,
+  /// See `MultiLegReq::client_order_id`.
+  pub client_order_id: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl MultiLegReqInit {
+  /// Create a [`MultiLegReq`] from a `MultiLegReqInit`, validating the
+  /// provided legs.
+  ///
+  /// The legs have to refer to a single underlying, their ratios have
+  /// to be coprime, and there can be at most four of them.
+  pub fn init<L>(self, legs: L, quantity: impl Into<Num>) -> Result<MultiLegReq, MultiLegError>
+  where
+    L: IntoIterator<Item = Leg>,
+  {
+    let legs = legs.into_iter().collect::<Vec<_>>();
+    if legs.len() < 2 {
+      return Err(MultiLegError::TooFewLegs)
+    }
+    if legs.len() > MAX_LEGS {
+      return Err(MultiLegError::TooManyLegs(legs.len()))
+    }
+
+    let underlying = legs[0].underlying();
+    let mut divisor = 0;
+    for (idx, leg) in legs.iter().enumerate() {
+      if leg.ratio == 0 {
+        return Err(MultiLegError::ZeroRatio(idx))
+      }
+      let leg_underlying = leg.underlying();
+      if leg_underlying != underlying {
+        return Err(MultiLegError::MixedUnderlyings(underlying, leg_underlying))
+      }
+      if let Some(intent) = leg.position_intent {
+        if intent.side() != leg.side {
+          return Err(MultiLegError::IntentSideMismatch(idx))
+        }
+      }
+      divisor = gcd(divisor, leg.ratio);
+    }
+
+    if divisor != 1 {
+      return Err(MultiLegError::RatiosNotCoprime)
+    }
+
+    let req = MultiLegReq {
+      legs,
+      quantity: quantity.into(),
+      type_: self.type_,
+      time_in_force: self.time_in_force,
+      limit_price: self.limit_price,
+      client_order_id: self.client_order_id,
+      _non_exhaustive: (),
+    };
+    Ok(req)
+  }
+}
+
+
+/// A POST request to be made to the /v2/orders endpoint for creating a
+/// multi-leg order.
+///
+/// Objects of this type are best created by means of
+/// [`MultiLegReqInit::init`], which validates the legs.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct MultiLegReq {
+  /// The legs of the order.
+  #[serde(rename = "legs")]
+  pub legs: Vec<Leg>,
+  /// The quantity of the overall order. The quantity of each leg is
+  /// this value multiplied by the leg's ratio.
+  #[serde(rename = "qty")]
+  pub quantity: Num,
+  /// The type of the order.
+  #[serde(rename = "type")]
+  pub type_: Type,
+  /// How long the order will be valid.
+  #[serde(rename = "time_in_force")]
+  pub time_in_force: TimeInForce,
+  /// The limit price, i.e., the net debit (positive) or credit
+  /// (negative) of the order.
+  #[serde(rename = "limit_price", skip_serializing_if = "Option::is_none")]
+  pub limit_price: Option<Num>,
+  /// Client unique order ID (free form string).
+  #[serde(rename = "client_order_id", skip_serializing_if = "Option::is_none")]
+  pub client_order_id: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+impl MultiLegReq {
+  /// Map the legs of an order, as returned in response to this
+  /// request, back to the request's legs.
+  ///
+  /// The result is index-aligned with `self.legs`. A leg is `None` if
+  /// no matching order leg is found.
+  pub fn map_legs<'o>(&self, order: &'o Order) -> Vec<Option<&'o Order>> {
+    let mut used = vec![false; order.legs.len()];
+    self
+      .legs
+      .iter()
+      .map(|leg| {
+        let idx = order.legs.iter().enumerate().position(|(idx, order)| {
+          !used[idx] && order.symbol == leg.symbol && order.side == leg.side
+        })?;
+        used[idx] = true;
+        Some(&order.legs[idx])
+      })
+      .collect()
+  }
+}
+
+
 /// A PATCH request to be made to the /v2/orders/{order-id} endpoint.
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ChangeReq {
@@ -634,6 +930,14 @@ pub struct Order {
   /// take profit part of a bracket-style order.
   #[serde(rename = "legs", deserialize_with = "vec_from_str")]
   pub legs: Vec<Order>,
+  /// The intent of the order with respect to the affected position, if
+  /// any.
+  #[serde(rename = "position_intent")]
+  pub position_intent: Option<PositionIntent>,
+  /// For a leg of a multi-leg order, the ratio of the leg's quantity to
+  /// the quantity of the overall order.
+  #[serde(rename = "ratio_qty")]
+  pub ratio_quantity: Option<Num>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   #[serde(skip)]
@@ -726,6 +1030,49 @@ Endpoint! {
 }
 
 
+Endpoint! {
+  /// The representation of a POST request to the /v2/orders endpoint
+  /// for creating a multi-leg order.
+  pub CreateMultiLeg(MultiLegReq),
+  Ok => Order, [
+    /// The order was submitted successfully.
+    /* 200 */ OK,
+  ],
+  Err => CreateMultiLegError, [
+    /// Some data in the request was invalid.
+    /* 422 */ UNPROCESSABLE_ENTITY => InvalidInput,
+  ]
+
+  #[inline]
+  fn method() -> Method {
+    Method::POST
+  }
+
+  #[inline]
+  fn path(_input: &Self::Input) -> Str {
+    "/v2/orders".into()
+  }
+
+  fn body(input: &Self::Input) -> Result<Option<Bytes>, Self::ConversionError> {
+    #[derive(Serialize)]
+    struct MultiLeg<'r> {
+      #[serde(rename = "order_class")]
+      class: Class,
+      #[serde(flatten)]
+      request: &'r MultiLegReq,
+    }
+
+    let request = MultiLeg {
+      class: Class::MultiLeg,
+      request: input,
+    };
+    let json = to_json(&request)?;
+    let bytes = Bytes::from(json);
+    Ok(Some(bytes))
+  }
+}
+
+
 Endpoint! {
   /// The representation of a PATCH request to the /v2/orders/{order-id}
   /// endpoint.
@@ -800,10 +1147,10 @@ EndpointNoParse! {
 mod tests {
   use super::*;
 
-  use std::str::FromStr as _;
-
   use futures::TryFutureExt;
 
+  use http_endpoint::Endpoint as _;
+
   use serde_json::from_slice as from_json;
 
   use test_log::test;
@@ -979,6 +1326,227 @@ mod tests {
     assert_eq!(from_json::<CreateReq>(&json).unwrap(), request);
   }
 
+  /// Check that we can serialize and deserialize a [`CreateReq`] for
+  /// an option contract, including its position intent.
+  #[test]
+  fn serialize_deserialize_option_order_request() {
+    let request = CreateReqInit {
+      type_: Type::Limit,
+      limit_price: Some(Num::new(345, 100)),
+      position_intent: Some(PositionIntent::SellToOpen),
+      ..Default::default()
+    }
+    .init("AAPL240119C00190000", Side::Sell, Amount::quantity(1));
+
+    let json = to_json(&request).unwrap();
+    let value = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+    assert_eq!(value["position_intent"], "sell_to_open");
+    assert_eq!(from_json::<CreateReq>(&json).unwrap(), request);
+  }
+
+  /// Check that we can map a [`PositionIntent`] to the implied order
+  /// side.
+  #[test]
+  fn position_intent_side() {
+    assert_eq!(PositionIntent::BuyToOpen.side(), Side::Buy);
+    assert_eq!(PositionIntent::BuyToClose.side(), Side::Buy);
+    assert_eq!(PositionIntent::SellToOpen.side(), Side::Sell);
+    assert_eq!(PositionIntent::SellToClose.side(), Side::Sell);
+  }
+
+  /// Verify that we can deserialize an option order.
+  #[test]
+  fn deserialize_option_order() {
+    let json = br#"{
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2024-01-02T15:04:05Z",
+    "updated_at": "2024-01-02T15:04:05Z",
+    "submitted_at": "2024-01-02T15:04:05Z",
+    "filled_at": null,
+    "expired_at": null,
+    "canceled_at": null,
+    "failed_at": null,
+    "asset_id": "6e58f870-fe73-4583-81e4-b9a37892c36f",
+    "symbol": "AAPL240119C00190000",
+    "asset_class": "us_option",
+    "qty": "1",
+    "filled_qty": "0",
+    "type": "limit",
+    "order_class": "simple",
+    "side": "sell",
+    "position_intent": "sell_to_open",
+    "time_in_force": "day",
+    "limit_price": "3.45",
+    "stop_price": null,
+    "filled_avg_price": null,
+    "status": "accepted",
+    "extended_hours": false,
+    "legs": null
+}"#;
+
+    let order = from_json::<Order>(json).unwrap();
+    assert_eq!(order.symbol, "AAPL240119C00190000");
+    assert_eq!(order.asset_class, asset::Class::UsOption);
+    assert_eq!(order.side, Side::Sell);
+    assert_eq!(order.position_intent, Some(PositionIntent::SellToOpen));
+    assert_eq!(order.limit_price, Some(Num::new(345, 100)));
+  }
+
+  /// Check that we can create and serialize a multi-leg order request.
+  #[test]
+  fn serialize_multi_leg_request() {
+    let buy = LegInit {
+      position_intent: Some(PositionIntent::BuyToOpen),
+      ..Default::default()
+    };
+    let sell = LegInit {
+      position_intent: Some(PositionIntent::SellToOpen),
+      ..Default::default()
+    };
+    let legs = [
+      buy.init("AAPL240119C00190000", 1, Side::Buy),
+      sell.init("AAPL240119C00195000", 1, Side::Sell),
+    ];
+    let request = MultiLegReqInit {
+      type_: Type::Limit,
+      limit_price: Some(Num::new(19, 10)),
+      ..Default::default()
+    }
+    .init(legs, 2)
+    .unwrap();
+
+    let body = CreateMultiLeg::body(&request).unwrap().unwrap();
+    let expected = br#"{"order_class":"mleg","legs":[{"symbol":"AAPL240119C00190000","ratio_qty":"1","side":"buy","position_intent":"buy_to_open"},{"symbol":"AAPL240119C00195000","ratio_qty":"1","side":"sell","position_intent":"sell_to_open"}],"qty":"2","type":"limit","time_in_force":"day","limit_price":"1.9"}"#;
+    assert_eq!(body, &expected[..]);
+  }
+
+  /// Check that we reject invalid multi-leg order requests.
+  #[test]
+  fn validate_multi_leg_request() {
+    let init = || MultiLegReqInit::default();
+    let leg = |symbol: &str, ratio, side| LegInit::default().init(symbol, ratio, side);
+
+    let err = init()
+      .init([leg("AAPL240119C00190000", 1, Side::Buy)], 1)
+      .unwrap_err();
+    assert_eq!(err, MultiLegError::TooFewLegs);
+
+    let legs = (0..5).map(|_| leg("AAPL240119C00190000", 1, Side::Buy));
+    let err = init().init(legs, 1).unwrap_err();
+    assert_eq!(err, MultiLegError::TooManyLegs(5));
+
+    let legs = [
+      leg("AAPL240119C00190000", 1, Side::Buy),
+      leg("SPY240119C00470000", 1, Side::Sell),
+    ];
+    let err = init().init(legs, 1).unwrap_err();
+    assert_eq!(
+      err,
+      MultiLegError::MixedUnderlyings("AAPL".to_string(), "SPY".to_string())
+    );
+
+    let legs = [
+      leg("AAPL240119C00190000", 2, Side::Buy),
+      leg("AAPL240119C00195000", 4, Side::Sell),
+    ];
+    let err = init().init(legs, 1).unwrap_err();
+    assert_eq!(err, MultiLegError::RatiosNotCoprime);
+
+    let legs = [
+      leg("AAPL240119C00190000", 1, Side::Buy),
+      leg("AAPL240119C00195000", 0, Side::Sell),
+    ];
+    let err = init().init(legs, 1).unwrap_err();
+    assert_eq!(err, MultiLegError::ZeroRatio(1));
+
+    let legs = [
+      leg("AAPL240119C00190000", 1, Side::Buy),
+      LegInit {
+        position_intent: Some(PositionIntent::BuyToClose),
+        ..Default::default()
+      }
+      .init("AAPL240119C00195000", 1, Side::Sell),
+    ];
+    let err = init().init(legs, 1).unwrap_err();
+    assert_eq!(err, MultiLegError::IntentSideMismatch(1));
+
+    // A ratio spread with coprime ratios, as well as a leg for the
+    // underlying itself, are fine.
+    let legs = [
+      leg("AAPL240119C00190000", 2, Side::Buy),
+      leg("AAPL240119C00195000", 3, Side::Sell),
+      leg("AAPL", 100, Side::Sell),
+    ];
+    let request = init().init(legs, 1).unwrap();
+    assert_eq!(request.legs.len(), 3);
+  }
+
+  /// Check that we can map the legs of a multi-leg order response back
+  /// to the legs of the request.
+  #[test]
+  fn map_multi_leg_response() {
+    let leg = |symbol: &str, side| LegInit::default().init(symbol, 1, side);
+    let legs = [
+      leg("SPY240119C00470000", Side::Buy),
+      leg("SPY240119P00470000", Side::Buy),
+    ];
+    let request = MultiLegReqInit::default().init(legs, 1).unwrap();
+
+    let order = |symbol: &str, side: &str| {
+      format!(
+        r#"{{
+    "id": "904837e3-3b76-47ec-b432-046db621571b",
+    "client_order_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "created_at": "2024-01-02T15:04:05Z",
+    "asset_id": "904837e3-3b76-47ec-b432-046db621571b",
+    "asset_class": "us_option",
+    "symbol": "{symbol}",
+    "qty": "1",
+    "ratio_qty": "1",
+    "filled_qty": "0",
+    "type": "market",
+    "order_class": "mleg",
+    "side": "{side}",
+    "time_in_force": "day",
+    "status": "accepted",
+    "extended_hours": false,
+    "legs": null
+}}"#
+      )
+    };
+    let json = format!(
+      r#"{{
+    "id": "2d9c9d3e-4a9a-4b3c-9e5b-6a1f3a2b1c0d",
+    "client_order_id": "2d9c9d3e-4a9a-4b3c-9e5b-6a1f3a2b1c0d",
+    "created_at": "2024-01-02T15:04:05Z",
+    "asset_id": "00000000-0000-0000-0000-000000000000",
+    "asset_class": "",
+    "symbol": "",
+    "qty": "1",
+    "filled_qty": "0",
+    "type": "market",
+    "order_class": "mleg",
+    "side": "buy",
+    "time_in_force": "day",
+    "status": "accepted",
+    "extended_hours": false,
+    "legs": [{}, {}]
+}}"#,
+      order("SPY240119P00470000", "buy"),
+      order("SPY240119C00470000", "buy"),
+    );
+
+    let order = from_json::<Order>(json.as_bytes()).unwrap();
+    assert_eq!(order.class, Class::MultiLeg);
+
+    let legs = request.map_legs(&order);
+    assert_eq!(legs.len(), 2);
+    assert_eq!(legs[0].unwrap().symbol, "SPY240119C00470000");
+    assert_eq!(legs[0].unwrap().ratio_quantity, Some(Num::from(1)));
+    assert_eq!(legs[1].unwrap().symbol, "SPY240119P00470000");
+  }
+
   /// Check that we can serialize and deserialize a [`ChangeReq`].
   #[test]
   fn serialize_deserialize_change_request() {
@@ -1,6 +1,9 @@
 // Copyright (C) 2019-2024 The apca Developers
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use chrono::DateTime;
+use chrono::Utc;
+
 use serde::Deserialize;
 use serde::Serialize;
 use serde_urlencoded::to_string as to_query;
@@ -45,6 +48,12 @@ pub struct ListReq {
   /// to 50 and max is 500.
   #[serde(rename = "limit")]
   pub limit: Option<usize>,
+  /// Only list orders submitted after this time (exclusive).
+  #[serde(rename = "after", skip_serializing_if = "Option::is_none")]
+  pub after: Option<DateTime<Utc>>,
+  /// Only list orders submitted until this time (exclusive).
+  #[serde(rename = "until", skip_serializing_if = "Option::is_none")]
+  pub until: Option<DateTime<Utc>>,
   /// If false the result will not roll up multi-leg orders under the
   /// legs field of the primary order.
   #[serde(rename = "nested")]
@@ -61,6 +70,8 @@ impl Default for ListReq {
       symbols: Vec::new(),
       status: Status::Open,
       limit: None,
+      after: None,
+      until: None,
       // Nested orders merely appear as legs in each order being
       // returned. As such, having them included is very non-intrusive
       // and should be a reasonable default.
@@ -144,6 +155,7 @@ mod tests {
       symbols: vec!["ABC".into()],
       status: Status::Closed,
       limit: Some(42),
+      after: Some(DateTime::from_timestamp(1614009104, 0).unwrap()),
       nested: true,
       ..Default::default()
     };
@@ -9,6 +9,7 @@ use num_decimal::Num;
 
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::from_slice as from_json;
 
 use crate::api::v2::asset;
 use crate::api::v2::order;
@@ -148,10 +149,56 @@ Endpoint! {
 }
 
 
+EndpointNoParse! {
+  /// The representation of a POST request to the
+  /// /v2/positions/{symbol}/exercise endpoint.
+  ///
+  /// The symbol identifies an option contract held in a long position,
+  /// either by its OCC symbol or by its ID.
+  pub Exercise(asset::Symbol),
+  Ok => (), [
+    /// The exercise request was submitted successfully.
+    /* 200 */ OK,
+  ],
+  Err => ExerciseError, [
+    /// No position was found for the given symbol/contract ID.
+    /* 404 */ NOT_FOUND => NotFound,
+    /// The position cannot be exercised, e.g., because it is not a
+    /// long option position.
+    /* 422 */ UNPROCESSABLE_ENTITY => NotExercisable,
+  ]
+
+  #[inline]
+  fn method() -> Method {
+    Method::POST
+  }
+
+  #[inline]
+  fn path(input: &Self::Input) -> Str {
+    format!("/v2/positions/{input}/exercise").into()
+  }
+
+  #[inline]
+  fn parse(_body: &[u8]) -> Result<Self::Output, Self::ConversionError> {
+    Ok(())
+  }
+
+  fn parse_err(body: &[u8]) -> Result<Self::ApiError, Vec<u8>> {
+    from_json::<Self::ApiError>(body).map_err(|_| body.to_vec())
+  }
+}
+
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  use http::Request;
+  use http::Response;
+
+  use hyper::body::Bytes;
+  use hyper::Body;
+
   use serde_json::from_str as from_json;
   use serde_json::to_string as to_json;
 
@@ -284,6 +331,58 @@ mod tests {
     assert_eq!(pos.quantity_available, Num::from(-24));
   }
 
+  /// Check that we can parse an option position.
+  #[test]
+  fn parse_option_position() {
+    let response = r#"{
+      "asset_id":"6e58f870-fe73-4583-81e4-b9a37892c36f",
+      "symbol":"AAPL240119C00190000",
+      "exchange":"",
+      "asset_class":"us_option",
+      "asset_marginable":true,
+      "qty":"-2",
+      "qty_available":"0",
+      "avg_entry_price":"3.4",
+      "side":"short",
+      "market_value":"-570",
+      "cost_basis":"-680",
+      "unrealized_pl":"110",
+      "unrealized_plpc":"0.1617647058823529",
+      "unrealized_intraday_pl":"110",
+      "unrealized_intraday_plpc":"0.1617647058823529",
+      "current_price":"2.85",
+      "lastday_price":"3.4",
+      "change_today":"-0.1617647058823529"
+    }"#;
+
+    let pos = from_json::<Position>(response).unwrap();
+    assert_eq!(pos.symbol, "AAPL240119C00190000");
+    assert_eq!(pos.asset_class, asset::Class::UsOption);
+    assert_eq!(pos.side, Side::Short);
+    assert_eq!(pos.quantity, Num::from(2));
+    assert_eq!(pos.current_price, Some(Num::new(285, 100)));
+  }
+
+  /// Check that we issue the expected request when exercising an
+  /// option position.
+  #[test(tokio::test)]
+  async fn exercise_position() {
+    let transport = |request: Request<Bytes>| {
+      assert_eq!(request.method(), Method::POST);
+      assert_eq!(
+        request.uri().path(),
+        "/v2/positions/AAPL240119C00190000/exercise"
+      );
+      Response::new(Body::empty())
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+
+    let symbol = asset::Symbol::Sym("AAPL240119C00190000".to_string());
+    let () = client.issue::<Exercise>(&symbol).await.unwrap();
+  }
+
   /// Check that we can retrieve an open position, if one exists.
   #[test(tokio::test)]
   async fn retrieve_position() {
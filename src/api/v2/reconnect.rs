@@ -0,0 +1,761 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use futures::stream::FusedStream;
+use futures::FutureExt as _;
+use futures::Stream;
+
+use tracing::debug;
+
+use crate::api::v2::clock;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::api::v2::updates::OrderStatus;
+use crate::api::v2::updates::OrderUpdate;
+use crate::api::v2::updates::OrderUpdates;
+use crate::reconnect::Connection;
+use crate::reconnect::Established;
+use crate::reconnect::Polled;
+use crate::reconnect::ReconnectFuture;
+use crate::reconnect::Reconnected;
+use crate::retry::reconnect;
+use crate::subscribable::Subscribable;
+use crate::Client;
+use crate::Error;
+use crate::RequestError;
+use crate::RetryPolicy;
+
+
+type UpdateStream = <OrderUpdates as Subscribable>::Stream;
+type UpdateSubscription = <OrderUpdates as Subscribable>::Subscription;
+type Item = Result<Event, Error>;
+type Orders = Result<Vec<order::Order>, Error>;
+
+
+/// The maximum number of orders to retrieve per request when
+/// reconciling.
+const RECONCILE_PAGE_SIZE: usize = 500;
+
+
+/// A description of a period during which no connection to the
+/// server existed and, hence, order updates may have been missed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Gap {
+  /// The time at which the loss of the connection was detected.
+  pub disconnected_at: DateTime<Utc>,
+  /// The time at which the connection was re-established.
+  pub reconnected_at: DateTime<Utc>,
+  /// The number of connection attempts it took to re-establish the
+  /// connection.
+  pub attempts: usize,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// An event as emitted by a [`ReconnectingOrderUpdates`] stream.
+#[derive(Debug)]
+#[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
+pub enum Event {
+  /// An order update as received over the websocket channel.
+  Update(OrderUpdate),
+  /// The connection was lost and has since been re-established.
+  ///
+  /// This event is followed by [`Event::Reconciled`] events for all
+  /// orders whose status changed in the meantime.
+  Reconnected(Gap),
+  /// A synthetic order update for an order whose status changed while
+  /// no connection existed.
+  ///
+  /// The update's `event` is derived from the order's current status.
+  /// Because the order's state is inquired after the connection was
+  /// re-established, the same change may additionally be reported by
+  /// a regular [`Event::Update`] shortly after.
+  Reconciled(OrderUpdate),
+}
+
+
+/// Derive the order update event corresponding to an order status.
+fn event(status: order::Status) -> OrderStatus {
+  match status {
+    order::Status::New => OrderStatus::New,
+    order::Status::Replaced => OrderStatus::Replaced,
+    order::Status::PartiallyFilled => OrderStatus::PartialFill,
+    order::Status::Filled => OrderStatus::Filled,
+    order::Status::DoneForDay => OrderStatus::DoneForDay,
+    order::Status::Canceled => OrderStatus::Canceled,
+    order::Status::Expired => OrderStatus::Expired,
+    // None of these states have a dedicated event. They all describe
+    // orders that were received but are not yet accepted for
+    // execution.
+    order::Status::Accepted
+    | order::Status::PendingNew
+    | order::Status::AcceptedForBidding
+    | order::Status::Held => OrderStatus::PendingNew,
+    order::Status::PendingCancel => OrderStatus::PendingCancel,
+    order::Status::PendingReplace => OrderStatus::PendingReplace,
+    order::Status::Stopped => OrderStatus::Stopped,
+    order::Status::Rejected => OrderStatus::Rejected,
+    order::Status::Suspended => OrderStatus::Suspended,
+    order::Status::Calculated => OrderStatus::Calculated,
+    order::Status::Unknown => OrderStatus::Unknown,
+  }
+}
+
+
+/// Convert an error as reported by a request issued for the purpose of
+/// reconciliation into an [`Error`].
+fn request_error<E>(what: &str, err: RequestError<E>) -> Error
+where
+  E: Display + StdError + 'static,
+{
+  let message = match err.source() {
+    Some(source) => format!("failed to {what} for reconciliation: {err}: {source}"),
+    None => format!("failed to {what} for reconciliation: {err}"),
+  };
+  Error::Str(message.into())
+}
+
+
+/// Retrieve all orders matching `request`, following pages backwards
+/// in time.
+async fn list_all(
+  client: &Client,
+  mut request: orders::ListReq,
+) -> Result<Vec<order::Order>, Error> {
+  let mut orders = Vec::new();
+  let mut seen = HashSet::new();
+  request.limit = Some(RECONCILE_PAGE_SIZE);
+
+  loop {
+    let page = client
+      .issue::<orders::List>(&request)
+      .await
+      .map_err(|err| request_error("list orders", err))?;
+    let count = page.len();
+    // Orders are reported most recently submitted first. Multiple
+    // orders may share the same submission time, though (e.g., legs
+    // carry that of their parent), and a page may end in between
+    // them. Hence, the next page includes orders submitted at the time
+    // of the oldest one we got and we skip the ones already seen.
+    let oldest = page.iter().filter_map(|order| order.submitted_at).min();
+    let len = orders.len();
+    let () = orders.extend(page.into_iter().filter(|order| seen.insert(order.id)));
+
+    match oldest {
+      Some(oldest) if count >= RECONCILE_PAGE_SIZE && orders.len() > len => {
+        request.until = Some(oldest + Duration::nanoseconds(1))
+      },
+      _ => break,
+    }
+  }
+  Ok(orders)
+}
+
+
+/// Retrieve the orders to reconcile our view of the world with.
+///
+/// These are all orders submitted after `since` as well as the ones
+/// identified by `open`, which we last knew to be open.
+async fn list_orders(
+  client: &Client,
+  since: DateTime<Utc>,
+  open: Vec<order::Id>,
+) -> Result<Vec<order::Order>, Error> {
+  let request = orders::ListReq {
+    status: orders::Status::All,
+    after: Some(since),
+    // We want to see legs as individual orders, as that is how they
+    // are reported as part of order updates as well.
+    nested: false,
+    ..Default::default()
+  };
+  let mut orders = list_all(client, request).await?;
+
+  // Open orders submitted before `since` may have changed as well, but
+  // they are not covered by the above.
+  for id in open {
+    if !orders.iter().any(|order| order.id == id) {
+      let order = client
+        .issue::<order::Get>(&id)
+        .await
+        .map_err(|err| request_error("retrieve order", err))?;
+      let () = orders.push(order);
+    }
+  }
+  Ok(orders)
+}
+
+
+/// Create a future re-establishing the connection to the server and
+/// retrieving the orders to reconcile.
+fn reestablish(
+  client: &Client,
+  policy: RetryPolicy,
+  since: DateTime<Utc>,
+  known: &HashMap<order::Id, order::Status>,
+) -> ReconnectFuture<UpdateStream, UpdateSubscription, Orders> {
+  let client = client.clone();
+  let open = known
+    .iter()
+    .filter(|(_id, status)| !status.is_terminal())
+    .map(|(id, _status)| *id)
+    .collect::<Vec<_>>();
+
+  async move {
+    let api_info = client.api_info();
+    let ((stream, subscription), attempts) =
+      reconnect(&policy, || OrderUpdates::connect(api_info)).await?;
+    let orders = list_orders(&client, since, open).await;
+
+    let established = Established {
+      stream,
+      subscription,
+      attempts,
+      extra: orders,
+    };
+    Ok(established)
+  }
+  .boxed()
+}
+
+
+/// A stream of order updates that transparently re-establishes the
+/// connection to the server when it is lost.
+///
+/// After reconnecting, the stream emits an [`Event::Reconnected`]
+/// event and reconciles its view of orders with the server: for every
+/// order whose status changed since the last update seen, a synthetic
+/// [`Event::Reconciled`] update is emitted. Reconciliation covers all
+/// orders submitted since then as well as all orders last known to be
+/// open, including those already open when the stream got created. If
+/// it fails, an error is reported, but the stream continues.
+///
+/// The stream only ends once re-establishing the connection failed for
+/// good, as governed by the [`RetryPolicy`] in use, in which case the
+/// last error is reported before.
+pub struct ReconnectingOrderUpdates {
+  /// The client used for connecting and reconciliation.
+  client: Client,
+  /// The policy governing how to re-establish the connection.
+  policy: RetryPolicy,
+  /// The time of the most recent order update seen, as reported by the
+  /// server.
+  since: DateTime<Utc>,
+  /// The last known status of each order seen.
+  known: HashMap<order::Id, order::Status>,
+  /// Items received but not yet emitted.
+  buffer: VecDeque<Item>,
+  /// The connection to the server.
+  ///
+  /// The subscription is not used, but it keeps the connection alive.
+  connection: Connection<UpdateStream, UpdateSubscription, Orders>,
+}
+
+impl ReconnectingOrderUpdates {
+  /// Connect to the server to stream order updates, using the provided
+  /// client's API information.
+  ///
+  /// The initial connection attempt is not retried: any error is
+  /// reported directly.
+  pub async fn connect(client: Client, policy: RetryPolicy) -> Result<Self, Error> {
+    // We only ever compare against time stamps reported by the server,
+    // so we start out with its notion of the current time. We inquire
+    // it before subscribing so as to not miss any updates.
+    let since = client
+      .issue::<clock::Get>(&())
+      .await
+      .map_err(|err| request_error("inquire server time", err))?
+      .current;
+    let (stream, subscription) = client.subscribe::<OrderUpdates>().await?;
+
+    // Orders that are open already may change while we are
+    // disconnected, so we have to know about them for reconciliation.
+    // Updates received in the meantime are buffered by the stream and
+    // will be recorded once polled.
+    let request = orders::ListReq {
+      status: orders::Status::Open,
+      nested: false,
+      ..Default::default()
+    };
+    let known = list_all(&client, request)
+      .await?
+      .into_iter()
+      .map(|order| (order.id, order.status))
+      .collect();
+
+    let slf = Self {
+      client,
+      policy,
+      since,
+      known,
+      buffer: VecDeque::new(),
+      connection: Connection::new(stream, subscription),
+    };
+    Ok(slf)
+  }
+
+  /// Record the state of an order as conveyed by an update.
+  fn record(&mut self, order: &order::Order) {
+    let _prev = self.known.insert(order.id, order.status);
+    if let Some(updated_at) = order.updated_at {
+      self.since = self.since.max(updated_at);
+    }
+  }
+
+  /// Handle the re-establishment of the connection.
+  fn reconnected(&mut self, reconnected: Reconnected<Orders>) {
+    let Reconnected {
+      disconnected_at,
+      reconnected_at,
+      attempts,
+      extra: orders,
+    } = reconnected;
+
+    let gap = Gap {
+      disconnected_at,
+      reconnected_at,
+      attempts,
+      _non_exhaustive: (),
+    };
+    self.buffer.push_back(Ok(Event::Reconnected(gap)));
+
+    match orders {
+      Ok(orders) => self.reconcile(orders),
+      Err(err) => self.buffer.push_back(Err(err)),
+    }
+  }
+
+  /// Emit synthetic updates for all orders whose status changed
+  /// unbeknownst to us.
+  fn reconcile(&mut self, orders: Vec<order::Order>) {
+    let mut changed = orders
+      .into_iter()
+      .filter(|order| {
+        let updated = order
+          .updated_at
+          .map(|updated_at| updated_at >= self.since)
+          .unwrap_or(false);
+        updated && self.known.get(&order.id) != Some(&order.status)
+      })
+      .collect::<Vec<_>>();
+    let () = changed.sort_by_key(|order| order.updated_at);
+
+    debug!(count = changed.len(), "reconciled orders");
+
+    for order in changed {
+      let () = self.record(&order);
+      let update = OrderUpdate {
+        event: event(order.status),
+        order,
+      };
+      self.buffer.push_back(Ok(Event::Reconciled(update)));
+    }
+  }
+}
+
+impl Debug for ReconnectingOrderUpdates {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("ReconnectingOrderUpdates")
+      .field("since", &self.since)
+      .field("state", &self.connection.state())
+      .finish()
+  }
+}
+
+impl Stream for ReconnectingOrderUpdates {
+  type Item = Item;
+
+  fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::get_mut(self);
+
+    loop {
+      if let Some(item) = this.buffer.pop_front() {
+        break Poll::Ready(Some(item))
+      }
+
+      let client = &this.client;
+      let policy = this.policy;
+      let since = this.since;
+      let known = &this.known;
+      let polled = this
+        .connection
+        .poll_next(ctx, || reestablish(client, policy, since, known));
+
+      match polled {
+        Poll::Ready(Some(Ok(Polled::Message(update)))) => {
+          let () = this.record(&update.order);
+          break Poll::Ready(Some(Ok(Event::Update(update))))
+        },
+        Poll::Ready(Some(Ok(Polled::Reconnected(reconnected)))) => {
+          let () = this.reconnected(reconnected);
+        },
+        Poll::Ready(Some(Err(err))) => break Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => break Poll::Ready(None),
+        Poll::Pending => break Poll::Pending,
+      }
+    }
+  }
+}
+
+impl FusedStream for ReconnectingOrderUpdates {
+  #[inline]
+  fn is_terminated(&self) -> bool {
+    self.buffer.is_empty() && self.connection.is_terminated()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+  use std::time::Duration;
+
+  use chrono::SecondsFormat;
+
+  use futures::channel::oneshot::channel;
+  use futures::SinkExt as _;
+  use futures::StreamExt as _;
+
+  use http::Request;
+  use http::Response;
+
+  use hyper::body::Bytes;
+  use hyper::Body;
+
+  use test_log::test;
+
+  use url::form_urlencoded;
+
+  use websocket_util::test::WebSocketStream;
+  use websocket_util::tungstenite::Error as WebSocketError;
+  use websocket_util::tungstenite::Message;
+
+  use crate::websocket::test::mock_api_info;
+  use crate::websocket::test::mock_server_multi;
+  use crate::websocket::test::AUTH_REQ;
+  use crate::websocket::test::UPDATES_AUTH_RESP;
+  use crate::ApiInfo;
+
+
+  const STREAM_REQ: &str = r#"{"action":"listen","data":{"streams":["trade_updates"]}}"#;
+  const STREAM_RESP: &str = r#"{"stream":"listening","data":{"streams":["trade_updates"]}}"#;
+
+  const ORDER_A: &str = "11111111-2222-3333-4444-555555555551";
+  const ORDER_B: &str = "11111111-2222-3333-4444-555555555552";
+  const ORDER_C: &str = "11111111-2222-3333-4444-555555555553";
+  const ORDER_D: &str = "11111111-2222-3333-4444-555555555554";
+
+
+  /// Create the JSON representation of an order with the given ID and
+  /// status, updated the given number of seconds from now.
+  fn order(id: &str, status: &str, offset: i64) -> String {
+    order_submitted_at(id, status, offset, "2021-12-09T19:48:46.175261379Z")
+  }
+
+  /// Create the JSON representation of an order with the given ID and
+  /// status, submitted at the given time and updated the given number
+  /// of seconds from now.
+  fn order_submitted_at(id: &str, status: &str, offset: i64, submitted_at: &str) -> String {
+    let updated_at =
+      (Utc::now() + chrono::Duration::seconds(offset)).to_rfc3339_opts(SecondsFormat::Micros, true);
+    format!(
+      r#"{{
+      "asset_class":"us_equity","asset_id":"11111111-2222-3333-4444-555555555555",
+      "canceled_at":null,"client_order_id":"{id}",
+      "created_at":"2021-12-09T19:48:46.176628398Z","expired_at":null,
+      "extended_hours":false,"failed_at":null,"filled_at":null,
+      "filled_avg_price":null,"filled_qty":"0","hwm":null,
+      "id":"{id}","legs":null,"limit_price":"1",
+      "notional":null,"order_class":"simple","order_type":"limit","qty":"1",
+      "replaced_at":null,"replaced_by":null,"replaces":null,"side":"buy",
+      "status":"{status}","stop_price":null,"submitted_at":"{submitted_at}",
+      "symbol":"AAPL","time_in_force":"day","trail_percent":null,"trail_price":null,
+      "type":"limit","updated_at":"{updated_at}"
+    }}"#
+    )
+  }
+
+  /// Create the JSON representation of the market clock at the current
+  /// time.
+  fn clock() -> String {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+    format!(r#"{{"timestamp":"{now}","is_open":true,"next_open":"{now}","next_close":"{now}"}}"#)
+  }
+
+  /// Create the JSON representation of an order update message.
+  fn update(event: &str, order: &str) -> String {
+    format!(r#"{{"stream":"trade_updates","data":{{"event":"{event}","order":{order}}}}}"#)
+  }
+
+  /// Perform the authentication and listen handshake on the server
+  /// side.
+  async fn handshake(stream: &mut WebSocketStream) -> Result<(), WebSocketError> {
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(AUTH_REQ.to_string()),
+    );
+    stream
+      .send(Message::Text(UPDATES_AUTH_RESP.to_string()))
+      .await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(STREAM_REQ.to_string()),
+    );
+    stream.send(Message::Text(STREAM_RESP.to_string())).await
+  }
+
+
+  /// Check that we derive the expected order update events from order
+  /// states.
+  #[test]
+  fn status_to_event() {
+    assert_eq!(event(order::Status::New), OrderStatus::New);
+    assert_eq!(
+      event(order::Status::PartiallyFilled),
+      OrderStatus::PartialFill
+    );
+    assert_eq!(event(order::Status::Filled), OrderStatus::Filled);
+    assert_eq!(event(order::Status::Held), OrderStatus::PendingNew);
+  }
+
+  /// Check that we re-establish a lost connection and reconcile orders
+  /// that changed while disconnected.
+  #[test(tokio::test)]
+  async fn reconnect_and_reconcile() {
+    let (sender0, receiver0) = channel();
+    let (sender1, receiver1) = channel::<()>();
+    let mut receivers = vec![receiver0, receiver1].into_iter();
+
+    let addr = mock_server_multi(move |idx, mut stream| {
+      let receiver = receivers.next().unwrap();
+
+      async move {
+        let () = handshake(&mut stream).await?;
+        // Wait until the connection was established before sending
+        // any additional messages.
+        let () = receiver.await.unwrap();
+
+        if idx == 0 {
+          let update = update("new", &order(ORDER_A, "new", 1));
+          stream.send(Message::Text(update)).await?;
+          stream.send(Message::Close(None)).await?;
+        } else {
+          let update = update("new", &order(ORDER_C, "new", 10));
+          stream.send(Message::Text(update)).await?;
+          // Keep the connection open until the client goes away.
+          while stream.next().await.is_some() {}
+        }
+        Ok(())
+      }
+    })
+    .await;
+
+    let transport = |request: Request<Bytes>| {
+      let query = request.uri().query().unwrap_or_default();
+      let body = match request.uri().path() {
+        "/v2/clock" => clock(),
+        // No orders were open initially.
+        "/v2/orders" if query.starts_with("status=open&") => "[]".to_string(),
+        "/v2/orders" => {
+          assert!(query.starts_with("status=all&limit=500&after="), "{query}");
+          assert!(query.ends_with("&nested=false"), "{query}");
+
+          // The orders are not sorted by update time on purpose.
+          let orders = [
+            order(ORDER_B, "new", 4),
+            // An order that has not changed in ages.
+            order(ORDER_D, "filled", -3600),
+          ];
+          format!("[{}]", orders.join(","))
+        },
+        // `ORDER_A` was last seen open and so it gets looked up
+        // individually.
+        path if path == format!("/v2/orders/{}", ORDER_A.replace('-', "")) => {
+          order(ORDER_A, "filled", 3)
+        },
+        path => panic!("unexpected request path: {path}"),
+      };
+      Response::new(Body::from(body))
+    };
+
+    let client = Client::builder()
+      .transport(transport)
+      .build(mock_api_info(addr));
+    let policy = RetryPolicy {
+      initial_backoff: Duration::from_millis(1),
+      jitter: false,
+      ..Default::default()
+    };
+    let mut stream = ReconnectingOrderUpdates::connect(client, policy)
+      .await
+      .unwrap();
+    let () = sender0.send(()).unwrap();
+
+    let mut events = Vec::new();
+    for _ in 0..4 {
+      events.push(stream.next().await.unwrap().unwrap());
+    }
+    // Reconciliation happens only once the connection was established,
+    // so by now we are good to receive more updates.
+    let () = sender1.send(()).unwrap();
+    events.push(stream.next().await.unwrap().unwrap());
+
+    match &events[0] {
+      Event::Update(update) => {
+        assert_eq!(update.event, OrderStatus::New);
+        assert_eq!(update.order.client_order_id, ORDER_A);
+      },
+      event => panic!("received unexpected event: {event:?}"),
+    }
+    match &events[1] {
+      Event::Reconnected(gap) => assert_eq!(gap.attempts, 1),
+      event => panic!("received unexpected event: {event:?}"),
+    }
+    match &events[2] {
+      Event::Reconciled(update) => {
+        assert_eq!(update.event, OrderStatus::Filled);
+        assert_eq!(update.order.client_order_id, ORDER_A);
+      },
+      event => panic!("received unexpected event: {event:?}"),
+    }
+    match &events[3] {
+      Event::Reconciled(update) => {
+        assert_eq!(update.event, OrderStatus::New);
+        assert_eq!(update.order.client_order_id, ORDER_B);
+      },
+      event => panic!("received unexpected event: {event:?}"),
+    }
+    match &events[4] {
+      Event::Update(update) => assert_eq!(update.order.client_order_id, ORDER_C),
+      event => panic!("received unexpected event: {event:?}"),
+    }
+  }
+
+  /// Check that we reconcile orders that were open before the stream
+  /// got created and changed while disconnected.
+  #[test(tokio::test)]
+  async fn reconcile_initially_open() {
+    let (sender, receiver) = channel::<()>();
+    let mut receivers = vec![receiver].into_iter();
+
+    let addr = mock_server_multi(move |idx, mut stream| {
+      let receiver = receivers.next();
+
+      async move {
+        let () = handshake(&mut stream).await?;
+        if idx == 0 {
+          let () = receiver.unwrap().await.unwrap();
+          stream.send(Message::Close(None)).await?;
+        } else {
+          while stream.next().await.is_some() {}
+        }
+        Ok(())
+      }
+    })
+    .await;
+
+    let transport = |request: Request<Bytes>| {
+      let query = request.uri().query().unwrap_or_default();
+      let body = match request.uri().path() {
+        "/v2/clock" => clock(),
+        "/v2/orders" if query.starts_with("status=open&") => {
+          assert!(query.ends_with("&nested=false"), "{query}");
+          format!("[{}]", order(ORDER_A, "new", -3600))
+        },
+        "/v2/orders" => "[]".to_string(),
+        path if path == format!("/v2/orders/{}", ORDER_A.replace('-', "")) => {
+          order(ORDER_A, "filled", 3)
+        },
+        path => panic!("unexpected request path: {path}"),
+      };
+      Response::new(Body::from(body))
+    };
+
+    let client = Client::builder()
+      .transport(transport)
+      .build(mock_api_info(addr));
+    let policy = RetryPolicy {
+      initial_backoff: Duration::from_millis(1),
+      jitter: false,
+      ..Default::default()
+    };
+    let mut stream = ReconnectingOrderUpdates::connect(client, policy)
+      .await
+      .unwrap();
+    let () = sender.send(()).unwrap();
+
+    match stream.next().await.unwrap().unwrap() {
+      Event::Reconnected(gap) => assert_eq!(gap.attempts, 1),
+      event => panic!("received unexpected event: {event:?}"),
+    }
+    match stream.next().await.unwrap().unwrap() {
+      Event::Reconciled(update) => {
+        assert_eq!(update.event, OrderStatus::Filled);
+        assert_eq!(update.order.client_order_id, ORDER_A);
+      },
+      event => panic!("received unexpected event: {event:?}"),
+    }
+  }
+
+  /// Check that we do not miss orders sharing their submission time
+  /// with the last order of a page when listing orders.
+  #[test(tokio::test)]
+  async fn list_orders_page_boundary() {
+    let base = DateTime::<Utc>::from_str("2024-01-02T15:00:00Z").unwrap();
+    // The three oldest orders share their submission time, with the
+    // first page ending after the first of them.
+    let orders = (0..RECONCILE_PAGE_SIZE as i64 + 2)
+      .map(|idx| {
+        let id = format!("11111111-2222-3333-4444-{idx:012}");
+        let submitted_at = base - chrono::Duration::seconds(idx.min(499));
+        (id, submitted_at)
+      })
+      .collect::<Vec<_>>();
+
+    let transport = move |request: Request<Bytes>| {
+      let query = request.uri().query().unwrap_or_default();
+      let until = form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _value)| key == "until")
+        .map(|(_key, value)| DateTime::<Utc>::from_str(&value).unwrap());
+
+      let page = orders
+        .iter()
+        .filter(|(_id, submitted_at)| until.map(|until| *submitted_at < until).unwrap_or(true))
+        .take(RECONCILE_PAGE_SIZE)
+        .map(|(id, submitted_at)| {
+          let submitted_at = submitted_at.to_rfc3339_opts(SecondsFormat::Nanos, true);
+          order_submitted_at(id, "new", 0, &submitted_at)
+        })
+        .collect::<Vec<_>>();
+      Response::new(Body::from(format!("[{}]", page.join(","))))
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+    let since = base - chrono::Duration::days(1);
+    let orders = list_orders(&client, since, Vec::new()).await.unwrap();
+    assert_eq!(orders.len(), RECONCILE_PAGE_SIZE + 2);
+  }
+}
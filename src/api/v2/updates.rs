@@ -390,10 +390,11 @@ impl Subscribable for OrderUpdates {
       api_stream_url: url,
       key_id,
       secret,
+      proxy,
       ..
     } = api_info;
 
-    let stream = connect(url).await?.map(map as MapFn);
+    let stream = connect(url, proxy.as_ref()).await?.map(map as MapFn);
     let (send, recv) = stream.split();
     let (stream, subscription) = subscribe::subscribe(recv, send);
     let mut stream = stream.fuse();
@@ -10,6 +10,7 @@ use crate::api::API_BASE_URL;
 use crate::data::DATA_BASE_URL;
 use crate::data::DATA_STREAM_BASE_URL;
 use crate::Error;
+use crate::Proxy;
 
 /// The base URL of the Trading API to use.
 const ENV_API_BASE_URL: &str = "APCA_API_BASE_URL";
@@ -50,6 +51,9 @@ pub struct ApiInfo {
   pub key_id: String,
   /// The secret to use for authentication.
   pub secret: String,
+  /// The proxy to tunnel HTTP and websocket connections through, if
+  /// any.
+  pub proxy: Option<Proxy>,
 }
 
 impl ApiInfo {
@@ -78,6 +82,7 @@ impl ApiInfo {
       data_stream_base_url: Url::parse(DATA_STREAM_BASE_URL).unwrap(),
       key_id: key_id.to_string(),
       secret: secret.to_string(),
+      proxy: None,
     })
   }
 
@@ -94,6 +99,8 @@ impl ApiInfo {
   ///   `APCA_API_KEY_ID` variable
   /// - the Alpaca account secret is retrieved from the
   ///   `APCA_API_SECRET_KEY` variable
+  /// - the proxy to use, if any, is retrieved as per
+  ///   [`Proxy::from_env`]
   ///
   /// # Notes
   /// - Neither of the two data APIs can be configured via the
@@ -138,6 +145,8 @@ impl ApiInfo {
         Error::Str(format!("{ENV_SECRET} environment variable is not a valid string").into())
       })?;
 
+    let proxy = Proxy::from_env();
+
     Ok(Self {
       api_base_url,
       api_stream_url,
@@ -148,6 +157,7 @@ impl ApiInfo {
       data_stream_base_url: Url::parse(DATA_STREAM_BASE_URL).unwrap(),
       key_id,
       secret,
+      proxy,
     })
   }
 }
@@ -157,6 +167,9 @@ impl ApiInfo {
 mod tests {
   use super::*;
 
+  use std::env::remove_var;
+  use std::env::set_var;
+
 
   /// Check that we can create an [`ApiInfo`] object from its
   /// constituent parts.
@@ -171,4 +184,32 @@ mod tests {
     assert_eq!(api_info.key_id, key_id);
     assert_eq!(api_info.secret, secret);
   }
+
+  /// Check that a proxy we do not support, as configured in the
+  /// environment, does not prevent the creation of an [`ApiInfo`]
+  /// object.
+  #[test]
+  fn from_env_unsupported_proxy() {
+    let https_proxy = var_os("HTTPS_PROXY");
+    let () = set_var("HTTPS_PROXY", "https://proxy.example.com:3128");
+
+    let mut set = Vec::new();
+    for name in [ENV_KEY_ID, ENV_SECRET] {
+      if var_os(name).is_none() {
+        let () = set_var(name, "XXXXXXXXXXXXXXXXXXXX");
+        let () = set.push(name);
+      }
+    }
+
+    let result = ApiInfo::from_env();
+
+    let () = set.into_iter().for_each(|name| remove_var(name));
+    let () = match https_proxy {
+      Some(value) => set_var("HTTPS_PROXY", value),
+      None => remove_var("HTTPS_PROXY"),
+    };
+
+    let api_info = result.unwrap();
+    assert_eq!(api_info.proxy, None);
+  }
 }
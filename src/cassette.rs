@@ -0,0 +1,431 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::fs::read;
+use std::fs::write;
+use std::io::Result as IoResult;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use http::header::ACCEPT_ENCODING;
+use http::HeaderMap;
+use http::HeaderName;
+use http::HeaderValue;
+use http::Request;
+use http::Response;
+use http::StatusCode;
+
+use hyper::body::to_bytes;
+use hyper::body::Bytes;
+use hyper::Body;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::from_slice as from_json;
+use serde_json::to_vec_pretty as to_json;
+
+use crate::client::mask_header;
+use crate::transport::SharedTransport;
+use crate::transport::Transport;
+use crate::transport::TransportError;
+
+
+/// The mode a [`Cassette`] operates in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CassetteMode {
+  /// Send requests to the server and record each exchange.
+  Record,
+  /// Serve requests from previously recorded exchanges, without
+  /// contacting the server.
+  Replay,
+  /// Send requests to the server without recording anything.
+  Passthrough,
+}
+
+
+/// An HTTP message body, as stored in a cassette.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+enum RecordedBody {
+  /// A textual body, as is the case for JSON.
+  Text(String),
+  /// A body that is not valid UTF-8.
+  Binary(Vec<u8>),
+}
+
+impl From<&Bytes> for RecordedBody {
+  fn from(bytes: &Bytes) -> Self {
+    match String::from_utf8(bytes.to_vec()) {
+      Ok(text) => Self::Text(text),
+      Err(err) => Self::Binary(err.into_bytes()),
+    }
+  }
+}
+
+impl From<&RecordedBody> for Bytes {
+  fn from(body: &RecordedBody) -> Self {
+    match body {
+      RecordedBody::Text(text) => Bytes::from(text.clone()),
+      RecordedBody::Binary(data) => Bytes::from(data.clone()),
+    }
+  }
+}
+
+
+/// Convert a header map into a form suitable for recording, masking
+/// sensitive values.
+fn record_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+  headers
+    .iter()
+    .filter_map(|(name, value)| {
+      let value = mask_header(name, value).to_str().ok()?;
+      Some((name.to_string(), value.to_string()))
+    })
+    .collect()
+}
+
+
+/// A recorded request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RecordedRequest {
+  #[serde(rename = "method")]
+  method: String,
+  /// The request's path and query.
+  #[serde(rename = "uri")]
+  uri: String,
+  #[serde(rename = "headers")]
+  headers: BTreeMap<String, String>,
+  #[serde(rename = "body")]
+  body: RecordedBody,
+}
+
+impl RecordedRequest {
+  fn new(request: &Request<Bytes>) -> Self {
+    Self {
+      method: request.method().to_string(),
+      uri: path_and_query(request),
+      headers: record_headers(request.headers()),
+      body: RecordedBody::from(request.body()),
+    }
+  }
+
+  /// Check whether the recorded request matches the provided one.
+  ///
+  /// Headers are not taken into account, because they include
+  /// credentials, which are masked.
+  fn matches(&self, request: &Request<Bytes>) -> bool {
+    self.method == request.method().as_str()
+      && self.uri == path_and_query(request)
+      && self.body == RecordedBody::from(request.body())
+  }
+}
+
+
+/// A recorded response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RecordedResponse {
+  #[serde(rename = "status")]
+  status: u16,
+  #[serde(rename = "headers")]
+  headers: BTreeMap<String, String>,
+  #[serde(rename = "body")]
+  body: RecordedBody,
+}
+
+impl RecordedResponse {
+  /// Convert the recorded response back into an actual one.
+  fn to_response(&self) -> Result<Response<Body>, TransportError> {
+    let mut response = Response::new(Body::from(Bytes::from(&self.body)));
+    *response.status_mut() = StatusCode::from_u16(self.status)?;
+
+    let headers = response.headers_mut();
+    for (name, value) in &self.headers {
+      let _prev = headers.insert(
+        HeaderName::from_bytes(name.as_bytes())?,
+        HeaderValue::from_str(value)?,
+      );
+    }
+    Ok(response)
+  }
+}
+
+
+/// A single recorded request-response exchange.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Interaction {
+  #[serde(rename = "request")]
+  request: RecordedRequest,
+  #[serde(rename = "response")]
+  response: RecordedResponse,
+}
+
+
+/// Retrieve the path and query of a request.
+fn path_and_query(request: &Request<Bytes>) -> String {
+  request
+    .uri()
+    .path_and_query()
+    .map(ToString::to_string)
+    .unwrap_or_default()
+}
+
+
+/// The recorded exchanges, along with information which ones were
+/// replayed already.
+#[derive(Debug, Default)]
+struct Tape {
+  interactions: Vec<Interaction>,
+  replayed: Vec<bool>,
+}
+
+
+/// A cassette for recording HTTP exchanges with the Alpaca API and for
+/// replaying them later on, allowing for deterministic tests without
+/// network access.
+///
+/// A cassette is installed by means of `Builder::cassette` on the
+/// builder returned by [`Client::builder`][crate::Client::builder].
+/// Exchanges are stored in a JSON file, with the key ID and secret
+/// being masked out.
+///
+/// When replaying, requests are matched by method, path, query, and
+/// body. Recorded exchanges are served in the order in which they were
+/// recorded, with the last matching one being served again once all
+/// have been used up. A request not matching any recorded exchange
+/// results in an error.
+#[derive(Clone, Debug)]
+pub struct Cassette {
+  /// The path to the file storing the exchanges.
+  path: PathBuf,
+  /// The mode the cassette operates in.
+  mode: CassetteMode,
+  /// The recorded exchanges.
+  tape: Arc<Mutex<Tape>>,
+}
+
+impl Cassette {
+  /// Create a new cassette backed by the file at the given path.
+  ///
+  /// In [`CassetteMode::Replay`] the file is loaded right away. In
+  /// [`CassetteMode::Record`] it will be overwritten as exchanges are
+  /// recorded.
+  pub fn new<P>(path: P, mode: CassetteMode) -> IoResult<Self>
+  where
+    P: AsRef<Path>,
+  {
+    let path = path.as_ref().to_path_buf();
+    let interactions = match mode {
+      CassetteMode::Replay => from_json::<Vec<Interaction>>(&read(&path)?)?,
+      CassetteMode::Record | CassetteMode::Passthrough => Vec::new(),
+    };
+    let tape = Tape {
+      replayed: vec![false; interactions.len()],
+      interactions,
+    };
+
+    Ok(Self {
+      path,
+      mode,
+      tape: Arc::new(Mutex::new(tape)),
+    })
+  }
+
+  /// Retrieve the mode the cassette operates in.
+  #[inline]
+  pub fn mode(&self) -> CassetteMode {
+    self.mode
+  }
+
+  /// Wrap the provided transport such that requests are recorded or
+  /// replayed as per this cassette.
+  pub(crate) fn wrap(&self, transport: SharedTransport) -> SharedTransport {
+    SharedTransport(Arc::new(Player {
+      cassette: self.clone(),
+      transport,
+    }))
+  }
+
+  /// Find a recorded response for the given request.
+  fn replay(&self, request: &Request<Bytes>) -> Result<Response<Body>, TransportError> {
+    let mut tape = self.tape.lock().unwrap();
+    let Tape {
+      interactions,
+      replayed,
+    } = &mut *tape;
+
+    let matching = interactions
+      .iter()
+      .enumerate()
+      .filter(|(_, interaction)| interaction.request.matches(request))
+      .map(|(index, _)| index)
+      .collect::<Vec<_>>();
+    let index = matching
+      .iter()
+      .copied()
+      .find(|index| !replayed[*index])
+      .or_else(|| matching.last().copied())
+      .ok_or_else(|| {
+        format!(
+          "cassette {} contains no recorded exchange matching request {} {}",
+          self.path.display(),
+          request.method(),
+          path_and_query(request)
+        )
+      })?;
+
+    replayed[index] = true;
+    interactions[index].response.to_response()
+  }
+
+  /// Record an exchange and persist all exchanges recorded so far.
+  fn record(&self, interaction: Interaction) -> IoResult<()> {
+    let mut tape = self.tape.lock().unwrap();
+    tape.interactions.push(interaction);
+    tape.replayed.push(false);
+
+    let json = to_json(&tape.interactions)?;
+    write(&self.path, json)
+  }
+}
+
+
+/// A transport recording or replaying requests as per a [`Cassette`].
+struct Player {
+  cassette: Cassette,
+  transport: SharedTransport,
+}
+
+#[async_trait]
+impl Transport for Player {
+  async fn send(&self, mut request: Request<Bytes>) -> Result<Response<Body>, TransportError> {
+    match self.cassette.mode {
+      CassetteMode::Passthrough => self.transport.0.send(request).await,
+      CassetteMode::Replay => self.cassette.replay(&request),
+      CassetteMode::Record => {
+        // We want to record bodies in a human readable form, so we ask
+        // for them to not be compressed.
+        let _encoding = request.headers_mut().remove(ACCEPT_ENCODING);
+        let recorded = RecordedRequest::new(&request);
+
+        let response = self.transport.0.send(request).await?;
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body).await?;
+
+        let interaction = Interaction {
+          request: recorded,
+          response: RecordedResponse {
+            status: parts.status.as_u16(),
+            headers: record_headers(&parts.headers),
+            body: RecordedBody::from(&body),
+          },
+        };
+        let () = self.cassette.record(interaction)?;
+
+        Ok(Response::from_parts(parts, Body::from(body)))
+      },
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::env::temp_dir;
+  use std::fs::read_to_string;
+  use std::fs::remove_file;
+
+  use test_log::test;
+
+  use uuid::Uuid;
+
+  use crate::api::v2::clock;
+  use crate::ApiInfo;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  /// Create a `Client` using the provided cassette, with a transport
+  /// reporting the market as open or closed in alternation.
+  fn client(cassette: Cassette) -> Client {
+    let counter = Mutex::new(0);
+    let transport = move |_request| {
+      let mut counter = counter.lock().unwrap();
+      *counter += 1;
+      let body = format!(
+        r#"{{
+  "timestamp": "2024-01-02T10:00:00.000000000-05:00",
+  "is_open": {},
+  "next_open": "2024-01-03T09:30:00-05:00",
+  "next_close": "2024-01-02T16:00:00-05:00"
+}}"#,
+        *counter % 2 == 1
+      );
+      Response::new(Body::from(body))
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "KEYID", "SECRET").unwrap();
+    Client::builder()
+      .transport(transport)
+      .cassette(cassette)
+      .build(api_info)
+  }
+
+  /// Check that we can record exchanges and replay them later on.
+  #[test(tokio::test)]
+  async fn record_replay() {
+    let path = temp_dir().join(format!("apca-cassette-{}.json", Uuid::new_v4()));
+
+    let cassette = Cassette::new(&path, CassetteMode::Record).unwrap();
+    let client = client(cassette);
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(clock.open);
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(!clock.open);
+
+    let content = read_to_string(&path).unwrap();
+    assert!(!content.contains("KEYID"), "{content}");
+    assert!(!content.contains("SECRET"), "{content}");
+    assert!(content.contains("<masked>"), "{content}");
+
+    let cassette = Cassette::new(&path, CassetteMode::Replay).unwrap();
+    let api_info = ApiInfo::from_parts("https://example.com/", "KEYID", "SECRET").unwrap();
+    let client = Client::builder().cassette(cassette).build(api_info);
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(clock.open);
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(!clock.open);
+    // Once exhausted, the last matching exchange is served again.
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(!clock.open);
+
+    let () = remove_file(&path).unwrap();
+  }
+
+  /// Make sure that replaying fails for requests that were not
+  /// recorded.
+  #[test(tokio::test)]
+  async fn replay_mismatch() {
+    let path = temp_dir().join(format!("apca-cassette-{}.json", Uuid::new_v4()));
+    let () = write(&path, "[]").unwrap();
+
+    let cassette = Cassette::new(&path, CassetteMode::Replay).unwrap();
+    let api_info = ApiInfo::from_parts("https://example.com/", "KEYID", "SECRET").unwrap();
+    let client = Client::builder().cassette(cassette).build(api_info);
+    let err = client.issue::<clock::Get>(&()).await.unwrap_err();
+    match err {
+      RequestError::Transport(err) => {
+        assert!(err.to_string().contains("GET /v2/clock"), "{err}")
+      },
+      _ => panic!("Received unexpected error: {err:?}"),
+    }
+
+    let () = remove_file(&path).unwrap();
+  }
+}
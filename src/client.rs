@@ -7,9 +7,14 @@ use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::future::Future;
 use std::str::from_utf8;
+use std::sync::Arc;
+use std::time::Instant;
 
+use http::header::ACCEPT_ENCODING;
+use http::header::CONTENT_ENCODING;
 use http::request::Builder as HttpRequestBuilder;
 use http::HeaderMap;
+use http::HeaderName;
 use http::HeaderValue;
 use http::Request;
 use http::Response;
@@ -18,11 +23,12 @@ use http_endpoint::Endpoint;
 use hyper::body::Bytes;
 use hyper::body::HttpBody as _;
 use hyper::client::Builder as HttpClientBuilder;
-use hyper::client::HttpConnector;
 use hyper::Body;
 use hyper::Client as HttpClient;
 use hyper::Error as HyperError;
-use hyper_tls::HttpsConnector;
+
+use tokio::time::sleep;
+use tokio::time::timeout;
 
 use tracing::debug;
 use tracing::field::debug;
@@ -38,11 +44,49 @@ use url::Url;
 use crate::api::HDR_KEY_ID;
 use crate::api::HDR_SECRET;
 use crate::api_info::ApiInfo;
+use crate::cassette::Cassette;
+use crate::compression::accept_encoding;
+use crate::compression::decode;
+use crate::compression::Coding;
+use crate::data::DATA_BASE_URL;
 use crate::error::RequestError;
+use crate::error::RequestErrorWithMeta;
+use crate::meta::ResponseMeta;
+use crate::paginate::Paginated;
+use crate::paginate::Paginator;
+use crate::proxy::Proxy;
+use crate::proxy::ProxyConnector;
+use crate::rate_limit::Priority;
+use crate::rate_limit::RateLimiter;
+use crate::retry::is_retryable_request;
+use crate::retry::is_retryable_status;
+use crate::retry::is_transient;
+use crate::retry::RetryPolicy;
 use crate::subscribable::Subscribable;
+use crate::timeout::with_connect_timeout;
+use crate::timeout::ConnectTimeout;
+use crate::timeout::TimeoutConnector;
+use crate::timeout::TimeoutPhase;
+use crate::timeout::Timeouts;
+use crate::tls::https_connector;
+use crate::transport::SharedTransport;
+use crate::transport::Transport;
 use crate::Error;
 
 
+/// Mask the value of the header with the given name if it carries
+/// sensitive data.
+pub(crate) fn mask_header<'v>(name: &HeaderName, value: &'v HeaderValue) -> &'v HeaderValue {
+  static MASKED: HeaderValue = HeaderValue::from_static("<masked>");
+
+  if name == HDR_KEY_ID || name == HDR_SECRET {
+    &MASKED
+  } else {
+    value
+  }
+}
+
+
 /// A type providing a debug representation of HTTP headers, with
 /// sensitive data being masked out.
 struct DebugHeaders<'h> {
@@ -51,16 +95,8 @@ struct DebugHeaders<'h> {
 
 impl<'h> Debug for DebugHeaders<'h> {
   fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-    static MASKED: HeaderValue = HeaderValue::from_static("<masked>");
-
     f.debug_map()
-      .entries(self.headers.iter().map(|(k, v)| {
-        if k == HDR_KEY_ID || k == HDR_SECRET {
-          (k, &MASKED)
-        } else {
-          (k, v)
-        }
-      }))
+      .entries(self.headers.iter().map(|(k, v)| (k, mask_header(k, v))))
       .finish()
   }
 }
@@ -69,7 +105,7 @@ impl<'h> Debug for DebugHeaders<'h> {
 /// A type providing a debug representation of an HTTP request, with
 /// sensitive data being masked out.
 struct DebugRequest<'r> {
-  request: &'r Request<Body>,
+  request: &'r Request<Bytes>,
 }
 
 impl<'r> Debug for DebugRequest<'r> {
@@ -92,31 +128,134 @@ impl<'r> Debug for DebugRequest<'r> {
 
 
 /// Emit a debug representation of an HTTP request.
-fn debug_request(request: &Request<Body>) -> DebugValue<DebugRequest<'_>> {
+fn debug_request(request: &Request<Bytes>) -> DebugValue<DebugRequest<'_>> {
   debug(DebugRequest { request })
 }
 
 
+/// Create a copy of the provided request, leaving the original intact
+/// so that it can be issued again later.
+fn clone_request(request: &Request<Bytes>) -> Request<Bytes> {
+  let mut clone = Request::new(request.body().clone());
+  *clone.method_mut() = request.method().clone();
+  *clone.uri_mut() = request.uri().clone();
+  *clone.version_mut() = request.version();
+  *clone.headers_mut() = request.headers().clone();
+  clone
+}
+
+
 /// A builder for creating customized `Client` objects.
 #[derive(Debug)]
 pub struct Builder {
   builder: HttpClientBuilder,
+  retry_policy: Option<RetryPolicy>,
+  rate_limiter: Option<RateLimiter>,
+  transport: Option<SharedTransport>,
+  cassette: Option<Cassette>,
+  timeouts: Timeouts,
+  proxy: Option<Proxy>,
 }
 
 impl Builder {
   /// Adjust the maximum number of idle connections per host.
+  ///
+  /// This setting has no effect if a custom transport is used.
   #[inline]
   pub fn max_idle_per_host(&mut self, max_idle: usize) -> &mut Self {
     let _ = self.builder.pool_max_idle_per_host(max_idle);
     self
   }
 
+  /// Set the policy to use for retrying requests that failed for
+  /// transient reasons.
+  ///
+  /// By default, requests are not retried.
+  #[inline]
+  pub fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+    self.retry_policy = Some(policy);
+    self
+  }
+
+  /// Set a rate limiter through which all requests issued by the
+  /// `Client` (and its clones) have to go.
+  ///
+  /// The same limiter may be shared by multiple `Client` objects.
+  #[inline]
+  pub fn rate_limiter(&mut self, limiter: RateLimiter) -> &mut Self {
+    self.rate_limiter = Some(limiter);
+    self
+  }
+
+  /// Set the transport to use for sending requests.
+  ///
+  /// By default, requests are sent over the network using `hyper`.
+  #[inline]
+  pub fn transport<T>(&mut self, transport: T) -> &mut Self
+  where
+    T: Transport + 'static,
+  {
+    self.transport = Some(SharedTransport(Arc::new(transport)));
+    self
+  }
+
+  /// Set the timeouts to apply to requests by default.
+  ///
+  /// By default, requests are not subject to any time limit. The
+  /// timeouts can be overridden for individual requests using
+  /// [`Client::with_timeouts`].
+  #[inline]
+  pub fn timeouts(&mut self, timeouts: Timeouts) -> &mut Self {
+    self.timeouts = timeouts;
+    self
+  }
+
+  /// Set the proxy to tunnel connections through.
+  ///
+  /// The proxy takes precedence over the one contained in the
+  /// [`ApiInfo`] object the `Client` is built with, and it is used for
+  /// websocket connections established through the `Client` as well.
+  /// It has no effect if a custom transport is used.
+  #[inline]
+  pub fn proxy(&mut self, proxy: Proxy) -> &mut Self {
+    self.proxy = Some(proxy);
+    self
+  }
+
+  /// Set a cassette for recording exchanges with the server or for
+  /// replaying previously recorded ones.
+  ///
+  /// The cassette sits in front of the transport in use.
+  #[inline]
+  pub fn cassette(&mut self, cassette: Cassette) -> &mut Self {
+    self.cassette = Some(cassette);
+    self
+  }
+
   /// Build the final `Client` object.
-  pub fn build(&self, api_info: ApiInfo) -> Client {
-    let https = HttpsConnector::new();
-    let client = self.builder.build(https);
+  pub fn build(&self, mut api_info: ApiInfo) -> Client {
+    if let Some(proxy) = &self.proxy {
+      api_info.proxy = Some(proxy.clone());
+    }
 
-    Client { api_info, client }
+    let transport = self.transport.clone().unwrap_or_else(|| {
+      let connector = ProxyConnector::new(api_info.proxy.clone());
+      let https = TimeoutConnector::new(https_connector(connector));
+      let client = self.builder.build::<_, Body>(https);
+      SharedTransport(Arc::new(client))
+    });
+    let transport = match &self.cassette {
+      Some(cassette) => cassette.wrap(transport),
+      None => transport,
+    };
+
+    Client {
+      api_info,
+      transport,
+      retry_policy: self.retry_policy,
+      rate_limiter: self.rate_limiter.clone(),
+      timeouts: self.timeouts,
+    }
   }
 }
 
@@ -134,7 +273,15 @@ impl Default for Builder {
     let mut builder = HttpClient::builder();
     let _ = builder.pool_max_idle_per_host(0);
 
-    Self { builder }
+    Self {
+      builder,
+      retry_policy: None,
+      rate_limiter: None,
+      transport: None,
+      cassette: None,
+      timeouts: Timeouts::default(),
+      proxy: None,
+    }
   }
 
   #[cfg(not(test))]
@@ -142,6 +289,12 @@ impl Default for Builder {
   fn default() -> Self {
     Self {
       builder: HttpClient::builder(),
+      retry_policy: None,
+      rate_limiter: None,
+      transport: None,
+      cassette: None,
+      timeouts: Timeouts::default(),
+      proxy: None,
     }
   }
 }
@@ -149,10 +302,13 @@ impl Default for Builder {
 
 /// A `Client` is the entity used by clients of this module for
 /// interacting with the Alpaca API.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Client {
   api_info: ApiInfo,
-  client: HttpClient<HttpsConnector<HttpConnector>, Body>,
+  transport: SharedTransport,
+  retry_policy: Option<RetryPolicy>,
+  rate_limiter: Option<RateLimiter>,
+  timeouts: Timeouts,
 }
 
 impl Client {
@@ -169,28 +325,67 @@ impl Client {
     Builder::default().build(api_info)
   }
 
-  /// Add "gzip" as an accepted encoding to the request.
-  #[cfg(feature = "gzip")]
-  fn maybe_add_gzip_header(request: &mut Request<Body>) {
-    use http::header::ACCEPT_ENCODING;
+  /// Create a copy of this `Client` that applies the provided timeouts
+  /// to requests, instead of the ones it was built with.
+  ///
+  /// The copy shares connections, rate limiter, and all other state
+  /// with the original, making this method suitable for overriding
+  /// timeouts for a single request:
+  /// ```rust,no_run
+  /// # use std::time::Duration;
+  /// # use apca::ApiInfo;
+  /// # use apca::Client;
+  /// # use apca::Timeouts;
+  /// use apca::api::v2::account;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// # let api_info = ApiInfo::from_env().unwrap();
+  /// # let client = Client::new(api_info);
+  /// let timeouts = Timeouts {
+  ///   response: Some(Duration::from_secs(5)),
+  ///   ..Default::default()
+  /// };
+  /// let account = client
+  ///   .with_timeouts(timeouts)
+  ///   .issue::<account::Get>(&())
+  ///   .await;
+  /// # }
+  /// ```
+  #[inline]
+  pub fn with_timeouts(&self, timeouts: Timeouts) -> Self {
+    Self {
+      timeouts,
+      ..self.clone()
+    }
+  }
 
-    let _ = request
-      .headers_mut()
-      .insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+  /// Retrieve the timeouts applied to requests issued by this `Client`.
+  #[inline]
+  pub fn timeouts(&self) -> &Timeouts {
+    &self.timeouts
   }
 
-  /// An implementation stub not actually doing anything.
-  #[cfg(not(feature = "gzip"))]
-  fn maybe_add_gzip_header(_request: &mut Request<Body>) {}
+  /// Add all enabled content codings as accepted encodings to the
+  /// request.
+  fn maybe_add_accept_encoding_header(request: &mut Request<Bytes>) {
+    if let Some(value) = accept_encoding() {
+      let _ = request.headers_mut().insert(ACCEPT_ENCODING, value);
+    }
+  }
 
   /// Create a `Request` to the endpoint.
-  fn request<R>(&self, input: &R::Input) -> Result<Request<Body>, R::Error>
+  fn request<R>(&self, input: &R::Input) -> Result<Request<Bytes>, R::Error>
   where
     R: Endpoint,
   {
-    let mut url = R::base_url()
-      .map(|url| Url::parse(url.as_ref()).expect("endpoint definition contains invalid URL"))
-      .unwrap_or_else(|| self.api_info.api_base_url.clone());
+    let mut url = match R::base_url() {
+      // Market data endpoints are directed at the configured data base
+      // URL, which may differ from the default one.
+      Some(url) if url == DATA_BASE_URL => self.api_info.data_base_url.clone(),
+      Some(url) => Url::parse(url.as_ref()).expect("endpoint definition contains invalid URL"),
+      None => self.api_info.api_base_url.clone(),
+    };
 
     url.set_path(&R::path(input));
     url.set_query(R::query(input)?.as_ref().map(AsRef::as_ref));
@@ -201,11 +396,11 @@ impl Client {
       // Add required authentication information.
       .header(HDR_KEY_ID, self.api_info.key_id.as_str())
       .header(HDR_SECRET, self.api_info.secret.as_str())
-      .body(Body::from(
-        R::body(input)?.unwrap_or(Cow::Borrowed(&[0; 0])),
+      .body(Bytes::from(
+        R::body(input)?.map(Cow::into_owned).unwrap_or_default(),
       ))?;
 
-    Self::maybe_add_gzip_header(&mut request);
+    Self::maybe_add_accept_encoding_header(&mut request);
     Ok(request)
   }
 
@@ -222,35 +417,20 @@ impl Client {
     Ok(response.collect().await?.to_bytes())
   }
 
-  /// Retrieve the HTTP body, possible uncompressing it if it was gzip
-  /// encoded.
-  #[cfg(feature = "gzip")]
+  /// Retrieve the HTTP body, uncompressing it while it is being
+  /// received if it was encoded using one of the enabled content
+  /// codings.
   async fn retrieve_body<E>(response: Response<Body>) -> Result<Bytes, RequestError<E>> {
-    use async_compression::futures::bufread::GzipDecoder;
-    use futures::AsyncReadExt as _;
-    use http::header::CONTENT_ENCODING;
-
     let (parts, body) = response.into_parts();
-    let encoding = parts.headers.get(CONTENT_ENCODING);
-
-    let bytes = Self::retrieve_raw_body(body).await?;
-    let bytes = match encoding {
-      Some(value) if value == HeaderValue::from_static("gzip") => {
-        let mut buffer = Vec::new();
-        let _count = GzipDecoder::new(&*bytes).read_to_end(&mut buffer).await?;
-        buffer.into()
-      },
-      _ => bytes,
-    };
-
-    Ok(bytes)
-  }
-
-  /// Retrieve the HTTP body.
-  #[cfg(not(feature = "gzip"))]
-  async fn retrieve_body<E>(response: Response<Body>) -> Result<Bytes, RequestError<E>> {
-    let bytes = Self::retrieve_raw_body(response.into_body()).await?;
-    Ok(bytes)
+    let coding = parts
+      .headers
+      .get(CONTENT_ENCODING)
+      .and_then(Coding::from_header);
+
+    match coding {
+      Some(coding) => decode(coding, body).await,
+      None => Ok(Self::retrieve_raw_body(body).await?),
+    }
   }
 
   /// Create and issue a request and decode the response.
@@ -258,6 +438,28 @@ impl Client {
     &self,
     input: &R::Input,
   ) -> impl Future<Output = Result<R::Output, RequestError<R::Error>>> + '_
+  where
+    R: Endpoint,
+  {
+    let future = self.issue_with_meta::<R>(input);
+    async move {
+      future
+        .await
+        .map(|(output, _meta)| output)
+        .map_err(|err| err.error)
+    }
+  }
+
+  /// Create and issue a request and decode the response, providing
+  /// access to metadata about the response, such as the request ID
+  /// assigned by Alpaca and rate limit information.
+  ///
+  /// Metadata are also available in case of an error, as long as a
+  /// response was received.
+  pub fn issue_with_meta<R>(
+    &self,
+    input: &R::Input,
+  ) -> impl Future<Output = Result<(R::Output, ResponseMeta), RequestErrorWithMeta<R::Error>>> + '_
   where
     R: Endpoint,
   {
@@ -274,28 +476,144 @@ impl Client {
     }
   }
 
-  /// Issue a request.
-  #[allow(clippy::cognitive_complexity)]
-  async fn issue_<R>(&self, request: Request<Body>) -> Result<R::Output, RequestError<R::Error>>
+  /// Issue a request, retrying it as per the configured retry policy.
+  async fn issue_<R>(
+    &self,
+    request: Request<Bytes>,
+  ) -> Result<(R::Output, ResponseMeta), RequestErrorWithMeta<R::Error>>
   where
     R: Endpoint,
   {
-    debug!("requesting");
-    trace!(request = debug_request(&request));
+    let policy = self
+      .retry_policy
+      .as_ref()
+      .filter(|_| is_retryable_request(&request));
+    let mut retry = 0;
+
+    loop {
+      let result = self.send::<R::Error>(&request).await;
+
+      if let Some(policy) = policy.filter(|policy| retry < policy.max_retries) {
+        let delay = match &result {
+          Ok((meta, _)) if is_retryable_status(meta.status) => {
+            Some(policy.delay(retry, Some(meta.status), &meta.headers))
+          },
+          Err(RequestErrorWithMeta {
+            error: RequestError::Hyper(err),
+            ..
+          }) if is_transient(err) => Some(policy.delay(retry, None, &HeaderMap::new())),
+          // Failure to connect in time means that the request was never
+          // sent, so it is safe to try again.
+          Err(RequestErrorWithMeta {
+            error: RequestError::Timeout(TimeoutPhase::Connect),
+            ..
+          }) => Some(policy.delay(retry, None, &HeaderMap::new())),
+          _ => None,
+        };
+
+        if let Some(delay) = delay {
+          debug!(retry, delay = debug(&delay), "retrying request");
+          let () = sleep(delay).await;
+          retry += 1;
+          continue
+        }
+      }
+
+      let (meta, bytes) = result?;
+      break match R::evaluate(meta.status, &bytes) {
+        Ok(output) => Ok((output, meta)),
+        Err(err) => Err(RequestErrorWithMeta {
+          error: RequestError::Endpoint(err),
+          meta: Some(meta),
+        }),
+      }
+    }
+  }
+
+  /// Send a request and retrieve the response's metadata and body.
+  #[allow(clippy::cognitive_complexity)]
+  async fn send<E>(
+    &self,
+    request: &Request<Bytes>,
+  ) -> Result<(ResponseMeta, Bytes), RequestErrorWithMeta<E>> {
+    if let Some(limiter) = &self.rate_limiter {
+      let () = limiter.acquire(Priority::of(request)).await;
+    }
 
-    let result = self.client.request(request).await?;
+    debug!("requesting");
+    trace!(request = debug_request(request));
+
+    let start = Instant::now();
+    let future = self.transport.0.send(clone_request(request));
+    let future = with_connect_timeout(self.timeouts.connect, future);
+    let result = match self.timeouts.response {
+      Some(response) => timeout(response, future)
+        .await
+        .map_err(|_| RequestError::Timeout(TimeoutPhase::Response))?,
+      None => future.await,
+    };
+    let result = result.map_err(|err| {
+      if ConnectTimeout::caused(&*err) {
+        RequestError::Timeout(TimeoutPhase::Connect)
+      } else {
+        match err.downcast::<HyperError>() {
+          Ok(err) => RequestError::Hyper(*err),
+          Err(err) => RequestError::Transport(err),
+        }
+      }
+    })?;
     let status = result.status();
+    if let Some(limiter) = &self.rate_limiter {
+      let () = limiter.update(result.headers());
+    }
+    // We have all the metadata at this point. Should retrieval of the
+    // body fail, we still want to report them.
+    let mut meta = ResponseMeta::new(status, result.headers(), start.elapsed());
     debug!(status = debug(&status));
     trace!(response = debug(&result));
 
-    let bytes = Self::retrieve_body::<R::Error>(result).await?;
+    let future = Self::retrieve_body::<E>(result);
+    let result = match self.timeouts.body {
+      Some(body) => timeout(body, future)
+        .await
+        .unwrap_or(Err(RequestError::Timeout(TimeoutPhase::Body))),
+      None => future.await,
+    };
+    let bytes = match result {
+      Ok(bytes) => bytes,
+      Err(error) => {
+        return Err(RequestErrorWithMeta {
+          error,
+          meta: Some(meta),
+        })
+      },
+    };
     let body = bytes.as_ref();
     match from_utf8(body) {
       Ok(s) => trace!(body = display(&s)),
       Err(b) => trace!(body = display(&b)),
     }
 
-    R::evaluate(status, body).map_err(RequestError::Endpoint)
+    meta.latency = start.elapsed();
+    debug!(
+      request_id = debug(&meta.request_id),
+      latency = debug(&meta.latency)
+    );
+    Ok((meta, bytes))
+  }
+
+  /// Create a stream over the individual items of a paginated
+  /// endpoint, following page tokens automatically.
+  ///
+  /// Retrieval starts at the page referenced by the page token set on
+  /// `input`, if any, which allows for resuming from a token obtained
+  /// earlier through [`Paginator::page_token`].
+  #[inline]
+  pub fn paginate<P>(&self, input: P::Input) -> Paginator<'_, P>
+  where
+    P: Paginated,
+  {
+    Paginator::new(self, input)
   }
 
   /// Subscribe to the given subscribable in order to receive updates.
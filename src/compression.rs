@@ -0,0 +1,326 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use http::HeaderValue;
+
+use hyper::body::Bytes;
+use hyper::Body;
+
+use crate::error::RequestError;
+
+
+/// A content coding that we can decode response bodies from.
+///
+/// Only codings for which the corresponding feature is enabled are
+/// available.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Coding {
+  /// The Zstandard coding.
+  #[cfg(feature = "zstd")]
+  Zstd,
+  /// The Brotli coding.
+  #[cfg(feature = "brotli")]
+  Brotli,
+  /// The gzip coding.
+  #[cfg(feature = "gzip")]
+  Gzip,
+  /// The deflate coding.
+  ///
+  /// Note that as per RFC 9110 this coding actually refers to the zlib
+  /// format, and not to raw deflate data.
+  #[cfg(feature = "deflate")]
+  Deflate,
+}
+
+impl Coding {
+  /// All enabled codings, in order of preference.
+  const ALL: &'static [Self] = &[
+    #[cfg(feature = "zstd")]
+    Self::Zstd,
+    #[cfg(feature = "brotli")]
+    Self::Brotli,
+    #[cfg(feature = "gzip")]
+    Self::Gzip,
+    #[cfg(feature = "deflate")]
+    Self::Deflate,
+  ];
+
+  /// Retrieve the name of the coding, as used in HTTP headers.
+  fn name(self) -> &'static str {
+    match self {
+      #[cfg(feature = "zstd")]
+      Self::Zstd => "zstd",
+      #[cfg(feature = "brotli")]
+      Self::Brotli => "br",
+      #[cfg(feature = "gzip")]
+      Self::Gzip => "gzip",
+      #[cfg(feature = "deflate")]
+      Self::Deflate => "deflate",
+    }
+  }
+
+  /// Determine the coding described by a `Content-Encoding` header
+  /// value, if it is one we support.
+  pub(crate) fn from_header(value: &HeaderValue) -> Option<Self> {
+    let value = value.to_str().ok()?.trim();
+    Self::ALL
+      .iter()
+      .copied()
+      .find(|coding| coding.name().eq_ignore_ascii_case(value))
+  }
+}
+
+
+/// Create the value of an `Accept-Encoding` header advertising all
+/// enabled codings, with preferred ones being assigned higher quality
+/// values.
+///
+/// `None` is returned if no coding is enabled.
+pub(crate) fn accept_encoding() -> Option<HeaderValue> {
+  if Coding::ALL.is_empty() {
+    return None
+  }
+
+  let value = Coding::ALL
+    .iter()
+    .enumerate()
+    .map(|(idx, coding)| match idx {
+      0 => coding.name().to_string(),
+      _ => format!("{};q=0.{}", coding.name(), 10 - idx),
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+  // The value is made up of ASCII characters only.
+  Some(HeaderValue::from_str(&value).unwrap())
+}
+
+
+/// Read the provided body, decoding it on the fly as per the given
+/// coding.
+#[cfg(any(
+  feature = "brotli",
+  feature = "deflate",
+  feature = "gzip",
+  feature = "zstd"
+))]
+pub(crate) async fn decode<E>(coding: Coding, body: Body) -> Result<Bytes, RequestError<E>> {
+  use std::io::Error as IoError;
+  use std::io::ErrorKind;
+
+  use futures::AsyncReadExt as _;
+  use futures::TryStreamExt as _;
+
+  use hyper::Error as HyperError;
+
+  let reader = body
+    .map_err(|err| IoError::new(ErrorKind::Other, err))
+    .into_async_read();
+  let mut buffer = Vec::new();
+
+  let result = match coding {
+    #[cfg(feature = "zstd")]
+    Coding::Zstd => {
+      use async_compression::futures::bufread::ZstdDecoder;
+      ZstdDecoder::new(reader).read_to_end(&mut buffer).await
+    },
+    #[cfg(feature = "brotli")]
+    Coding::Brotli => {
+      use async_compression::futures::bufread::BrotliDecoder;
+      BrotliDecoder::new(reader).read_to_end(&mut buffer).await
+    },
+    #[cfg(feature = "gzip")]
+    Coding::Gzip => {
+      use async_compression::futures::bufread::GzipDecoder;
+      GzipDecoder::new(reader).read_to_end(&mut buffer).await
+    },
+    #[cfg(feature = "deflate")]
+    Coding::Deflate => {
+      use async_compression::futures::bufread::ZlibDecoder;
+      ZlibDecoder::new(reader).read_to_end(&mut buffer).await
+    },
+  };
+
+  let _count = result.map_err(|err| {
+    // Errors reported while receiving the body should surface as they
+    // would without decoding.
+    if err.get_ref().map(|err| err.is::<HyperError>()) == Some(true) {
+      // SANITY: We just checked that there is an inner error of the
+      //         correct type.
+      let err = err.into_inner().unwrap().downcast::<HyperError>().unwrap();
+      RequestError::Hyper(*err)
+    } else {
+      RequestError::Io(err)
+    }
+  })?;
+  Ok(buffer.into())
+}
+
+/// An implementation stub for when no coding is enabled.
+#[cfg(not(any(
+  feature = "brotli",
+  feature = "deflate",
+  feature = "gzip",
+  feature = "zstd"
+)))]
+pub(crate) async fn decode<E>(coding: Coding, _body: Body) -> Result<Bytes, RequestError<E>> {
+  match coding {}
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use test_log::test;
+
+
+  /// Create a body from the provided data, split into small chunks.
+  #[cfg(any(
+    feature = "brotli",
+    feature = "deflate",
+    feature = "gzip",
+    feature = "zstd"
+  ))]
+  fn chunked(data: Vec<u8>) -> Body {
+    use std::io::Error as IoError;
+
+    use futures::stream::iter;
+
+    let chunks = data
+      .chunks(7)
+      .map(|chunk| Ok::<_, IoError>(Bytes::copy_from_slice(chunk)))
+      .collect::<Vec<_>>();
+    Body::wrap_stream(iter(chunks))
+  }
+
+  /// Encode the provided data as per the given coding.
+  #[cfg(any(
+    feature = "brotli",
+    feature = "deflate",
+    feature = "gzip",
+    feature = "zstd"
+  ))]
+  async fn encode(coding: Coding, data: &[u8]) -> Vec<u8> {
+    use futures::AsyncReadExt as _;
+
+    let mut buffer = Vec::new();
+    let _count = match coding {
+      #[cfg(feature = "zstd")]
+      Coding::Zstd => {
+        use async_compression::futures::bufread::ZstdEncoder;
+        ZstdEncoder::new(data).read_to_end(&mut buffer).await
+      },
+      #[cfg(feature = "brotli")]
+      Coding::Brotli => {
+        use async_compression::futures::bufread::BrotliEncoder;
+        BrotliEncoder::new(data).read_to_end(&mut buffer).await
+      },
+      #[cfg(feature = "gzip")]
+      Coding::Gzip => {
+        use async_compression::futures::bufread::GzipEncoder;
+        GzipEncoder::new(data).read_to_end(&mut buffer).await
+      },
+      #[cfg(feature = "deflate")]
+      Coding::Deflate => {
+        use async_compression::futures::bufread::ZlibEncoder;
+        ZlibEncoder::new(data).read_to_end(&mut buffer).await
+      },
+    }
+    .unwrap();
+    buffer
+  }
+
+
+  /// Check that we advertise all enabled codings.
+  #[test]
+  fn accept_encoding_value() {
+    let value = accept_encoding();
+    let expected = match Coding::ALL.len() {
+      0 => None,
+      _ => Some(Coding::ALL.iter().map(|coding| coding.name())),
+    };
+
+    match (value, expected) {
+      (None, None) => (),
+      (Some(value), Some(mut expected)) => {
+        let value = value.to_str().unwrap();
+        assert!(value
+          .split(", ")
+          .all(|coding| coding.split(';').next() == expected.next()));
+        assert_eq!(expected.next(), None);
+      },
+      (value, _) => panic!("unexpected Accept-Encoding value: {value:?}"),
+    }
+  }
+
+  /// Check that we can decode bodies in all enabled codings.
+  #[cfg(any(
+    feature = "brotli",
+    feature = "deflate",
+    feature = "gzip",
+    feature = "zstd"
+  ))]
+  #[test(tokio::test)]
+  async fn decode_codings() {
+    let data = br#"{"bars":{"SPY":[{"t":"2024-01-02T14:30:00Z","o":472.16}]}}"#.repeat(32);
+
+    for coding in Coding::ALL {
+      let encoded = encode(*coding, &data).await;
+      let header = HeaderValue::from_static(coding.name());
+      assert_eq!(Coding::from_header(&header), Some(*coding));
+
+      let decoded = decode::<()>(*coding, chunked(encoded)).await.unwrap();
+      assert_eq!(decoded, data, "{coding:?}");
+    }
+  }
+
+  /// Check that we report an error on invalid data.
+  #[cfg(any(
+    feature = "brotli",
+    feature = "deflate",
+    feature = "gzip",
+    feature = "zstd"
+  ))]
+  #[test(tokio::test)]
+  async fn decode_invalid() {
+    for coding in Coding::ALL {
+      let result = decode::<()>(*coding, Body::from("not compressed")).await;
+      assert!(
+        matches!(result, Err(RequestError::Io(..))),
+        "{coding:?}: {result:?}"
+      );
+    }
+  }
+
+  /// Check that a `Client` transparently decodes compressed responses.
+  #[cfg(feature = "gzip")]
+  #[test(tokio::test)]
+  async fn client_decoding() {
+    use http::header::ACCEPT_ENCODING;
+    use http::header::CONTENT_ENCODING;
+    use http::Response;
+
+    use crate::api::v2::clock;
+    use crate::ApiInfo;
+    use crate::Client;
+
+    let json = br#"{"timestamp":"2024-01-02T10:00:00Z","is_open":true,"next_open":"2024-01-03T14:30:00Z","next_close":"2024-01-02T21:00:00Z"}"#;
+    let encoded = encode(Coding::Gzip, json).await;
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder()
+      .transport(move |request: http::Request<Bytes>| {
+        let accepted = request.headers().get(ACCEPT_ENCODING).unwrap();
+        assert!(accepted.to_str().unwrap().contains("gzip"));
+
+        Response::builder()
+          .header(CONTENT_ENCODING, "gzip")
+          .body(Body::from(encoded.clone()))
+          .unwrap()
+      })
+      .build(api_info);
+
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(clock.open);
+  }
+}
@@ -0,0 +1,7 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Functionality for retrieving and streaming news articles.
+pub mod news;
+/// Functionality for retrieving market data of option contracts.
+pub mod options;
@@ -0,0 +1,560 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v2::stream::private;
+use crate::data::v2::stream::Bar;
+use crate::data::v2::stream::Quote;
+use crate::data::v2::stream::RealtimeData;
+use crate::data::v2::stream::Source;
+use crate::data::v2::stream::SourceVariant;
+use crate::data::v2::stream::Trade;
+use crate::data::DATA_BASE_URL;
+use crate::util::string_slice_to_str;
+use crate::Paginated;
+use crate::Str;
+
+
+/// A GET request to be issued to the /v1beta1/news endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ListReq {
+  /// The symbols to retrieve news for.
+  ///
+  /// If empty, news for all symbols are retrieved.
+  #[serde(
+    rename = "symbols",
+    serialize_with = "string_slice_to_str",
+    skip_serializing_if = "Vec::is_empty"
+  )]
+  pub symbols: Vec<String>,
+  /// Filter articles published at or after this time.
+  #[serde(rename = "start", skip_serializing_if = "Option::is_none")]
+  pub start: Option<DateTime<Utc>>,
+  /// Filter articles published at or before this time.
+  #[serde(rename = "end", skip_serializing_if = "Option::is_none")]
+  pub end: Option<DateTime<Utc>>,
+  /// Whether to include the content of articles, if available.
+  #[serde(rename = "include_content", skip_serializing_if = "Option::is_none")]
+  pub include_content: Option<bool>,
+  /// Whether to exclude articles without content.
+  #[serde(
+    rename = "exclude_contentless",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub exclude_contentless: Option<bool>,
+  /// The maximum number of articles to be returned for each page.
+  ///
+  /// It can be between 1 and 50. Defaults to 10 if the provided value
+  /// is None.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided we will pass a page token to continue where we left
+  /// off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`ListReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct ListReqInit {
+  /// See `ListReq::start`.
+  pub start: Option<DateTime<Utc>>,
+  /// See `ListReq::end`.
+  pub end: Option<DateTime<Utc>>,
+  /// See `ListReq::include_content`.
+  pub include_content: Option<bool>,
+  /// See `ListReq::exclude_contentless`.
+  pub exclude_contentless: Option<bool>,
+  /// See `ListReq::limit`.
+  pub limit: Option<usize>,
+  /// See `ListReq::page_token`.
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl ListReqInit {
+  /// Create a [`ListReq`] from a `ListReqInit`.
+  #[inline]
+  pub fn init<I, S>(self, symbols: I) -> ListReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    ListReq {
+      symbols: symbols.into_iter().map(S::into).collect(),
+      start: self.start,
+      end: self.end,
+      include_content: self.include_content,
+      exclude_contentless: self.exclude_contentless,
+      limit: self.limit,
+      page_token: self.page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// An enumeration of the sizes images of news articles are provided
+/// in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum ImageSize {
+  /// A thumbnail.
+  #[serde(rename = "thumb")]
+  Thumb,
+  /// A small image.
+  #[serde(rename = "small")]
+  Small,
+  /// A large image.
+  #[serde(rename = "large")]
+  Large,
+  /// Any other image size that we have not accounted for.
+  ///
+  /// Note that having any such unknown image size should be considered
+  /// a bug.
+  #[doc(hidden)]
+  #[serde(other, rename(serialize = "unknown"))]
+  Unknown,
+}
+
+
+/// An image associated with a news article.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Image {
+  /// The size of the image.
+  #[serde(rename = "size")]
+  pub size: ImageSize,
+  /// The URL of the image.
+  #[serde(rename = "url")]
+  pub url: String,
+}
+
+
+/// A news article.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NewsArticle {
+  /// The article's ID.
+  #[serde(rename = "id")]
+  pub id: u64,
+  /// The article's headline.
+  #[serde(rename = "headline")]
+  pub headline: String,
+  /// A summary of the article, which may be empty.
+  #[serde(rename = "summary")]
+  pub summary: String,
+  /// The author of the article.
+  #[serde(rename = "author")]
+  pub author: String,
+  /// The content of the article.
+  ///
+  /// This member is only populated if requested and may contain
+  /// HTML.
+  #[serde(rename = "content", default)]
+  pub content: String,
+  /// The URL of the article, if any.
+  #[serde(rename = "url")]
+  pub url: Option<String>,
+  /// The images associated with the article.
+  ///
+  /// Images are not reported for articles received over a realtime
+  /// stream.
+  #[serde(rename = "images", default)]
+  pub images: Vec<Image>,
+  /// The symbols the article relates to.
+  #[serde(rename = "symbols")]
+  pub symbols: Vec<String>,
+  /// The source of the article (e.g., "benzinga").
+  #[serde(rename = "source")]
+  pub source: String,
+  /// The time the article was created at.
+  #[serde(rename = "created_at")]
+  pub created_at: DateTime<Utc>,
+  /// The time the article was last updated at.
+  #[serde(rename = "updated_at")]
+  pub updated_at: DateTime<Utc>,
+}
+
+
+/// A collection of news articles as returned by the API. This is one
+/// page of articles.
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+pub struct News {
+  /// The list of returned articles.
+  #[serde(rename = "news")]
+  pub articles: Vec<NewsArticle>,
+  /// The token to provide to a request to get the next page of
+  /// articles for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the /v1beta1/news endpoint.
+  pub List(ListReq),
+  Ok => News, [
+    /// The news articles were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => ListError, [
+    /// A query parameter was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1beta1/news".into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+impl Paginated for List {
+  type Item = NewsArticle;
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.articles, output.next_page_token)
+  }
+}
+
+
+/// Use Alpaca's realtime news as the data source.
+///
+/// News articles are reported as
+/// [`Data::News`][crate::data::v2::stream::Data::News] objects and can
+/// be subscribed to via the
+/// [`news`][crate::data::v2::stream::MarketData::news] member of
+/// [`MarketData`][crate::data::v2::stream::MarketData].
+#[derive(Clone, Copy, Debug)]
+pub enum NewsSource {}
+
+impl Source for NewsSource {
+  type Bar = Bar;
+  type Quote = Quote;
+  type Trade = Trade;
+
+  #[inline]
+  fn source() -> SourceVariant {
+    SourceVariant::Path("v1beta1/news".into())
+  }
+}
+
+impl private::Sealed for NewsSource {}
+
+
+/// A type used for requesting a subscription to realtime news.
+///
+/// The stream uses the same handshake and subscription protocol as
+/// realtime market data. Usage could be as follows:
+/// ```no_run
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # use apca::data::v1beta1::news::RealtimeNews;
+/// # use apca::data::v2::stream::drive;
+/// # use apca::data::v2::stream::MarketData;
+/// # use apca::data::v2::stream::Symbols;
+/// # use futures::FutureExt as _;
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async move {
+/// let api_info = ApiInfo::from_env().unwrap();
+/// let client = Client::new(api_info);
+/// let (mut stream, mut subscription) = client.subscribe::<RealtimeNews>().await.unwrap();
+///
+/// let data = MarketData {
+///   news: Symbols::All,
+///   ..Default::default()
+/// };
+/// let subscribe = subscription.subscribe(&data).boxed();
+/// let () = drive(subscribe, &mut stream)
+///   .await
+///   .unwrap()
+///   .unwrap()
+///   .unwrap();
+/// # })
+/// ```
+pub type RealtimeNews = RealtimeData<NewsSource>;
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use futures::future::join;
+  use futures::future::ready;
+  use futures::SinkExt as _;
+  use futures::StreamExt as _;
+  use futures::TryStreamExt as _;
+
+  use http::Request;
+  use http::Response;
+
+  use http_endpoint::Endpoint as _;
+
+  use hyper::body::Bytes;
+  use hyper::Body;
+
+  use serde_json::from_str as from_json;
+
+  use test_log::test;
+
+  use websocket_util::test::WebSocketStream;
+  use websocket_util::tungstenite::Error as WebSocketError;
+  use websocket_util::tungstenite::Message;
+
+  use crate::data::v2::stream::Data;
+  use crate::data::v2::stream::MarketData;
+  use crate::websocket::test::mock_stream;
+  use crate::ApiInfo;
+  use crate::Client;
+  use crate::Error;
+
+
+  /// Check that we do not fail parsing an image of an unknown size.
+  #[test]
+  fn parse_unknown_image_size() {
+    let response = r#"{"size": "huge", "url": "https://example.com/image.jpeg"}"#;
+    let image = from_json::<Image>(response).unwrap();
+    assert_eq!(image.size, ImageSize::Unknown);
+  }
+
+  /// Check that we can parse the reference news from the
+  /// documentation.
+  #[test]
+  fn parse_reference_news() {
+    let response = r#"{
+      "news": [
+        {
+          "author": "Benzinga Insights",
+          "content": "",
+          "created_at": "2024-01-02T15:04:05Z",
+          "headline": "Apple Shares Are Trading Lower",
+          "id": 36426541,
+          "images": [
+            {
+              "size": "large",
+              "url": "https://cdn.benzinga.com/files/images/story/2024/apple_2.jpeg?width=2048&height=1536"
+            },
+            {
+              "size": "thumb",
+              "url": "https://cdn.benzinga.com/files/images/story/2024/apple_2.jpeg?width=250&height=187"
+            }
+          ],
+          "source": "benzinga",
+          "summary": "Shares of Apple are trading lower after an analyst downgrade.",
+          "symbols": ["AAPL"],
+          "updated_at": "2024-01-02T15:04:06Z",
+          "url": "https://www.benzinga.com/news/24/01/36426541/apple-shares-are-trading-lower"
+        }
+      ],
+      "next_page_token": "MTcwNDIwNzg0NTAwMDAwMDAwMHwzNjQyNjU0MQ=="
+    }"#;
+
+    let news = from_json::<News>(response).unwrap();
+    assert_eq!(news.articles.len(), 1);
+    assert_eq!(
+      news.next_page_token.as_deref(),
+      Some("MTcwNDIwNzg0NTAwMDAwMDAwMHwzNjQyNjU0MQ==")
+    );
+
+    let article = &news.articles[0];
+    assert_eq!(article.id, 36426541);
+    assert_eq!(article.headline, "Apple Shares Are Trading Lower");
+    assert_eq!(article.author, "Benzinga Insights");
+    assert_eq!(article.content, "");
+    assert_eq!(article.symbols, vec!["AAPL"]);
+    assert_eq!(article.images.len(), 2);
+    assert_eq!(article.images[0].size, ImageSize::Large);
+    assert_eq!(article.images[1].size, ImageSize::Thumb);
+    assert_eq!(
+      article.created_at,
+      DateTime::<Utc>::from_str("2024-01-02T15:04:05Z").unwrap()
+    );
+  }
+
+  /// Check that we serialize only the filters that were set.
+  #[test]
+  fn serialize_request() {
+    let req = ListReqInit::default().init::<_, String>([]);
+    assert_eq!(List::path(&req), "/v1beta1/news");
+    assert_eq!(List::query(&req).unwrap().unwrap(), "");
+
+    let req = ListReqInit {
+      start: Some(DateTime::from_str("2024-01-02T00:00:00Z").unwrap()),
+      include_content: Some(true),
+      exclude_contentless: Some(false),
+      limit: Some(50),
+      ..Default::default()
+    }
+    .init(["AAPL", "BTCUSD"]);
+
+    let query = List::query(&req).unwrap().unwrap();
+    let expected = "symbols=AAPL%2CBTCUSD&start=2024-01-02T00%3A00%3A00Z&include_content=true&exclude_contentless=false&limit=50";
+    assert_eq!(query, expected);
+  }
+
+  /// Check that we can page through news articles.
+  #[test(tokio::test)]
+  async fn paginate_news() {
+    let transport = |request: Request<Bytes>| {
+      assert_eq!(request.uri().path(), "/v1beta1/news");
+
+      let query = request.uri().query().unwrap_or_default();
+      let (id, next) = if query.contains("page_token=next") {
+        (2, "null")
+      } else {
+        (1, r#""next""#)
+      };
+      let body = format!(
+        r#"{{"news":[{{"author":"","content":"","created_at":"2024-01-02T15:04:05Z","headline":"Headline {id}","id":{id},"images":[],"source":"benzinga","summary":"","symbols":["AAPL"],"updated_at":"2024-01-02T15:04:05Z","url":null}}],"next_page_token":{next}}}"#
+      );
+      Response::new(Body::from(body))
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+
+    let req = ListReqInit::default().init(["AAPL"]);
+    let articles = client
+      .paginate::<List>(req)
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(
+      articles
+        .iter()
+        .map(|article| article.id)
+        .collect::<Vec<_>>(),
+      vec![1, 2]
+    );
+    assert_eq!(articles[1].headline, "Headline 2");
+    assert_eq!(articles[1].url, None);
+  }
+
+  /// Check that we can stream news articles.
+  #[test(tokio::test)]
+  async fn stream_news() {
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      stream
+        .send(Message::Text(
+          r#"[{"T":"success","msg":"connected"}]"#.to_string(),
+        ))
+        .await?;
+      // Authentication.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(
+          r#"{"action":"auth","key":"USER12345678","secret":"justletmein"}"#.to_string()
+        ),
+      );
+      stream
+        .send(Message::Text(
+          r#"[{"T":"success","msg":"authenticated"}]"#.to_string(),
+        ))
+        .await?;
+
+      // Subscription.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(
+          r#"{"action":"subscribe","bars":[],"quotes":[],"trades":[],"news":["AAPL"]}"#.to_string()
+        ),
+      );
+      stream
+        .send(Message::Text(
+          r#"[{"T":"subscription","news":["AAPL"]}]"#.to_string(),
+        ))
+        .await?;
+      stream
+        .send(Message::Text(
+          r#"[{"T":"n","id":24918784,"headline":"Apple Q1 Earnings","summary":"","author":"Benzinga Newsdesk","created_at":"2024-01-05T22:00:37Z","updated_at":"2024-01-05T22:00:38Z","url":"https://www.benzinga.com/node/24918784","content":"","symbols":["AAPL"],"source":"benzinga"}]"#
+            .to_string(),
+        ))
+        .await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let (stream, mut subscription) = mock_stream::<RealtimeNews, _, _>(test).await.unwrap();
+
+    let mut data = MarketData::default();
+    data.set_news(["AAPL"]);
+
+    let subscribe = subscription.subscribe(&data);
+    let collect = stream
+      .map_err(Error::WebSocket)
+      .and_then(ready)
+      .try_collect::<Vec<_>>();
+    let (result, updates) = join(subscribe, collect).await;
+    let () = result.unwrap().unwrap();
+    let updates = updates.unwrap();
+
+    assert_eq!(subscription.subscriptions(), &data);
+    assert_eq!(updates.len(), 1);
+
+    match &updates[0] {
+      Data::News(article) => {
+        assert_eq!(article.id, 24918784);
+        assert_eq!(article.headline, "Apple Q1 Earnings");
+        assert_eq!(article.symbols, vec!["AAPL"]);
+        assert!(article.images.is_empty());
+      },
+      data => panic!("received unexpected data: {data:?}"),
+    }
+  }
+
+  /// Check that we can retrieve news for a symbol.
+  #[test(tokio::test)]
+  async fn request_news() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = ListReqInit {
+      limit: Some(5),
+      ..Default::default()
+    }
+    .init(["AAPL"]);
+    let news = client.issue::<List>(&req).await.unwrap();
+
+    assert!(!news.articles.is_empty());
+    assert!(news.articles.len() <= 5);
+    for article in news.articles {
+      assert!(article.symbols.iter().any(|symbol| symbol == "AAPL"));
+    }
+  }
+}
@@ -0,0 +1,230 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::NaiveDate;
+
+use num_decimal::Num;
+
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v1beta1::options::OptionType;
+use crate::data::v2::Feed;
+use crate::data::DATA_BASE_URL;
+use crate::Paginated;
+use crate::Str;
+
+/// A snapshot of an option contract of a chain.
+pub use super::snapshots::Snapshot;
+/// A page of snapshots of the option contracts of a chain.
+pub use super::snapshots::Snapshots;
+
+
+/// A GET request to be made to the
+/// /v1beta1/options/snapshots/{underlying} endpoint, retrieving the
+/// option chain of an underlying.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ListReq {
+  /// The symbol of the underlying.
+  #[serde(skip)]
+  pub underlying: String,
+  /// The data feed to use.
+  #[serde(rename = "feed", skip_serializing_if = "Option::is_none")]
+  pub feed: Option<Feed>,
+  /// Filter contracts by type.
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub option_type: Option<OptionType>,
+  /// Filter contracts with a strike price equal to or above this
+  /// value.
+  #[serde(rename = "strike_price_gte", skip_serializing_if = "Option::is_none")]
+  pub strike_price_gte: Option<Num>,
+  /// Filter contracts with a strike price equal to or below this
+  /// value.
+  #[serde(rename = "strike_price_lte", skip_serializing_if = "Option::is_none")]
+  pub strike_price_lte: Option<Num>,
+  /// Filter contracts expiring on this date.
+  #[serde(rename = "expiration_date", skip_serializing_if = "Option::is_none")]
+  pub expiration_date: Option<NaiveDate>,
+  /// Filter contracts expiring on or after this date.
+  #[serde(
+    rename = "expiration_date_gte",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub expiration_date_gte: Option<NaiveDate>,
+  /// Filter contracts expiring on or before this date.
+  #[serde(
+    rename = "expiration_date_lte",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub expiration_date_lte: Option<NaiveDate>,
+  /// The maximum number of snapshots to be returned for each page.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided we will pass a page token to continue where we left
+  /// off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`ListReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ListReqInit {
+  /// See `ListReq::feed`.
+  pub feed: Option<Feed>,
+  /// See `ListReq::option_type`.
+  pub option_type: Option<OptionType>,
+  /// See `ListReq::strike_price_gte`.
+  pub strike_price_gte: Option<Num>,
+  /// See `ListReq::strike_price_lte`.
+  pub strike_price_lte: Option<Num>,
+  /// See `ListReq::expiration_date`.
+  pub expiration_date: Option<NaiveDate>,
+  /// See `ListReq::expiration_date_gte`.
+  pub expiration_date_gte: Option<NaiveDate>,
+  /// See `ListReq::expiration_date_lte`.
+  pub expiration_date_lte: Option<NaiveDate>,
+  /// See `ListReq::limit`.
+  pub limit: Option<usize>,
+  /// See `ListReq::page_token`.
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl ListReqInit {
+  /// Create a [`ListReq`] from a `ListReqInit`.
+  #[inline]
+  pub fn init<S>(self, underlying: S) -> ListReq
+  where
+    S: Into<String>,
+  {
+    ListReq {
+      underlying: underlying.into(),
+      feed: self.feed,
+      option_type: self.option_type,
+      strike_price_gte: self.strike_price_gte,
+      strike_price_lte: self.strike_price_lte,
+      expiration_date: self.expiration_date,
+      expiration_date_gte: self.expiration_date_gte,
+      expiration_date_lte: self.expiration_date_lte,
+      limit: self.limit,
+      page_token: self.page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1beta1/options/snapshots/{underlying} endpoint.
+  pub List(ListReq),
+  Ok => Snapshots, [
+    /// The option chain was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => ListError, [
+    /// A query parameter was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("/v1beta1/options/snapshots/{}", input.underlying).into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+impl Paginated for List {
+  /// A snapshot along with the symbol of the contract it belongs to.
+  type Item = (String, Snapshot);
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (
+      output.snapshots.into_iter().collect(),
+      output.next_page_token,
+    )
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint as _;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  /// Check that we create the expected path and query for a chain
+  /// request.
+  #[test]
+  fn serialize_request() {
+    let req = ListReqInit::default().init("AAPL");
+    assert_eq!(List::path(&req), "/v1beta1/options/snapshots/AAPL");
+    assert_eq!(List::query(&req).unwrap().unwrap(), "");
+
+    let req = ListReqInit {
+      feed: Some(Feed::OPRA),
+      option_type: Some(OptionType::Put),
+      strike_price_gte: Some(Num::new(1875, 10)),
+      strike_price_lte: Some(Num::from(195)),
+      expiration_date: Some(NaiveDate::from_ymd_opt(2024, 1, 19).unwrap()),
+      limit: Some(100),
+      ..Default::default()
+    }
+    .init("AAPL");
+
+    let query = List::query(&req).unwrap().unwrap();
+    let expected = "feed=opra&type=put&strike_price_gte=187.5&strike_price_lte=195&expiration_date=2024-01-19&limit=100";
+    assert_eq!(query, expected);
+  }
+
+  /// Check that we can retrieve the option chain of an underlying.
+  #[test(tokio::test)]
+  async fn request_chain() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = ListReqInit {
+      feed: Some(Feed::Indicative),
+      option_type: Some(OptionType::Call),
+      limit: Some(10),
+      ..Default::default()
+    }
+    .init("SPY");
+    let chain = client.issue::<List>(&req).await.unwrap();
+    assert!(chain.snapshots.len() <= 10);
+    assert!(chain
+      .snapshots
+      .keys()
+      .all(|symbol| symbol.starts_with("SPY")));
+  }
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Historic bars and trades as well as the latest quotes and trades of
+//! option contracts are available through the corresponding
+//! `data::v2` endpoints, by using
+//! [`MarketPrefix::Options`][crate::data::v2::prefix::MarketPrefix::Options].
+
+mod symbol;
+
+/// Functionality for retrieving option chains.
+pub mod chain;
+/// Functionality for retrieving snapshots of option contracts.
+pub mod snapshots;
+
+pub use symbol::OptionSymbol;
+pub use symbol::OptionType;
+pub use symbol::ParseOptionSymbolError;
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::collections::BTreeMap;
+  use std::str::FromStr as _;
+
+  use chrono::DateTime;
+  use chrono::NaiveDate;
+
+  use futures::TryStreamExt as _;
+
+  use num_decimal::Num;
+
+  use test_log::test;
+
+  use crate::data::v2::bars;
+  use crate::data::v2::bars::Sort;
+  use crate::data::v2::bars::TimeFrame;
+  use crate::data::v2::last_quotes;
+  use crate::data::v2::prefix::MarketPrefix;
+  use crate::data::v2::trades;
+  use crate::data::v2::Feed;
+  use crate::ApiInfo;
+  use crate::Cassette;
+  use crate::CassetteMode;
+  use crate::Client;
+
+
+  /// Create a `Client` replaying the recorded options market data
+  /// exchanges.
+  fn client() -> Client {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/options.json");
+    let cassette = Cassette::new(path, CassetteMode::Replay).unwrap();
+    let api_info =
+      ApiInfo::from_parts("https://paper-api.alpaca.markets", "KEYID", "SECRET").unwrap();
+    Client::builder().cassette(cassette).build(api_info)
+  }
+
+
+  /// Check that we can retrieve historic bars for an option contract.
+  #[test(tokio::test)]
+  async fn option_bars() {
+    let symbol = OptionSymbol::from_str("AAPL240119C00190000").unwrap();
+    let start = DateTime::from_str("2024-01-02T00:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-04T00:00:00Z").unwrap();
+    let request = bars::ListReqInit::default().init(
+      symbol.to_string(),
+      MarketPrefix::Options,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
+
+    let bars = client().issue::<bars::List>(&request).await.unwrap();
+    assert_eq!(bars.symbol, "AAPL240119C00190000");
+    assert_eq!(bars.bars.len(), 2);
+    assert_eq!(bars.bars[0].open, Num::new(375, 100));
+    assert_eq!(bars.bars[1].close, Num::new(285, 100));
+    assert_eq!(bars.bars[1].volume, Num::from(2540));
+  }
+
+  /// Check that we can retrieve historic trades for an option
+  /// contract.
+  #[test(tokio::test)]
+  async fn option_trades() {
+    let start = DateTime::from_str("2024-01-02T20:58:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T21:00:00Z").unwrap();
+    let request =
+      trades::ListReqInit::default().init("AAPL240119C00190000", MarketPrefix::Options, start, end);
+
+    let trades = client().issue::<trades::List>(&request).await.unwrap();
+    assert_eq!(trades.trades.len(), 2);
+    assert_eq!(trades.trades[0].price, Num::new(34, 10));
+    assert_eq!(trades.trades[1].size, Num::from(10));
+  }
+
+  /// Check that we can retrieve the latest quotes for option
+  /// contracts.
+  #[test(tokio::test)]
+  async fn option_latest_quotes() {
+    let request = last_quotes::GetReqInit {
+      prefix: MarketPrefix::Options,
+      feed: Some(Feed::Indicative),
+      ..Default::default()
+    }
+    .init(["AAPL240119C00190000", "AAPL240119P00190000"]);
+
+    let quotes = client().issue::<last_quotes::Get>(&request).await.unwrap();
+    assert_eq!(quotes.len(), 2);
+    assert_eq!(quotes[0].0, "AAPL240119C00190000");
+    assert_eq!(quotes[0].1.ask_price, Num::new(345, 100));
+    assert_eq!(quotes[1].0, "AAPL240119P00190000");
+    assert_eq!(quotes[1].1.bid_size, Num::from(5));
+  }
+
+  /// Check that we can screen an option chain, following page tokens.
+  #[test(tokio::test)]
+  async fn option_chain() {
+    let request = chain::ListReqInit {
+      option_type: Some(OptionType::Call),
+      strike_price_gte: Some(Num::from(190)),
+      strike_price_lte: Some(Num::from(195)),
+      expiration_date: Some(NaiveDate::from_ymd_opt(2024, 1, 19).unwrap()),
+      limit: Some(1),
+      ..Default::default()
+    }
+    .init("AAPL");
+
+    let client = client();
+    let chain = client
+      .paginate::<chain::List>(request)
+      .try_collect::<BTreeMap<_, _>>()
+      .await
+      .unwrap();
+    assert_eq!(chain.len(), 2);
+
+    let contracts = chain
+      .keys()
+      .map(|symbol| OptionSymbol::from_str(symbol).unwrap())
+      .collect::<Vec<_>>();
+    assert!(contracts.iter().all(
+      |contract| contract.underlying() == "AAPL" && contract.option_type() == OptionType::Call
+    ));
+    assert_eq!(contracts[0].strike(), &Num::from(190));
+    assert_eq!(contracts[1].strike(), &Num::from(195));
+
+    let snapshot = &chain["AAPL240119C00195000"];
+    let greeks = snapshot.greeks.as_ref().unwrap();
+    assert_eq!(greeks.delta, Num::new(3611, 10000));
+    assert_eq!(snapshot.implied_volatility, Some(Num::new(2049, 10000)));
+  }
+}
@@ -0,0 +1,329 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v2::bars::Bar;
+use crate::data::v2::last_quotes::Quote;
+use crate::data::v2::trades::Trade;
+use crate::data::v2::Feed;
+use crate::data::DATA_BASE_URL;
+use crate::util::map_from_str;
+use crate::util::string_slice_to_str;
+use crate::Paginated;
+use crate::Str;
+
+
+/// A GET request to be made to the /v1beta1/options/snapshots
+/// endpoint.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct GetReq {
+  /// The option contract symbols to retrieve snapshots for.
+  #[serde(rename = "symbols", serialize_with = "string_slice_to_str")]
+  pub symbols: Vec<String>,
+  /// The data feed to use.
+  #[serde(rename = "feed", skip_serializing_if = "Option::is_none")]
+  pub feed: Option<Feed>,
+  /// The maximum number of snapshots to be returned for each page.
+  #[serde(rename = "limit", skip_serializing_if = "Option::is_none")]
+  pub limit: Option<usize>,
+  /// If provided we will pass a page token to continue where we left
+  /// off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`GetReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct GetReqInit {
+  /// See `GetReq::feed`.
+  pub feed: Option<Feed>,
+  /// See `GetReq::limit`.
+  pub limit: Option<usize>,
+  /// See `GetReq::page_token`.
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl GetReqInit {
+  /// Create a [`GetReq`] from a `GetReqInit`.
+  #[inline]
+  pub fn init<I, S>(self, symbols: I) -> GetReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    GetReq {
+      symbols: symbols.into_iter().map(S::into).collect(),
+      feed: self.feed,
+      limit: self.limit,
+      page_token: self.page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// The greeks of an option contract.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Greeks {
+  /// The rate of change of the option's price with respect to the
+  /// price of the underlying.
+  #[serde(rename = "delta")]
+  pub delta: Num,
+  /// The rate of change of delta with respect to the price of the
+  /// underlying.
+  #[serde(rename = "gamma")]
+  pub gamma: Num,
+  /// The rate of change of the option's price with respect to time.
+  #[serde(rename = "theta")]
+  pub theta: Num,
+  /// The rate of change of the option's price with respect to the
+  /// implied volatility.
+  #[serde(rename = "vega")]
+  pub vega: Num,
+  /// The rate of change of the option's price with respect to the
+  /// risk-free interest rate.
+  #[serde(rename = "rho")]
+  pub rho: Num,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A snapshot of the market data of an option contract.
+///
+/// Members are `None` if no data are available, e.g., for a contract
+/// that has not been traded yet.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Snapshot {
+  /// The latest trade.
+  #[serde(rename = "latestTrade")]
+  pub latest_trade: Option<Trade>,
+  /// The latest quote.
+  #[serde(rename = "latestQuote")]
+  pub latest_quote: Option<Quote>,
+  /// The most recent minute bar.
+  #[serde(rename = "minuteBar")]
+  pub minute_bar: Option<Bar>,
+  /// The daily bar of the current (or most recent) trading day.
+  #[serde(rename = "dailyBar")]
+  pub daily_bar: Option<Bar>,
+  /// The daily bar of the trading day before that of `daily_bar`.
+  #[serde(rename = "prevDailyBar")]
+  pub prev_daily_bar: Option<Bar>,
+  /// The greeks of the contract.
+  #[serde(rename = "greeks")]
+  pub greeks: Option<Greeks>,
+  /// The implied volatility of the contract.
+  #[serde(rename = "impliedVolatility")]
+  pub implied_volatility: Option<Num>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A collection of snapshots as returned by the API. This is one page
+/// of snapshots.
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+pub struct Snapshots {
+  /// A mapping from option contract symbols to snapshots.
+  #[serde(rename = "snapshots", deserialize_with = "map_from_str")]
+  pub snapshots: BTreeMap<String, Snapshot>,
+  /// The token to provide to a request to get the next page of
+  /// snapshots for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+Endpoint! {
+  /// The representation of a GET request to the
+  /// /v1beta1/options/snapshots endpoint.
+  pub Get(GetReq),
+  Ok => Snapshots, [
+    /// The snapshots were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// A query parameter was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(_input: &Self::Input) -> Str {
+    "/v1beta1/options/snapshots".into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+impl Paginated for Get {
+  /// A snapshot along with the symbol of the contract it belongs to.
+  type Item = (String, Snapshot);
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (
+      output.snapshots.into_iter().collect(),
+      output.next_page_token,
+    )
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint as _;
+
+  use serde_json::from_str as from_json;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::Client;
+
+
+  /// Check that we can parse the reference snapshots from the
+  /// documentation.
+  #[test]
+  fn parse_reference_snapshots() {
+    let response = r#"{
+      "next_page_token": null,
+      "snapshots": {
+        "AAPL240119C00190000": {
+          "greeks": {
+            "delta": 0.5234,
+            "gamma": 0.0412,
+            "rho": 0.0121,
+            "theta": -0.1875,
+            "vega": 0.0913
+          },
+          "impliedVolatility": 0.2163,
+          "latestQuote": {
+            "ap": 3.45,
+            "as": 12,
+            "ax": "C",
+            "bp": 3.35,
+            "bs": 7,
+            "bx": "X",
+            "c": "A",
+            "t": "2024-01-02T20:59:59.123456Z"
+          },
+          "latestTrade": {
+            "c": "I",
+            "p": 3.4,
+            "s": 2,
+            "t": "2024-01-02T20:58:12.654321Z",
+            "x": "C"
+          }
+        },
+        "AAPL240119P00190000": {
+          "latestQuote": {
+            "ap": 2.2,
+            "as": 3,
+            "ax": "N",
+            "bp": 2.1,
+            "bs": 5,
+            "bx": "N",
+            "c": " ",
+            "t": "2024-01-02T20:59:58Z"
+          }
+        }
+      }
+    }"#;
+
+    let snapshots = from_json::<Snapshots>(response).unwrap();
+    assert_eq!(snapshots.next_page_token, None);
+    assert_eq!(snapshots.snapshots.len(), 2);
+
+    let call = &snapshots.snapshots["AAPL240119C00190000"];
+    let greeks = call.greeks.as_ref().unwrap();
+    assert_eq!(greeks.delta, Num::new(5234, 10000));
+    assert_eq!(greeks.theta, Num::new(-1875, 10000));
+    assert_eq!(call.implied_volatility, Some(Num::new(2163, 10000)));
+    assert_eq!(call.latest_quote.as_ref().unwrap().ask_size, Num::from(12));
+    assert_eq!(call.latest_trade.as_ref().unwrap().price, Num::new(34, 10));
+    assert_eq!(call.minute_bar, None);
+
+    let put = &snapshots.snapshots["AAPL240119P00190000"];
+    assert_eq!(put.greeks, None);
+    assert_eq!(put.implied_volatility, None);
+    assert_eq!(put.latest_trade, None);
+    assert_eq!(
+      put.latest_quote.as_ref().unwrap().bid_price,
+      Num::new(21, 10)
+    );
+  }
+
+  /// Check that we create the expected query for a request.
+  #[test]
+  fn serialize_request() {
+    let req = GetReqInit {
+      feed: Some(Feed::Indicative),
+      ..Default::default()
+    }
+    .init(["AAPL240119C00190000", "AAPL240119P00190000"]);
+
+    assert_eq!(Get::path(&req), "/v1beta1/options/snapshots");
+    let query = Get::query(&req).unwrap().unwrap();
+    assert_eq!(
+      query,
+      "symbols=AAPL240119C00190000%2CAAPL240119P00190000&feed=indicative"
+    );
+  }
+
+  /// Check that we can retrieve snapshots of option contracts.
+  #[test(tokio::test)]
+  async fn request_snapshots() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = GetReqInit {
+      feed: Some(Feed::Indicative),
+      ..Default::default()
+    }
+    .init(["AAPL260116C00200000"]);
+    let snapshots = client.issue::<Get>(&req).await.unwrap();
+    assert!(snapshots.snapshots.len() <= 1);
+  }
+}
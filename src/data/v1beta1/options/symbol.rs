@@ -0,0 +1,389 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+
+use chrono::Datelike as _;
+use chrono::NaiveDate;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::Serializer;
+
+use thiserror::Error;
+
+
+/// The maximum length of the underlying's symbol in an OCC symbol.
+const MAX_UNDERLYING_LEN: usize = 6;
+/// The length of the part of an OCC symbol following the underlying.
+const SUFFIX_LEN: usize = 15;
+/// The factor strike prices are scaled with in OCC symbols.
+const STRIKE_SCALE: u64 = 1000;
+/// The maximum scaled strike price representable in an OCC symbol.
+const MAX_SCALED_STRIKE: u64 = 99_999_999;
+
+
+/// An enumeration of the different option types.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum OptionType {
+  /// A call option.
+  #[serde(rename = "call")]
+  Call,
+  /// A put option.
+  #[serde(rename = "put")]
+  Put,
+}
+
+
+/// An enumeration of all possible option symbol parsing errors.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[non_exhaustive]
+pub enum ParseOptionSymbolError {
+  /// The symbol of the underlying is empty, too long, or contains an
+  /// invalid character.
+  #[error("the underlying symbol is invalid")]
+  InvalidUnderlying,
+  /// The expiration date is invalid or not representable.
+  #[error("the expiration date is invalid")]
+  InvalidExpiration,
+  /// The option type is neither call ('C') nor put ('P').
+  #[error("the option type is invalid ('{0}')")]
+  InvalidType(char),
+  /// The strike price is negative, too large, or has more than three
+  /// decimal places.
+  #[error("the strike price is invalid")]
+  InvalidStrike,
+  /// The symbol has an invalid/unrecognized format.
+  #[error("the symbol is of an invalid format")]
+  InvalidFormat,
+}
+
+
+/// An option contract symbol in the format used by the Options
+/// Clearing Corporation (OCC), e.g., `AAPL240119C00190000`.
+///
+/// Such a symbol is comprised of the symbol of the underlying, the
+/// expiration date (`YYMMDD`), the option type (`C` or `P`), and the
+/// strike price multiplied by 1000 and padded to eight digits.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub struct OptionSymbol {
+  /// The symbol of the underlying.
+  underlying: String,
+  /// The expiration date.
+  expiration: NaiveDate,
+  /// The option type.
+  option_type: OptionType,
+  /// The strike price.
+  strike: Num,
+}
+
+impl OptionSymbol {
+  /// Create an `OptionSymbol` from its parts.
+  pub fn new<S>(
+    underlying: S,
+    expiration: NaiveDate,
+    option_type: OptionType,
+    strike: Num,
+  ) -> Result<Self, ParseOptionSymbolError>
+  where
+    S: Into<String>,
+  {
+    let underlying = underlying.into();
+    let () = check_underlying(&underlying)?;
+
+    if !(2000..=2099).contains(&expiration.year()) {
+      return Err(ParseOptionSymbolError::InvalidExpiration)
+    }
+
+    let scaled = &strike * STRIKE_SCALE;
+    if scaled.is_negative() || !scaled.fract().is_zero() {
+      return Err(ParseOptionSymbolError::InvalidStrike)
+    }
+    match scaled.to_u64() {
+      Some(scaled) if scaled <= MAX_SCALED_STRIKE => (),
+      _ => return Err(ParseOptionSymbolError::InvalidStrike),
+    }
+
+    Ok(Self {
+      underlying,
+      expiration,
+      option_type,
+      strike,
+    })
+  }
+
+  /// Retrieve the symbol of the underlying.
+  #[inline]
+  pub fn underlying(&self) -> &str {
+    &self.underlying
+  }
+
+  /// Retrieve the expiration date.
+  #[inline]
+  pub fn expiration(&self) -> NaiveDate {
+    self.expiration
+  }
+
+  /// Retrieve the option type.
+  #[inline]
+  pub fn option_type(&self) -> OptionType {
+    self.option_type
+  }
+
+  /// Retrieve the strike price.
+  #[inline]
+  pub fn strike(&self) -> &Num {
+    &self.strike
+  }
+}
+
+/// Check that the provided underlying symbol can be used in an OCC
+/// symbol.
+fn check_underlying(underlying: &str) -> Result<(), ParseOptionSymbolError> {
+  let valid = !underlying.is_empty()
+    && underlying.len() <= MAX_UNDERLYING_LEN
+    && underlying
+      .bytes()
+      .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+  if valid {
+    Ok(())
+  } else {
+    Err(ParseOptionSymbolError::InvalidUnderlying)
+  }
+}
+
+impl TryFrom<&str> for OptionSymbol {
+  type Error = ParseOptionSymbolError;
+
+  fn try_from(other: &str) -> Result<Self, Self::Error> {
+    OptionSymbol::from_str(other)
+  }
+}
+
+impl TryFrom<String> for OptionSymbol {
+  type Error = ParseOptionSymbolError;
+
+  fn try_from(other: String) -> Result<Self, Self::Error> {
+    OptionSymbol::from_str(&other)
+  }
+}
+
+impl FromStr for OptionSymbol {
+  type Err = ParseOptionSymbolError;
+
+  fn from_str(sym: &str) -> Result<Self, Self::Err> {
+    if !sym.is_ascii() || sym.len() <= SUFFIX_LEN {
+      return Err(ParseOptionSymbolError::InvalidFormat)
+    }
+
+    let (underlying, suffix) = sym.split_at(sym.len() - SUFFIX_LEN);
+    let (date, rest) = suffix.split_at(6);
+    let (option_type, strike) = rest.split_at(1);
+
+    let () = check_underlying(underlying)?;
+
+    let number = |s: &str| -> Result<u32, ParseOptionSymbolError> {
+      if s.bytes().all(|c| c.is_ascii_digit()) {
+        s.parse().map_err(|_| ParseOptionSymbolError::InvalidFormat)
+      } else {
+        Err(ParseOptionSymbolError::InvalidFormat)
+      }
+    };
+    let year = number(&date[0..2])?;
+    let month = number(&date[2..4])?;
+    let day = number(&date[4..6])?;
+    // SANITY: `year` has at most two digits and so fits into an `i32`.
+    let expiration = NaiveDate::from_ymd_opt(2000 + year as i32, month, day)
+      .ok_or(ParseOptionSymbolError::InvalidExpiration)?;
+
+    let option_type = match option_type {
+      "C" => OptionType::Call,
+      "P" => OptionType::Put,
+      // SANITY: We checked that the symbol is ASCII and the type is a
+      //         single byte.
+      _ => {
+        return Err(ParseOptionSymbolError::InvalidType(
+          option_type.chars().next().unwrap(),
+        ))
+      },
+    };
+
+    let strike = Num::new(number(strike)?, STRIKE_SCALE);
+
+    Ok(Self {
+      underlying: underlying.to_string(),
+      expiration,
+      option_type,
+      strike,
+    })
+  }
+}
+
+impl Display for OptionSymbol {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    let option_type = match self.option_type {
+      OptionType::Call => 'C',
+      OptionType::Put => 'P',
+    };
+    // SANITY: The strike price was checked to be representable on
+    //         construction.
+    let strike = (&self.strike * STRIKE_SCALE).to_u64().unwrap();
+
+    write!(
+      fmt,
+      "{}{:02}{:02}{:02}{}{:08}",
+      self.underlying,
+      self.expiration.year() % 100,
+      self.expiration.month(),
+      self.expiration.day(),
+      option_type,
+      strike,
+    )
+  }
+}
+
+impl Serialize for OptionSymbol {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(&self.to_string())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+  use serde_json::from_value as from_json_value;
+  use serde_json::to_string as to_json;
+  use serde_json::to_value as to_json_value;
+
+
+  /// Check that we can parse OCC symbols.
+  #[test]
+  fn parse_symbol() {
+    let symbol = OptionSymbol::from_str("AAPL240119C00190000").unwrap();
+    assert_eq!(symbol.underlying(), "AAPL");
+    assert_eq!(
+      symbol.expiration(),
+      NaiveDate::from_ymd_opt(2024, 1, 19).unwrap()
+    );
+    assert_eq!(symbol.option_type(), OptionType::Call);
+    assert_eq!(symbol.strike(), &Num::from(190));
+
+    let symbol = OptionSymbol::from_str("SPY250321P00412500").unwrap();
+    assert_eq!(symbol.underlying(), "SPY");
+    assert_eq!(symbol.option_type(), OptionType::Put);
+    assert_eq!(symbol.strike(), &Num::new(4125, 10));
+
+    // Adjusted contracts may carry a digit in the root symbol.
+    let symbol = OptionSymbol::from_str("TSLA1240119C00000500").unwrap();
+    assert_eq!(symbol.underlying(), "TSLA1");
+    assert_eq!(symbol.strike(), &Num::new(1, 2));
+  }
+
+  /// Check that we reject invalid OCC symbols.
+  #[test]
+  fn parse_invalid_symbol() {
+    let tests = [
+      ("", ParseOptionSymbolError::InvalidFormat),
+      ("240119C00190000", ParseOptionSymbolError::InvalidFormat),
+      ("AAPL240119C0019000€", ParseOptionSymbolError::InvalidFormat),
+      (
+        "aapl240119C00190000",
+        ParseOptionSymbolError::InvalidUnderlying,
+      ),
+      (
+        "ABCDEFG240119C00190000",
+        ParseOptionSymbolError::InvalidUnderlying,
+      ),
+      (
+        "AAPL241319C00190000",
+        ParseOptionSymbolError::InvalidExpiration,
+      ),
+      (
+        "AAPL240119X00190000",
+        ParseOptionSymbolError::InvalidType('X'),
+      ),
+      ("AAPL240119C0019000X", ParseOptionSymbolError::InvalidFormat),
+      ("AAPL24011AC00190000", ParseOptionSymbolError::InvalidFormat),
+    ];
+
+    for (symbol, expected) in tests {
+      assert_eq!(
+        OptionSymbol::from_str(symbol).unwrap_err(),
+        expected,
+        "{symbol}"
+      );
+    }
+  }
+
+  /// Check that we can build OCC symbols from their parts.
+  #[test]
+  fn build_symbol() {
+    let expiration = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+    let symbol = OptionSymbol::new("AAPL", expiration, OptionType::Call, Num::from(190)).unwrap();
+    assert_eq!(symbol.to_string(), "AAPL240119C00190000");
+
+    let symbol =
+      OptionSymbol::new("SPY", expiration, OptionType::Put, Num::new(41255, 100)).unwrap();
+    assert_eq!(symbol.to_string(), "SPY240119P00412550");
+
+    let err = OptionSymbol::new("AAPL", expiration, OptionType::Put, Num::new(1, 10000));
+    assert_eq!(err.unwrap_err(), ParseOptionSymbolError::InvalidStrike);
+
+    let err = OptionSymbol::new("AAPL", expiration, OptionType::Put, Num::from(100_000));
+    assert_eq!(err.unwrap_err(), ParseOptionSymbolError::InvalidStrike);
+
+    let err = OptionSymbol::new("AAPL", expiration, OptionType::Put, Num::from(-1));
+    assert_eq!(err.unwrap_err(), ParseOptionSymbolError::InvalidStrike);
+
+    let err = OptionSymbol::new("", expiration, OptionType::Put, Num::from(1));
+    assert_eq!(err.unwrap_err(), ParseOptionSymbolError::InvalidUnderlying);
+
+    let expiration = NaiveDate::from_ymd_opt(2100, 1, 1).unwrap();
+    let err = OptionSymbol::new("AAPL", expiration, OptionType::Put, Num::from(1));
+    assert_eq!(err.unwrap_err(), ParseOptionSymbolError::InvalidExpiration);
+  }
+
+  /// Check that parsing and formatting an OCC symbol round trips.
+  #[test]
+  fn round_trip_symbol() {
+    for symbol in [
+      "AAPL240119C00190000",
+      "X260618P00002500",
+      "TSLA1240119C00000500",
+    ] {
+      assert_eq!(OptionSymbol::from_str(symbol).unwrap().to_string(), symbol);
+    }
+  }
+
+  /// Check that we can serialize and deserialize an `OptionSymbol`.
+  #[test]
+  fn serialize_deserialize_symbol() {
+    let symbol = OptionSymbol::from_str("AAPL240119C00190000").unwrap();
+    let json = to_json(&symbol).unwrap();
+    assert_eq!(json, r#""AAPL240119C00190000""#);
+    assert_eq!(from_json::<OptionSymbol>(&json).unwrap(), symbol);
+
+    // Deserialization should not depend on being able to borrow
+    // from the input.
+    let value = to_json_value(&symbol).unwrap();
+    assert_eq!(from_json_value::<OptionSymbol>(value).unwrap(), symbol);
+    let json = r#""AAPL240119\u004300190000""#;
+    assert_eq!(from_json::<OptionSymbol>(json).unwrap(), symbol);
+
+    assert!(from_json::<OptionSymbol>(r#""AAPL""#).is_err());
+  }
+}
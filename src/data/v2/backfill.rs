@@ -0,0 +1,768 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use futures::future::BoxFuture;
+use futures::stream::FusedStream;
+use futures::FutureExt as _;
+use futures::Stream;
+use futures::StreamExt as _;
+
+use serde::de::DeserializeOwned;
+
+use tracing::debug;
+
+use crate::data::v2::bars;
+use crate::data::v2::bars::Sort;
+use crate::data::v2::bars::TimeFrame;
+use crate::data::v2::prefix::MarketPrefix;
+use crate::data::v2::reconnect::Event;
+use crate::data::v2::reconnect::Gap;
+use crate::data::v2::reconnect::ReconnectingData;
+use crate::data::v2::stream::Bar;
+use crate::data::v2::stream::CryptoBar;
+use crate::data::v2::stream::CryptoTrade;
+use crate::data::v2::stream::Data;
+use crate::data::v2::stream::Source;
+use crate::data::v2::stream::Symbols;
+use crate::data::v2::stream::Trade;
+use crate::data::v2::trades;
+use crate::data::v2::Feed;
+use crate::Client;
+use crate::Error;
+use crate::RequestError;
+
+
+type Item<B, Q, T> = Result<Event<B, Q, T>, Error>;
+type Fetched<B, T> = (Vec<B>, Vec<T>);
+type FetchFuture<B, T> = BoxFuture<'static, Result<Fetched<B, T>, Error>>;
+
+
+/// A trait for bars as received over a real time market data stream
+/// that can be backfilled from historic bars.
+pub trait BackfillBar: Sized {
+  /// Retrieve the bar's symbol.
+  fn symbol(&self) -> &str;
+
+  /// Retrieve the bar's time stamp.
+  fn timestamp(&self) -> DateTime<Utc>;
+
+  /// Create a bar for the given symbol from a historic one.
+  fn from_historic(symbol: &str, bar: bars::Bar) -> Self;
+}
+
+impl BackfillBar for Bar {
+  #[inline]
+  fn symbol(&self) -> &str {
+    &self.symbol
+  }
+
+  #[inline]
+  fn timestamp(&self) -> DateTime<Utc> {
+    self.timestamp
+  }
+
+  fn from_historic(symbol: &str, bar: bars::Bar) -> Self {
+    Self {
+      symbol: symbol.to_string(),
+      open_price: bar.open,
+      high_price: bar.high,
+      low_price: bar.low,
+      close_price: bar.close,
+      volume: bar.volume,
+      timestamp: bar.time,
+    }
+  }
+}
+
+impl BackfillBar for CryptoBar {
+  #[inline]
+  fn symbol(&self) -> &str {
+    &self.symbol
+  }
+
+  #[inline]
+  fn timestamp(&self) -> DateTime<Utc> {
+    self.timestamp
+  }
+
+  fn from_historic(symbol: &str, bar: bars::Bar) -> Self {
+    Self {
+      symbol: symbol.to_string(),
+      open_price: bar.open,
+      high_price: bar.high,
+      low_price: bar.low,
+      close_price: bar.close,
+      volume: bar.volume,
+      trade_count: bar.trade_count.unwrap_or_default(),
+      weighted_average: bar.weighted_average,
+      timestamp: bar.time,
+    }
+  }
+}
+
+
+/// A trait for trades as received over a real time market data stream
+/// that can be backfilled from historic trades.
+pub trait BackfillTrade: Sized {
+  /// Retrieve the trade's symbol.
+  fn symbol(&self) -> &str;
+
+  /// Retrieve the trade's time stamp.
+  fn timestamp(&self) -> DateTime<Utc>;
+
+  /// Retrieve the trade's ID.
+  fn trade_id(&self) -> u64;
+
+  /// Create a trade for the given symbol from a historic one.
+  ///
+  /// Historic trades lacking an ID can't be deduplicated and are
+  /// skipped by returning `None`.
+  fn from_historic(symbol: &str, trade: trades::Trade) -> Option<Self>;
+}
+
+impl BackfillTrade for Trade {
+  #[inline]
+  fn symbol(&self) -> &str {
+    &self.symbol
+  }
+
+  #[inline]
+  fn timestamp(&self) -> DateTime<Utc> {
+    self.timestamp
+  }
+
+  #[inline]
+  fn trade_id(&self) -> u64 {
+    self.trade_id
+  }
+
+  fn from_historic(symbol: &str, trade: trades::Trade) -> Option<Self> {
+    let trade = Self {
+      symbol: symbol.to_string(),
+      trade_id: trade.id?,
+      trade_price: trade.price,
+      trade_size: trade.size,
+      timestamp: trade.timestamp,
+    };
+    Some(trade)
+  }
+}
+
+impl BackfillTrade for CryptoTrade {
+  #[inline]
+  fn symbol(&self) -> &str {
+    &self.symbol
+  }
+
+  #[inline]
+  fn timestamp(&self) -> DateTime<Utc> {
+    self.timestamp
+  }
+
+  #[inline]
+  fn trade_id(&self) -> u64 {
+    self.trade_id
+  }
+
+  fn from_historic(symbol: &str, trade: trades::Trade) -> Option<Self> {
+    let trade = Self {
+      symbol: symbol.to_string(),
+      trade_id: trade.id?,
+      trade_price: trade.price,
+      trade_size: trade.size,
+      timestamp: trade.timestamp,
+    };
+    Some(trade)
+  }
+}
+
+
+/// The trades seen for a single symbol.
+#[derive(Debug, Default)]
+struct Seen {
+  /// The time stamp of the most recent trade.
+  timestamp: DateTime<Utc>,
+  /// The IDs of trades that may still be reported again.
+  ///
+  /// During regular operation these are only the trades with the most
+  /// recent time stamp. After a backfill they additionally include all
+  /// backfilled trades, until live trades have moved past them.
+  ids: HashSet<u64>,
+}
+
+impl Seen {
+  /// Check whether a trade should be emitted, recording it if so.
+  fn admit(&mut self, id: u64, timestamp: DateTime<Utc>, prune: bool) -> bool {
+    if self.ids.contains(&id) {
+      return false
+    }
+
+    if timestamp > self.timestamp {
+      if prune {
+        let () = self.ids.clear();
+      }
+      self.timestamp = timestamp;
+    }
+    self.ids.insert(id)
+  }
+}
+
+
+/// Convert an error as reported when requesting historic data into an
+/// [`Error`].
+fn request_error<E>(kind: &str, err: RequestError<E>) -> Error
+where
+  E: Display + StdError + 'static,
+{
+  let message = match err.source() {
+    Some(source) => format!("failed to backfill {kind}: {err}: {source}"),
+    None => format!("failed to backfill {kind}: {err}"),
+  };
+  Error::Str(message.into())
+}
+
+
+/// Determine the symbols to backfill along with the time stamps to
+/// start at.
+fn ranges<V>(
+  symbols: &Symbols,
+  seen: &HashMap<String, V>,
+  last: impl Fn(&V) -> DateTime<Utc>,
+  gap: &Gap,
+) -> BTreeMap<String, DateTime<Utc>> {
+  let symbols = match symbols {
+    Symbols::List(list) => list.iter().map(ToString::to_string).collect(),
+    // We can't enumerate "all" symbols, so we backfill the ones we have
+    // seen data for.
+    Symbols::All => seen.keys().cloned().collect::<Vec<_>>(),
+  };
+
+  symbols
+    .into_iter()
+    .filter_map(|symbol| {
+      let start = seen.get(&symbol).map(&last).unwrap_or(gap.disconnected_at);
+      (start < gap.reconnected_at).then_some((symbol, start))
+    })
+    .collect()
+}
+
+
+/// Retrieve the bars and trades published for the given symbols in the
+/// range from their respective start time stamp up to `end`.
+///
+/// Data for all symbols is retrieved with a single paginated request
+/// per type, starting at the earliest of the symbols' start time
+/// stamps.
+async fn fetch<B, T>(
+  client: Client,
+  prefix: MarketPrefix,
+  feed: Option<Feed>,
+  bar_ranges: BTreeMap<String, DateTime<Utc>>,
+  trade_ranges: BTreeMap<String, DateTime<Utc>>,
+  end: DateTime<Utc>,
+) -> Result<Fetched<B, T>, Error>
+where
+  B: BackfillBar,
+  T: BackfillTrade,
+{
+  let mut bars = Vec::new();
+  if let Some(start) = bar_ranges.values().min() {
+    let request = bars::MultiListReqInit {
+      feed,
+      ..Default::default()
+    }
+    .init(
+      bar_ranges.keys().cloned(),
+      prefix.clone(),
+      *start,
+      end,
+      TimeFrame::OneMinute,
+      Sort::Asc,
+    );
+
+    let historic = client
+      .paginate::<bars::MultiList>(request)
+      .collect_grouped()
+      .await
+      .map_err(|err| request_error("bars", err))?;
+
+    for (symbol, historic) in historic {
+      if let Some(start) = bar_ranges.get(&symbol) {
+        // The bar covering the minute we reconnected in is not yet
+        // complete and will be reported over the stream once it is.
+        bars.extend(
+          historic
+            .into_iter()
+            .filter(|bar| &bar.time >= start && bar.time + Duration::minutes(1) <= end)
+            .map(|bar| B::from_historic(&symbol, bar)),
+        );
+      }
+    }
+  }
+
+  let mut trades = Vec::new();
+  if let Some(start) = trade_ranges.values().min() {
+    let request = trades::MultiListReqInit {
+      feed,
+      ..Default::default()
+    }
+    .init(trade_ranges.keys().cloned(), prefix, *start, end);
+
+    let historic = client
+      .paginate::<trades::MultiList>(request)
+      .collect_grouped()
+      .await
+      .map_err(|err| request_error("trades", err))?;
+
+    for (symbol, historic) in historic {
+      if let Some(start) = trade_ranges.get(&symbol) {
+        trades.extend(
+          historic
+            .into_iter()
+            .filter(|trade| &trade.timestamp >= start)
+            .filter_map(|trade| T::from_historic(&symbol, trade)),
+        );
+      }
+    }
+  }
+
+  Ok((bars, trades))
+}
+
+
+/// A [`ReconnectingData`] stream that backfills bars and trades missed
+/// while the connection was lost.
+///
+/// The stream records the time stamp of the last bar and trade it saw
+/// for each symbol. Once the connection has been re-established, it
+/// retrieves the bars and trades published in the meantime through the
+/// [`bars::MultiList`] and [`trades::MultiList`] endpoints and emits them, in
+/// time stamp order, right after the [`Event::Reconnected`] event.
+/// Live data received while the backfill is in progress is held back
+/// until after. Data reported both ways is emitted only once: trades
+/// are deduplicated by their ID, bars by their time stamp.
+///
+/// Only minute bars are backfilled. Failure to retrieve historic data
+/// is reported as an error, but the stream continues.
+pub struct Backfilled<
+  S: Source,
+  B = <S as Source>::Bar,
+  Q = <S as Source>::Quote,
+  T = <S as Source>::Trade,
+> {
+  /// The stream we backfill.
+  inner: ReconnectingData<S, B, Q, T>,
+  /// The client used for retrieving historic data.
+  client: Client,
+  /// The market to retrieve historic data for.
+  prefix: MarketPrefix,
+  /// The feed to retrieve historic data from.
+  feed: Option<Feed>,
+  /// The time stamp of the last bar seen, per symbol.
+  bars: HashMap<String, DateTime<Utc>>,
+  /// The trades seen, per symbol.
+  trades: HashMap<String, Seen>,
+  /// Live items received while a backfill is in progress.
+  held: VecDeque<Item<B, Q, T>>,
+  /// Items ready to be emitted.
+  buffer: VecDeque<Item<B, Q, T>>,
+  /// The future retrieving historic data, if a backfill is in
+  /// progress.
+  fetch: Option<FetchFuture<B, T>>,
+}
+
+impl<S, B, Q, T> Backfilled<S, B, Q, T>
+where
+  S: Source + 'static,
+  B: BackfillBar + Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: BackfillTrade + Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  /// Create a new `Backfilled` stream, using `client` for retrieving
+  /// historic data for the market identified by `prefix` from the
+  /// given `feed`.
+  pub fn new(
+    inner: ReconnectingData<S, B, Q, T>,
+    client: Client,
+    prefix: MarketPrefix,
+    feed: Option<Feed>,
+  ) -> Self {
+    Self {
+      inner,
+      client,
+      prefix,
+      feed,
+      bars: HashMap::new(),
+      trades: HashMap::new(),
+      held: VecDeque::new(),
+      buffer: VecDeque::new(),
+      fetch: None,
+    }
+  }
+
+  /// Retrieve a reference to the wrapped stream.
+  #[inline]
+  pub fn get_ref(&self) -> &ReconnectingData<S, B, Q, T> {
+    &self.inner
+  }
+
+  /// Retrieve a mutable reference to the wrapped stream, e.g., for
+  /// changing subscriptions.
+  #[inline]
+  pub fn get_mut(&mut self) -> &mut ReconnectingData<S, B, Q, T> {
+    &mut self.inner
+  }
+
+  /// Consume the `Backfilled` stream, returning the wrapped one.
+  #[inline]
+  pub fn into_inner(self) -> ReconnectingData<S, B, Q, T> {
+    self.inner
+  }
+
+  /// Start backfilling the provided gap.
+  ///
+  /// A backfill still in progress is superseded, as the new one covers
+  /// its range as well.
+  fn backfill(&mut self, gap: &Gap) {
+    let bar_ranges = ranges(&gap.subscriptions.bars, &self.bars, |last| *last, gap);
+    let trade_ranges = ranges(
+      &gap.subscriptions.trades,
+      &self.trades,
+      |seen| seen.timestamp,
+      gap,
+    );
+    debug!(
+      bars = bar_ranges.len(),
+      trades = trade_ranges.len(),
+      "backfilling"
+    );
+
+    let future = fetch::<B, T>(
+      self.client.clone(),
+      self.prefix.clone(),
+      self.feed,
+      bar_ranges,
+      trade_ranges,
+      gap.reconnected_at,
+    )
+    .boxed();
+    self.fetch = Some(future);
+  }
+
+  /// Handle the result of a backfill, emitting the historic data and
+  /// all live items held back in the meantime.
+  fn backfilled(&mut self, result: Result<Fetched<B, T>, Error>) {
+    match result {
+      Ok((bars, trades)) => {
+        let mut data = bars
+          .into_iter()
+          .map(|bar| (bar.timestamp(), Data::Bar(bar)))
+          .chain(
+            trades
+              .into_iter()
+              .map(|trade| (trade.timestamp(), Data::Trade(trade))),
+          )
+          .collect::<Vec<_>>();
+        let () = data.sort_by_key(|(timestamp, _data)| *timestamp);
+
+        for (_timestamp, data) in data {
+          if self.admit(&data, false) {
+            self.buffer.push_back(Ok(Event::Data(data)));
+          }
+        }
+      },
+      Err(err) => {
+        debug!(error = display(&err), "failed to backfill");
+        self.buffer.push_back(Err(err))
+      },
+    }
+
+    while let Some(item) = self.held.pop_front() {
+      if let Some(item) = self.filter(item, false) {
+        self.buffer.push_back(item);
+      }
+    }
+  }
+
+  /// Check whether a data item should be emitted, recording it if so.
+  ///
+  /// `prune` indicates whether trade IDs no longer needed for
+  /// deduplication can be discarded.
+  fn admit(&mut self, data: &Data<B, Q, T>, prune: bool) -> bool {
+    match data {
+      Data::Bar(bar) => {
+        let timestamp = bar.timestamp();
+        match self.bars.get_mut(bar.symbol()) {
+          Some(last) if timestamp <= *last => false,
+          Some(last) => {
+            *last = timestamp;
+            true
+          },
+          None => {
+            let _prev = self.bars.insert(bar.symbol().to_string(), timestamp);
+            true
+          },
+        }
+      },
+      Data::Trade(trade) => {
+        if !self.trades.contains_key(trade.symbol()) {
+          let _prev = self
+            .trades
+            .insert(trade.symbol().to_string(), Seen::default());
+        }
+        // SANITY: We just made sure an entry exists.
+        let seen = self.trades.get_mut(trade.symbol()).unwrap();
+        seen.admit(trade.trade_id(), trade.timestamp(), prune)
+      },
+      _ => true,
+    }
+  }
+
+  /// Filter a stream item, dropping data that was already emitted.
+  fn filter(&mut self, item: Item<B, Q, T>, prune: bool) -> Option<Item<B, Q, T>> {
+    match item {
+      Ok(Event::Data(data)) => self.admit(&data, prune).then_some(Ok(Event::Data(data))),
+      item => Some(item),
+    }
+  }
+}
+
+impl<S, B, Q, T> Debug for Backfilled<S, B, Q, T>
+where
+  S: Source,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("Backfilled")
+      .field("inner", &self.inner)
+      .field("prefix", &self.prefix)
+      .field("feed", &self.feed)
+      .field("backfilling", &self.fetch.is_some())
+      .finish()
+  }
+}
+
+impl<S, B, Q, T> Stream for Backfilled<S, B, Q, T>
+where
+  S: Source + 'static,
+  B: BackfillBar + Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: BackfillTrade + Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  type Item = Item<B, Q, T>;
+
+  fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::get_mut(self);
+
+    loop {
+      if let Some(item) = this.buffer.pop_front() {
+        break Poll::Ready(Some(item))
+      }
+
+      if let Some(fetch) = &mut this.fetch {
+        if let Poll::Ready(result) = fetch.poll_unpin(ctx) {
+          this.fetch = None;
+          let () = this.backfilled(result);
+          continue
+        }
+      }
+
+      match this.inner.poll_next_unpin(ctx) {
+        Poll::Ready(Some(Ok(Event::Reconnected(gap)))) => {
+          let () = this.backfill(&gap);
+          break Poll::Ready(Some(Ok(Event::Reconnected(gap))))
+        },
+        Poll::Ready(Some(item)) => {
+          if this.fetch.is_some() {
+            this.held.push_back(item);
+          } else if let Some(item) = this.filter(item, true) {
+            break Poll::Ready(Some(item))
+          }
+        },
+        // Even if the wrapped stream ended we still have to finish an
+        // ongoing backfill, which was polled above already.
+        Poll::Ready(None) if this.fetch.is_some() => break Poll::Pending,
+        Poll::Ready(None) => break Poll::Ready(None),
+        Poll::Pending => break Poll::Pending,
+      }
+    }
+  }
+}
+
+impl<S, B, Q, T> FusedStream for Backfilled<S, B, Q, T>
+where
+  S: Source + 'static,
+  B: BackfillBar + Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: BackfillTrade + Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  #[inline]
+  fn is_terminated(&self) -> bool {
+    self.buffer.is_empty() && self.fetch.is_none() && self.inner.is_terminated()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+
+  use futures::SinkExt as _;
+
+  use http::Request;
+  use http::Response;
+
+  use hyper::body::Bytes;
+  use hyper::Body;
+
+  use test_log::test;
+
+  use websocket_util::test::WebSocketStream;
+  use websocket_util::tungstenite::Error as WebSocketError;
+  use websocket_util::tungstenite::Message;
+
+  use crate::data::v2::stream::MarketData;
+  use crate::data::v2::stream::IEX;
+  use crate::websocket::test::data_handshake;
+  use crate::websocket::test::mock_api_info;
+  use crate::websocket::test::mock_server_multi;
+  use crate::RetryPolicy;
+
+
+  const SUB_REQ: &str = r#"{"action":"subscribe","bars":["AAPL"],"quotes":[],"trades":["AAPL"]}"#;
+  const SUB_RESP: &str = r#"[{"T":"subscription","bars":["AAPL"],"trades":["AAPL"]}]"#;
+  const LIVE1: &str = r#"[
+    {"T":"b","S":"AAPL","o":185.1,"h":185.3,"l":185.0,"c":185.2,"v":1200,"t":"2024-03-12T14:30:00Z","n":34,"vw":185.15},
+    {"T":"t","S":"AAPL","i":1,"x":"V","p":185.2,"s":10,"t":"2024-03-12T14:31:05Z","c":["@"],"z":"C"}
+  ]"#;
+  const LIVE2: &str = r#"[
+    {"T":"t","S":"AAPL","i":3,"x":"V","p":185.4,"s":30,"t":"2024-03-12T14:31:30Z","c":["@"],"z":"C"},
+    {"T":"b","S":"AAPL","o":185.2,"h":185.5,"l":185.1,"c":185.4,"v":800,"t":"2024-03-12T14:31:00Z","n":12,"vw":185.3},
+    {"T":"t","S":"AAPL","i":4,"x":"V","p":185.5,"s":40,"t":"2024-03-12T14:32:10Z","c":["@"],"z":"C"}
+  ]"#;
+  const HISTORIC_BARS: &str = r#"{"bars":{"AAPL":[
+    {"t":"2024-03-12T14:30:00Z","o":185.1,"h":185.3,"l":185.0,"c":185.2,"v":1200,"n":34,"vw":185.15},
+    {"t":"2024-03-12T14:31:00Z","o":185.2,"h":185.5,"l":185.1,"c":185.4,"v":800,"n":12,"vw":185.3}
+  ]},"next_page_token":null}"#;
+  const HISTORIC_TRADES: &str = r#"{"trades":{"AAPL":[
+    {"t":"2024-03-12T14:31:05Z","x":"V","p":185.2,"s":10,"c":["@"],"i":1,"z":"C"},
+    {"t":"2024-03-12T14:31:20Z","x":"V","p":185.3,"s":20,"c":["@"],"i":2,"z":"C"},
+    {"t":"2024-03-12T14:31:30Z","x":"V","p":185.4,"s":30,"c":["@"],"i":3,"z":"C"}
+  ]},"next_page_token":null}"#;
+
+
+  /// Perform the connection, authentication, and subscription handshake
+  /// on the server side.
+  async fn handshake(stream: &mut WebSocketStream) -> Result<(), WebSocketError> {
+    let () = data_handshake(stream).await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(SUB_REQ.to_string()),
+    );
+    stream.send(Message::Text(SUB_RESP.to_string())).await
+  }
+
+
+  /// Check that we backfill bars and trades missed while the connection
+  /// was lost, without reporting any twice.
+  #[test(tokio::test)]
+  async fn backfill_gap() {
+    let addr = mock_server_multi(|idx, mut stream| async move {
+      let () = handshake(&mut stream).await?;
+
+      if idx == 0 {
+        stream.send(Message::Text(LIVE1.to_string())).await?;
+        stream.send(Message::Close(None)).await?;
+      } else {
+        stream.send(Message::Text(LIVE2.to_string())).await?;
+        // Keep the connection open until the client goes away.
+        while stream.next().await.is_some() {}
+      }
+      Ok(())
+    })
+    .await;
+
+    let transport = |request: Request<Bytes>| {
+      let query = request.uri().query().unwrap_or_default();
+      assert!(query.contains("feed=iex"), "{query}");
+      assert!(query.contains("symbols=AAPL"), "{query}");
+
+      let body = match request.uri().path() {
+        "/v2/stocks/bars" => {
+          assert!(query.contains("timeframe=1Min"), "{query}");
+          assert!(query.contains("start=2024-03-12T14%3A30%3A00Z"), "{query}");
+          HISTORIC_BARS
+        },
+        "/v2/stocks/trades" => {
+          assert!(query.contains("start=2024-03-12T14%3A31%3A05Z"), "{query}");
+          HISTORIC_TRADES
+        },
+        path => panic!("unexpected request path: {path}"),
+      };
+      Response::new(Body::from(body))
+    };
+
+    let api_info = mock_api_info(addr);
+    let client = Client::builder()
+      .transport(transport)
+      .build(api_info.clone());
+    let policy = RetryPolicy {
+      initial_backoff: Duration::from_millis(1),
+      jitter: false,
+      ..Default::default()
+    };
+    let inner = ReconnectingData::<IEX>::connect(api_info, policy)
+      .await
+      .unwrap();
+    let mut stream = Backfilled::new(inner, client, MarketPrefix::Stocks, Some(Feed::IEX));
+
+    let mut data = MarketData::default();
+    data.set_bars(["AAPL"]);
+    data.set_trades(["AAPL"]);
+    let () = stream.get_mut().subscribe(&data).await.unwrap();
+
+    let mut events = Vec::new();
+    for _ in 0..7 {
+      let event = match stream.next().await.unwrap().unwrap() {
+        Event::Data(Data::Bar(bar)) => format!("bar {}", bar.close_price),
+        Event::Data(Data::Trade(trade)) => format!("trade {}", trade.trade_id),
+        Event::Reconnected(gap) => {
+          assert_eq!(gap.subscriptions, data);
+          "reconnected".to_string()
+        },
+        event => panic!("received unexpected event: {event:?}"),
+      };
+      let () = events.push(event);
+    }
+
+    let expected = vec![
+      "bar 185.2",
+      "trade 1",
+      "reconnected",
+      "bar 185.4",
+      "trade 2",
+      "trade 3",
+      "trade 4",
+    ];
+    assert_eq!(events, expected);
+  }
+}
@@ -1,6 +1,8 @@
 // Copyright (C) 2021-2024 The apca Developers
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::collections::BTreeMap;
+
 use chrono::DateTime;
 use chrono::Utc;
 
@@ -10,14 +12,16 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_urlencoded::to_string as to_query;
 
+use crate::data::v2::prefix::MarketPrefix;
+use crate::data::v2::symbol_items::SymbolItems;
 use crate::data::v2::Feed;
 use crate::data::DATA_BASE_URL;
-use crate::data::v2::prefix::MarketPrefix;
-use crate::util::vec_from_str;
+use crate::util::map_from_str;
+use crate::util::string_slice_to_str;
+use crate::Paginated;
 use crate::Str;
 
 
-
 /// An enumeration of the various supported time frames.
 /// [1-59]Min / T
 /// [1-23]Hour / H
@@ -66,7 +70,7 @@ pub enum TimeFrame {
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
 pub enum Sort {
   Asc,
-  Desc
+  Desc,
 }
 impl ToString for Sort {
   fn to_string(&self) -> String {
@@ -169,7 +173,7 @@ impl ListReqInit {
     start: DateTime<Utc>,
     end: DateTime<Utc>,
     timeframe: TimeFrame,
-    sort: Sort
+    sort: Sort,
   ) -> ListReq
   where
     S: Into<String>,
@@ -211,10 +215,13 @@ pub struct Bar {
   pub low: Num,
   /// The trading volume.
   #[serde(rename = "v")]
-  pub volume: usize,
+  pub volume: Num,
   /// The volume weighted average price.
   #[serde(rename = "vw")]
   pub weighted_average: Num,
+  /// The number of trades the bar aggregates, if reported.
+  #[serde(rename = "n", default)]
+  pub trade_count: Option<u64>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   #[serde(skip)]
@@ -225,23 +232,50 @@ pub struct Bar {
 /// A collection of bars as returned by the API. This is one page of
 /// bars.
 #[derive(Debug, Deserialize, Eq, PartialEq)]
+#[serde(from = "RawBars")]
 pub struct Bars {
   /// The list of returned bars.
-  #[serde(rename = "bars", deserialize_with = "vec_from_str")]
   pub bars: Vec<Bar>,
   /// The symbol the bars correspond to.
-  #[serde(rename = "symbol")]
+  ///
+  /// For crypto currencies this member is empty if no bars were
+  /// reported.
   pub symbol: String,
-  /// The token to provide to a request to get the next page of bars for
-  /// this request.
-  #[serde(rename = "next_page_token")]
+  /// The token to provide to a request to get the next page of bars
+  /// for this request.
   pub next_page_token: Option<String>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
-  #[serde(skip)]
   pub _non_exhaustive: (),
 }
 
+
+/// The representation of a page of bars as reported by the API.
+#[derive(Deserialize)]
+struct RawBars {
+  /// The returned bars.
+  #[serde(rename = "bars")]
+  bars: SymbolItems<Bar>,
+  /// The symbol the bars correspond to, if reported separately.
+  #[serde(rename = "symbol")]
+  symbol: Option<String>,
+  /// The token to provide to a request to get the next page of bars.
+  #[serde(rename = "next_page_token")]
+  next_page_token: Option<String>,
+}
+
+impl From<RawBars> for Bars {
+  fn from(raw: RawBars) -> Self {
+    let SymbolItems { symbol, items } = raw.bars;
+    Self {
+      bars: items,
+      symbol: raw.symbol.or(symbol).unwrap_or_default(),
+      next_page_token: raw.next_page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
 Endpoint! {
   /// The representation of a GET request to the /v2/stocks/{symbol}/bars endpoint.
   pub List(ListReq),
@@ -268,6 +302,193 @@ Endpoint! {
 }
 
 
+impl Paginated for List {
+  type Item = Bar;
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.bars, output.next_page_token)
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/bars endpoint,
+/// retrieving bars for multiple symbols at once.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct MultiListReq {
+  /// The symbols for which to retrieve market data.
+  #[serde(rename = "symbols", serialize_with = "string_slice_to_str")]
+  pub symbols: Vec<String>,
+  /// Path prefix based on market (e.g. stocks or crypto)
+  #[serde(skip)]
+  pub prefix: MarketPrefix,
+  /// The time frame for the bars.
+  #[serde(rename = "timeframe")]
+  pub timeframe: TimeFrame,
+  /// Filter bars equal to or after this time.
+  #[serde(rename = "start")]
+  pub start: DateTime<Utc>,
+  /// Filter bars equal to or before this time.
+  #[serde(rename = "end")]
+  pub end: DateTime<Utc>,
+  /// The maximum number of bars to be returned in total, i.e., across
+  /// all symbols. It can be between 1 and 10000. Defaults to 1000 if
+  /// the provided value is None.
+  #[serde(rename = "limit")]
+  pub limit: Option<usize>,
+  /// How to sort the bars.
+  /// "asc" or "desc"
+  #[serde(rename = "sort")]
+  pub sort: String,
+  /// The adjustment to use (defaults to raw)
+  #[serde(rename = "adjustment")]
+  pub adjustment: Option<Adjustment>,
+  /// The data feed to use.
+  ///
+  /// Defaults to [`IEX`][Feed::IEX] for free users and
+  /// [`SIP`][Feed::SIP] for users with an unlimited subscription.
+  #[serde(rename = "feed")]
+  pub feed: Option<Feed>,
+  /// If provided we will pass a page token to continue where we left off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`MultiListReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MultiListReqInit {
+  /// See `MultiListReq::limit`.
+  pub limit: Option<usize>,
+  /// See `MultiListReq::adjustment`.
+  pub adjustment: Option<Adjustment>,
+  /// See `MultiListReq::feed`.
+  pub feed: Option<Feed>,
+  /// See `MultiListReq::page_token`.
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl MultiListReqInit {
+  /// Create a [`MultiListReq`] from a `MultiListReqInit`.
+  #[inline]
+  pub fn init<I, S>(
+    self,
+    symbols: I,
+    prefix: MarketPrefix,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    timeframe: TimeFrame,
+    sort: Sort,
+  ) -> MultiListReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    MultiListReq {
+      symbols: symbols.into_iter().map(S::into).collect(),
+      prefix,
+      start,
+      end,
+      timeframe,
+      limit: self.limit,
+      sort: sort.to_string(),
+      adjustment: self.adjustment,
+      feed: self.feed,
+      page_token: self.page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// A collection of bars for multiple symbols as returned by the API.
+/// This is one page of bars.
+///
+/// A page may contain bars for only a subset of the requested symbols.
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+pub struct MultiBars {
+  /// A mapping from symbols to the bars returned for them.
+  #[serde(rename = "bars", deserialize_with = "map_from_str")]
+  pub bars: BTreeMap<String, Vec<Bar>>,
+  /// The token to provide to a request to get the next page of bars for
+  /// this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/stocks/bars endpoint.
+  pub MultiList(MultiListReq),
+  Ok => MultiBars, [
+    /// The market data was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => MultiListError, [
+    /// A query parameter was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("{}bars", input.prefix).into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+
+impl Paginated for MultiList {
+  /// A bar along with the symbol it belongs to.
+  type Item = (String, Bar);
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    let bars = output
+      .bars
+      .into_iter()
+      .flat_map(|(symbol, bars)| bars.into_iter().map(move |bar| (symbol.clone(), bar)))
+      .collect();
+    (bars, output.next_page_token)
+  }
+}
+
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -282,6 +503,8 @@ mod tests {
   use test_log::test;
 
   use crate::api_info::ApiInfo;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::test::CRYPTO;
   use crate::Client;
   use crate::RequestError;
 
@@ -339,6 +562,36 @@ mod tests {
     assert!(res.next_page_token.is_some())
   }
 
+  /// Verify that we can properly parse a reference multi-symbol bar
+  /// response.
+  #[test]
+  fn parse_reference_multi_bars() {
+    let response = r#"{
+    "bars": {
+      "AAPL": [
+        {"t": "2021-02-01T16:01:00Z", "o": 133.32, "h": 133.74, "l": 133.31, "c": 133.5, "v": 9876, "vw": 133.4}
+      ],
+      "MSFT": [
+        {"t": "2021-02-01T16:01:00Z", "o": 240.1, "h": 240.5, "l": 240.0, "c": 240.2, "v": 1234, "vw": 240.3},
+        {"t": "2021-02-01T16:02:00Z", "o": 240.2, "h": 240.4, "l": 240.1, "c": 240.3, "v": 2345, "vw": 240.25}
+      ]
+    },
+    "next_page_token": "TVNGVHxNfDIwMjEtMDItMDFUMTY6MDI6MDBa"
+}"#;
+
+    let res = from_json::<<MultiList as Endpoint>::Output>(response).unwrap();
+    assert_eq!(res.bars.len(), 2);
+    assert_eq!(res.bars["AAPL"].len(), 1);
+    assert_eq!(res.bars["AAPL"][0].open, Num::new(13332, 100));
+    assert_eq!(res.bars["MSFT"].len(), 2);
+    assert_eq!(res.bars["MSFT"][1].volume, Num::from(2345));
+    assert!(res.next_page_token.is_some());
+
+    let response = r#"{"bars": null, "next_page_token": null}"#;
+    let res = from_json::<<MultiList as Endpoint>::Output>(response).unwrap();
+    assert!(res.bars.is_empty());
+  }
+
   /// Check that we can decode a response containing no bars correctly.
   #[test(tokio::test)]
   async fn no_bars() {
@@ -346,7 +599,14 @@ mod tests {
     let client = Client::new(api_info);
     let start = DateTime::from_str("2021-11-05T00:00:00Z").unwrap();
     let end = DateTime::from_str("2021-11-05T00:00:00Z").unwrap();
-    let request = ListReqInit::default().init("AAPL", MarketPrefix::Stocks, start, end, TimeFrame::OneDay, Sort::Asc);
+    let request = ListReqInit::default().init(
+      "AAPL",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
 
     let res = client.issue::<List>(&request).await.unwrap();
     assert_eq!(res.bars, Vec::new())
@@ -363,7 +623,14 @@ mod tests {
       limit: Some(2),
       ..Default::default()
     }
-    .init("AAPL", MarketPrefix::Stocks, start, end, TimeFrame::OneDay, Sort::Asc);
+    .init(
+      "AAPL",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
 
     let res = client.issue::<List>(&request).await.unwrap();
     let bars = res.bars;
@@ -401,7 +668,14 @@ mod tests {
       limit: Some(2),
       ..Default::default()
     }
-    .init("AAPL", MarketPrefix::Stocks, start, end, TimeFrame::OneDay, Sort::Asc);
+    .init(
+      "AAPL",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
 
     let mut res = client.issue::<List>(&request).await.unwrap();
     let bars = res.bars;
@@ -428,7 +702,14 @@ mod tests {
       adjustment: Some(adjustment),
       ..Default::default()
     }
-    .init("AAPL", MarketPrefix::Stocks, start, end, TimeFrame::OneDay, Sort::Asc);
+    .init(
+      "AAPL",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
 
     client.issue::<List>(&request).await.unwrap()
   }
@@ -499,7 +780,14 @@ mod tests {
       feed: Some(Feed::SIP),
       ..Default::default()
     }
-    .init("AAPL", MarketPrefix::Stocks, start, end, TimeFrame::OneDay, Sort::Asc);
+    .init(
+      "AAPL",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
 
     let result = client.issue::<List>(&request).await;
     // Unfortunately we can't really know whether the user has the
@@ -524,7 +812,14 @@ mod tests {
       page_token: Some("123456789abcdefghi".to_string()),
       ..Default::default()
     }
-    .init("SPY", MarketPrefix::Stocks, start, end, TimeFrame::OneMinute, Sort::Asc);
+    .init(
+      "SPY",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneMinute,
+      Sort::Asc,
+    );
 
     let err = client.issue::<List>(&request).await.unwrap_err();
     match err {
@@ -542,7 +837,14 @@ mod tests {
 
     let start = DateTime::from_str("2022-02-01T00:00:00Z").unwrap();
     let end = DateTime::from_str("2022-02-20T00:00:00Z").unwrap();
-    let request = ListReqInit::default().init("ABC123", MarketPrefix::Stocks, start, end, TimeFrame::OneDay, Sort::Asc);
+    let request = ListReqInit::default().init(
+      "ABC123",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneDay,
+      Sort::Asc,
+    );
 
     let err = client.issue::<List>(&request).await.unwrap_err();
     match err {
@@ -550,4 +852,56 @@ mod tests {
       _ => panic!("Received unexpected error: {err:?}"),
     };
   }
+
+  /// Check that we can retrieve historic bars for a crypto currency
+  /// pair.
+  #[test(tokio::test)]
+  async fn crypto_bars() {
+    let start = DateTime::from_str("2024-01-02T00:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T02:00:00Z").unwrap();
+    let request =
+      ListReqInit::default().init("BTC/USD", CRYPTO, start, end, TimeFrame::OneHour, Sort::Asc);
+
+    let bars = crypto_client().issue::<List>(&request).await.unwrap();
+    assert_eq!(bars.symbol, "BTC/USD");
+    assert_eq!(bars.bars.len(), 2);
+    assert_eq!(bars.bars[0].time, start);
+    assert_eq!(bars.bars[0].open, Num::new(4500001, 100));
+    assert_eq!(bars.bars[0].volume, Num::new(123456789, 100000000));
+    assert_eq!(bars.next_page_token, None);
+  }
+
+  /// Check that we can retrieve historic bars for multiple crypto
+  /// currency pairs, following page tokens.
+  #[test(tokio::test)]
+  async fn crypto_multi_bars() {
+    let start = DateTime::from_str("2024-01-02T00:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T02:00:00Z").unwrap();
+    let request = MultiListReqInit {
+      limit: Some(3),
+      ..Default::default()
+    }
+    .init(
+      ["BTC/USD", "ETH/USD"],
+      CRYPTO,
+      start,
+      end,
+      TimeFrame::OneHour,
+      Sort::Asc,
+    );
+
+    let client = crypto_client();
+    let bars = client
+      .paginate::<MultiList>(request)
+      .collect_grouped()
+      .await
+      .unwrap();
+    assert_eq!(
+      bars.keys().map(String::as_str).collect::<Vec<_>>(),
+      vec!["BTC/USD", "ETH/USD"]
+    );
+    assert_eq!(bars["BTC/USD"].len(), 2);
+    assert_eq!(bars["ETH/USD"].len(), 2);
+    assert_eq!(bars["ETH/USD"][1].volume, Num::new(4231, 100));
+  }
 }
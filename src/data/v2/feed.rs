@@ -20,4 +20,15 @@ pub enum Feed {
   /// This feed is only usable with the unlimited market data plan.
   #[serde(rename = "sip")]
   SIP,
+  /// Use the Options Price Reporting Authority (OPRA) as the data
+  /// source for options.
+  ///
+  /// This feed is only usable with a subscription to it.
+  #[serde(rename = "opra")]
+  OPRA,
+  /// Use Alpaca's indicative data as the data source for options.
+  ///
+  /// This feed is available unconditionally.
+  #[serde(rename = "indicative")]
+  Indicative,
 }
@@ -0,0 +1,183 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::from_slice as from_json;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::DATA_BASE_URL;
+use crate::Str;
+
+/// A market data bar as returned by the latest bars endpoint.
+pub use super::bars::Bar;
+/// A GET request to be made to the latest bars endpoint of the market
+/// selected by its prefix, e.g., /v2/stocks/bars/latest or
+/// /v1beta3/crypto/{loc}/latest/bars.
+pub use super::last_quotes::GetReq;
+/// A helper for initializing [`GetReq`] objects.
+pub use super::last_quotes::GetReqInit;
+
+
+EndpointNoParse! {
+  /// The representation of a GET request to the latest bars endpoint
+  /// of a market.
+  pub Get(GetReq),
+  Ok => BTreeMap<String, Bar>, [
+    /// The last bars were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// The provided symbol was invalid or not found or the data feed is
+    /// not supported.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    input.prefix.latest("bars").into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::ConversionError> {
+    /// A helper object for parsing the response to a `Get` request.
+    #[derive(Deserialize)]
+    struct Response {
+      /// A mapping from symbols to bar objects.
+      bars: BTreeMap<String, Bar>,
+    }
+
+    from_json::<Response>(body)
+      .map(|response| response.bars)
+      .map_err(Self::ConversionError::from)
+  }
+
+  fn parse_err(body: &[u8]) -> Result<Self::ApiError, Vec<u8>> {
+    from_json::<Self::ApiError>(body).map_err(|_| body.to_vec())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use chrono::DateTime;
+  use chrono::Duration;
+  use chrono::Utc;
+
+  use http_endpoint::Endpoint as _;
+
+  use num_decimal::Num;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::test::CRYPTO;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  /// Check that we can parse the reference bars from the
+  /// documentation.
+  #[test]
+  fn parse_reference_bars() {
+    let response = br#"{
+      "bars": {
+        "TSLA": {
+          "t": "2022-04-12T17:04:00Z",
+          "o": 995.3,
+          "h": 995.3,
+          "l": 994.35,
+          "c": 994.35,
+          "v": 2208,
+          "n": 24,
+          "vw": 994.847
+        },
+        "AAPL": {
+          "t": "2022-04-12T17:04:00Z",
+          "o": 167.9,
+          "h": 167.93,
+          "l": 167.85,
+          "c": 167.86,
+          "v": 4263,
+          "n": 41,
+          "vw": 167.89
+        }
+      }
+    }"#;
+
+    let bars = Get::parse(response).unwrap();
+    assert_eq!(bars.len(), 2);
+
+    let aapl = &bars["AAPL"];
+    assert_eq!(
+      aapl.time,
+      DateTime::parse_from_rfc3339("2022-04-12T17:04:00Z").unwrap()
+    );
+    assert_eq!(aapl.open, Num::new(1679, 10));
+    assert_eq!(aapl.close, Num::new(16786, 100));
+    assert_eq!(aapl.volume, Num::from(4263));
+
+    let tsla = &bars["TSLA"];
+    assert_eq!(tsla.low, Num::new(99435, 100));
+    assert_eq!(tsla.weighted_average, Num::new(994847, 1000));
+  }
+
+  /// Verify that we can retrieve the last bars for multiple assets.
+  #[test(tokio::test)]
+  async fn request_last_bars() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = GetReqInit::default().init(["SPY", "AAPL"]);
+    let bars = client.issue::<Get>(&req).await.unwrap();
+    assert_eq!(
+      bars.keys().map(String::as_str).collect::<Vec<_>>(),
+      vec!["AAPL", "SPY"]
+    );
+
+    for bar in bars.values() {
+      assert!(bar.time >= Utc::now() - Duration::try_weeks(2).unwrap());
+    }
+  }
+
+  /// Verify that we error out as expected when attempting to retrieve
+  /// the last bar for an invalid symbol.
+  #[test(tokio::test)]
+  async fn invalid_symbol() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = GetReqInit::default().init(["ABC123"]);
+    let err = client.issue::<Get>(&req).await.unwrap_err();
+    match err {
+      RequestError::Endpoint(GetError::InvalidInput(_)) => (),
+      _ => panic!("Received unexpected error: {err:?}"),
+    };
+  }
+
+  /// Check that we can retrieve the latest bars for crypto currency
+  /// pairs.
+  #[test(tokio::test)]
+  async fn crypto_latest_bars() {
+    let request = GetReqInit {
+      prefix: CRYPTO,
+      ..Default::default()
+    }
+    .init(["ETH/USD"]);
+
+    let bars = crypto_client().issue::<Get>(&request).await.unwrap();
+    assert_eq!(bars.len(), 1);
+    assert_eq!(bars["ETH/USD"].close, Num::new(235612, 100));
+    assert_eq!(bars["ETH/USD"].volume, Num::new(1523, 1000));
+  }
+}
@@ -0,0 +1,213 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::from_slice as from_json;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v2::orderbook::Level;
+use crate::data::v2::prefix::CryptoLocation;
+use crate::data::v2::prefix::MarketPrefix;
+use crate::data::DATA_BASE_URL;
+use crate::util::string_slice_to_str;
+use crate::Str;
+
+
+/// A GET request to be made to the
+/// /v1beta3/crypto/{loc}/latest/orderbooks endpoint.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct GetReq {
+  /// The symbols to retrieve the latest order book for.
+  #[serde(rename = "symbols", serialize_with = "string_slice_to_str")]
+  pub symbols: Vec<String>,
+  /// The location to retrieve the order books for.
+  #[serde(skip)]
+  pub location: CryptoLocation,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`GetReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct GetReqInit {
+  /// See `GetReq::location`.
+  pub location: CryptoLocation,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl GetReqInit {
+  /// Create a [`GetReq`] from a `GetReqInit`.
+  #[inline]
+  pub fn init<I, S>(self, symbols: I) -> GetReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    GetReq {
+      symbols: symbols.into_iter().map(S::into).collect(),
+      location: self.location,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// An order book as returned by the
+/// /v1beta3/crypto/{loc}/latest/orderbooks endpoint.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Orderbook {
+  /// The time stamp of the order book.
+  #[serde(rename = "t")]
+  pub time: DateTime<Utc>,
+  /// The bids, in descending order of price.
+  #[serde(rename = "b")]
+  pub bids: Vec<Level>,
+  /// The asks, in ascending order of price.
+  #[serde(rename = "a")]
+  pub asks: Vec<Level>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+EndpointNoParse! {
+  /// The representation of a GET request to the
+  /// /v1beta3/crypto/{loc}/latest/orderbooks endpoint.
+  pub Get(GetReq),
+  Ok => BTreeMap<String, Orderbook>, [
+    /// The latest order books were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// The provided symbol was invalid or not found.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    MarketPrefix::Crypto(input.location).latest("orderbooks").into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::ConversionError> {
+    /// A helper object for parsing the response to a `Get` request.
+    #[derive(Deserialize)]
+    struct Response {
+      /// A mapping from symbols to order book objects.
+      orderbooks: BTreeMap<String, Orderbook>,
+    }
+
+    from_json::<Response>(body)
+      .map(|response| response.orderbooks)
+      .map_err(Self::ConversionError::from)
+  }
+
+  fn parse_err(body: &[u8]) -> Result<Self::ApiError, Vec<u8>> {
+    from_json::<Self::ApiError>(body).map_err(|_| body.to_vec())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_endpoint::Endpoint as _;
+
+  use num_decimal::Num;
+
+  use test_log::test;
+
+  use crate::data::v2::orderbook::Book;
+  use crate::data::v2::test::crypto_client;
+
+
+  /// Check that we can parse the reference order books from the
+  /// documentation.
+  #[test]
+  fn parse_reference_orderbooks() {
+    let response = br#"{
+      "orderbooks": {
+        "BTC/USD": {
+          "a": [
+            {"p": 42886.2, "s": 0.23},
+            {"p": 42887.9, "s": 0.5}
+          ],
+          "b": [
+            {"p": 42880.1, "s": 0.11},
+            {"p": 42879, "s": 1.2}
+          ],
+          "t": "2024-01-02T15:04:05.123456789Z"
+        }
+      }
+    }"#;
+
+    let books = Get::parse(response).unwrap();
+    assert_eq!(books.len(), 1);
+
+    let book = &books["BTC/USD"];
+    assert_eq!(
+      book.time,
+      DateTime::parse_from_rfc3339("2024-01-02T15:04:05.123456789Z").unwrap()
+    );
+    assert_eq!(book.asks.len(), 2);
+    assert_eq!(book.asks[0].price, Num::new(428862, 10));
+    assert_eq!(book.asks[0].size, Num::new(23, 100));
+    assert_eq!(book.bids.len(), 2);
+    assert_eq!(book.bids[1].price, Num::from(42879));
+    assert_eq!(book.bids[1].size, Num::new(12, 10));
+  }
+
+  /// Check that we create the expected path and query for a request.
+  #[test]
+  fn serialize_request() {
+    let req = GetReqInit {
+      location: CryptoLocation::Us1,
+      ..Default::default()
+    }
+    .init(["BTC/USD", "ETH/USD"]);
+
+    assert_eq!(Get::path(&req), "/v1beta3/crypto/us-1/latest/orderbooks");
+    let query = Get::query(&req).unwrap().unwrap();
+    assert_eq!(query, "symbols=BTC%2FUSD%2CETH%2FUSD");
+  }
+
+  /// Check that we can retrieve the latest order book for a crypto
+  /// currency pair and use it to initialize a local book.
+  #[test(tokio::test)]
+  async fn crypto_latest_orderbooks() {
+    let request = GetReqInit::default().init(["BTC/USD"]);
+
+    let books = crypto_client().issue::<Get>(&request).await.unwrap();
+    assert_eq!(books.len(), 1);
+
+    let book = Book::from_snapshot(&books["BTC/USD"]);
+    assert!(book.is_consistent());
+    assert_eq!(book.best_bid().unwrap().price, Num::new(4512012, 100));
+    assert_eq!(book.best_ask().unwrap().size, Num::new(3812, 10000));
+
+    let (bids, asks) = book.depth(2);
+    assert_eq!(bids[1].price, Num::from(45119));
+    assert_eq!(asks[1].size, Num::new(125, 100));
+  }
+}
@@ -13,18 +13,24 @@ use serde::Serialize;
 use serde_json::from_slice as from_json;
 use serde_urlencoded::to_string as to_query;
 
+use crate::data::v2::prefix::MarketPrefix;
 use crate::data::v2::Feed;
 use crate::data::DATA_BASE_URL;
 use crate::util::string_slice_to_str;
 use crate::Str;
 
 
-/// A GET request to be made to the /v2/stocks/quotes/latest endpoint.
+/// A GET request to be made to the /v2/stocks/quotes/latest endpoint,
+/// or one of the other endpoints reporting the latest data for a set
+/// of symbols.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct GetReq {
   /// The symbols to retrieve the last quote for.
   #[serde(rename = "symbols", serialize_with = "string_slice_to_str")]
   pub symbols: Vec<String>,
+  /// The path prefix based on the market (e.g. stocks or crypto).
+  #[serde(skip)]
+  pub prefix: MarketPrefix,
   /// The data feed to use.
   #[serde(rename = "feed")]
   pub feed: Option<Feed>,
@@ -39,6 +45,8 @@ pub struct GetReq {
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[allow(missing_copy_implementations)]
 pub struct GetReqInit {
+  /// See `GetReq::prefix`.
+  pub prefix: MarketPrefix,
   /// See `GetReq::feed`.
   pub feed: Option<Feed>,
   /// The type is non-exhaustive and open to extension.
@@ -56,6 +64,7 @@ impl GetReqInit {
   {
     GetReq {
       symbols: symbols.into_iter().map(S::into).collect(),
+      prefix: self.prefix,
       feed: self.feed,
       _non_exhaustive: (),
     }
@@ -75,13 +84,13 @@ pub struct Quote {
   pub ask_price: Num,
   /// The ask size.
   #[serde(rename = "as")]
-  pub ask_size: u64,
+  pub ask_size: Num,
   /// The bid price.
   #[serde(rename = "bp")]
   pub bid_price: Num,
   /// The bid size.
   #[serde(rename = "bs")]
-  pub bid_size: u64,
+  pub bid_size: Num,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   #[serde(skip)]
@@ -107,8 +116,8 @@ EndpointNoParse! {
     Some(DATA_BASE_URL.into())
   }
 
-  fn path(_input: &Self::Input) -> Str {
-    "/v2/stocks/quotes/latest".into()
+  fn path(input: &Self::Input) -> Str {
+    input.prefix.latest("quotes").into()
   }
 
   fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
@@ -158,6 +167,8 @@ mod tests {
   use test_log::test;
 
   use crate::api_info::ApiInfo;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::test::CRYPTO;
   use crate::Client;
   use crate::RequestError;
 
@@ -203,9 +214,9 @@ mod tests {
       DateTime::parse_from_rfc3339("2022-04-12T17:26:44.962998616Z").unwrap()
     );
     assert_eq!(aapl.ask_price, Num::new(170, 1));
-    assert_eq!(aapl.ask_size, 1);
+    assert_eq!(aapl.ask_size, Num::from(1));
     assert_eq!(aapl.bid_price, Num::new(16803, 100));
-    assert_eq!(aapl.bid_size, 1);
+    assert_eq!(aapl.bid_size, Num::from(1));
 
     assert_eq!(quotes[1].0, "TSLA");
     let tsla = &quotes[1].1;
@@ -214,9 +225,9 @@ mod tests {
       DateTime::parse_from_rfc3339("2022-04-12T17:26:45.009288296Z").unwrap()
     );
     assert_eq!(tsla.ask_price, Num::new(1020, 1));
-    assert_eq!(tsla.ask_size, 3);
+    assert_eq!(tsla.ask_size, Num::from(3));
     assert_eq!(tsla.bid_price, Num::new(990, 1));
-    assert_eq!(tsla.bid_size, 5);
+    assert_eq!(tsla.bid_size, Num::from(5));
   }
 
   /// Verify that we can retrieve the last quote for an asset.
@@ -302,4 +313,22 @@ mod tests {
     let quotes = client.issue::<Get>(&req).await.unwrap();
     assert_eq!(quotes.len(), 1);
   }
+
+  /// Check that we can retrieve the latest quotes for crypto currency
+  /// pairs.
+  #[test(tokio::test)]
+  async fn crypto_latest_quotes() {
+    let request = GetReqInit {
+      prefix: CRYPTO,
+      ..Default::default()
+    }
+    .init(["BTC/USD", "ETH/USD"]);
+
+    let quotes = crypto_client().issue::<Get>(&request).await.unwrap();
+    assert_eq!(quotes.len(), 2);
+    assert_eq!(quotes[0].0, "BTC/USD");
+    assert_eq!(quotes[0].1.bid_price, Num::new(4512012, 100));
+    assert_eq!(quotes[1].0, "ETH/USD");
+    assert_eq!(quotes[1].1.ask_size, Num::new(8125, 1000));
+  }
 }
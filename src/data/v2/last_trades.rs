@@ -0,0 +1,179 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::from_slice as from_json;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::DATA_BASE_URL;
+use crate::Str;
+
+/// A GET request to be made to the latest trades endpoint of the
+/// market selected by its prefix, e.g., /v2/stocks/trades/latest or
+/// /v1beta3/crypto/{loc}/latest/trades.
+pub use super::last_quotes::GetReq;
+/// A helper for initializing [`GetReq`] objects.
+pub use super::last_quotes::GetReqInit;
+/// A trade as returned by the latest trades endpoint.
+pub use super::trades::Trade;
+
+
+EndpointNoParse! {
+  /// The representation of a GET request to the latest trades
+  /// endpoint of a market.
+  pub Get(GetReq),
+  Ok => BTreeMap<String, Trade>, [
+    /// The last trades were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// The provided symbol was invalid or not found or the data feed is
+    /// not supported.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    input.prefix.latest("trades").into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::ConversionError> {
+    /// A helper object for parsing the response to a `Get` request.
+    #[derive(Deserialize)]
+    struct Response {
+      /// A mapping from symbols to trade objects.
+      trades: BTreeMap<String, Trade>,
+    }
+
+    from_json::<Response>(body)
+      .map(|response| response.trades)
+      .map_err(Self::ConversionError::from)
+  }
+
+  fn parse_err(body: &[u8]) -> Result<Self::ApiError, Vec<u8>> {
+    from_json::<Self::ApiError>(body).map_err(|_| body.to_vec())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use chrono::DateTime;
+  use chrono::Duration;
+  use chrono::Utc;
+
+  use http_endpoint::Endpoint as _;
+
+  use num_decimal::Num;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::data::v2::prefix::CryptoLocation;
+  use crate::data::v2::prefix::MarketPrefix;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::Feed;
+  use crate::Client;
+
+
+  /// Check that we can parse the reference trades from the
+  /// documentation.
+  #[test]
+  fn parse_reference_trades() {
+    let response = br#"{
+      "trades": {
+        "TSLA": {
+          "t": "2022-04-12T17:05:06.936423531Z",
+          "x": "V",
+          "p": 995,
+          "s": 100,
+          "c": ["@"],
+          "i": 10741,
+          "z": "C"
+        },
+        "AAPL": {
+          "t": "2022-04-12T17:05:17.428334819Z",
+          "x": "V",
+          "p": 167.86,
+          "s": 99,
+          "c": ["@"],
+          "i": 7980,
+          "z": "C"
+        }
+      }
+    }"#;
+
+    let trades = Get::parse(response).unwrap();
+    assert_eq!(trades.len(), 2);
+
+    let aapl = &trades["AAPL"];
+    assert_eq!(
+      aapl.timestamp,
+      DateTime::parse_from_rfc3339("2022-04-12T17:05:17.428334819Z").unwrap()
+    );
+    assert_eq!(aapl.price, Num::new(16786, 100));
+    assert_eq!(aapl.size, Num::from(99));
+
+    let tsla = &trades["TSLA"];
+    assert_eq!(tsla.price, Num::from(995));
+    assert_eq!(tsla.size, Num::from(100));
+  }
+
+  /// Check that we pass along the requested symbols and data feed.
+  #[test]
+  fn serialize_request() {
+    let req = GetReqInit {
+      feed: Some(Feed::SIP),
+      ..Default::default()
+    }
+    .init(["AAPL", "MSFT"]);
+
+    let query = Get::query(&req).unwrap().unwrap();
+    assert_eq!(query, "symbols=AAPL%2CMSFT&feed=sip");
+  }
+
+  /// Verify that we can retrieve the last trades for multiple assets.
+  #[test(tokio::test)]
+  async fn request_last_trades() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = GetReqInit::default().init(["SPY", "AAPL"]);
+    let trades = client.issue::<Get>(&req).await.unwrap();
+    assert_eq!(
+      trades.keys().map(String::as_str).collect::<Vec<_>>(),
+      vec!["AAPL", "SPY"]
+    );
+
+    for trade in trades.values() {
+      assert!(trade.timestamp >= Utc::now() - Duration::try_weeks(2).unwrap());
+    }
+  }
+
+  /// Check that we can retrieve the latest trades for crypto currency
+  /// pairs, at a location other than the default one.
+  #[test(tokio::test)]
+  async fn crypto_latest_trades() {
+    let request = GetReqInit {
+      prefix: MarketPrefix::Crypto(CryptoLocation::Us1),
+      ..Default::default()
+    }
+    .init(["BTC/USD"]);
+
+    let trades = crypto_client().issue::<Get>(&request).await.unwrap();
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades["BTC/USD"].price, Num::new(4512150, 100));
+    assert_eq!(trades["BTC/USD"].size, Num::new(75, 100000));
+  }
+}
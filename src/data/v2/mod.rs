@@ -2,20 +2,64 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 mod feed;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod symbol_items;
 mod unfold;
 
+/// Definitions for backfilling market data missed while streaming.
+pub mod backfill;
 /// Definitions for retrieval of market data bars.
 pub mod bars;
+/// Functionality for retrieval of most recent bars.
+pub mod last_bars;
+/// Functionality for retrieval of most recent crypto currency order
+/// books.
+pub mod last_orderbooks;
 /// Functionality for retrieval of most recent quotes.
 pub mod last_quotes;
+/// Functionality for retrieval of most recent trades.
+pub mod last_trades;
+/// Definitions for maintaining order books locally.
+pub mod orderbook;
+/// Definitions for market path prefixes
+pub mod prefix;
 /// Functionality for retrieving historic quotes.
 pub mod quotes;
+/// Definitions for real-time streaming of market data that survives
+/// connection loss.
+pub mod reconnect;
+/// Functionality for retrieval of market data snapshots.
+pub mod snapshots;
 /// Definitions for real-time streaming of market data.
 pub mod stream;
 /// Definitions for retrieval of market data trades.
 pub mod trades;
-/// Definitions for market path prefixes
-pub mod prefix;
 
 pub use feed::Feed;
 
+
+#[cfg(test)]
+pub(crate) mod test {
+  use crate::data::v2::prefix::CryptoLocation;
+  use crate::data::v2::prefix::MarketPrefix;
+  use crate::ApiInfo;
+  use crate::Cassette;
+  use crate::CassetteMode;
+  use crate::Client;
+
+
+  /// The prefix for the crypto currency market at the default location.
+  pub(crate) const CRYPTO: MarketPrefix = MarketPrefix::Crypto(CryptoLocation::Us);
+
+
+  /// Create a `Client` replaying the recorded crypto currency market
+  /// data exchanges.
+  pub(crate) fn crypto_client() -> Client {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/crypto.json");
+    let cassette = Cassette::new(path, CassetteMode::Replay).unwrap();
+    let api_info =
+      ApiInfo::from_parts("https://paper-api.alpaca.markets", "KEYID", "SECRET").unwrap();
+    Client::builder().cassette(cassette).build(api_info)
+  }
+}
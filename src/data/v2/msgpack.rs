@@ -0,0 +1,590 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use chrono::DateTime;
+use chrono::SecondsFormat;
+
+use rmp_serde::decode::Error as DecodeError;
+use rmp_serde::encode::Error as EncodeError;
+use rmp_serde::to_vec_named;
+use rmp_serde::Deserializer;
+
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::EnumAccess;
+use serde::de::Error as _;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::VariantAccess;
+use serde::de::Visitor;
+use serde::Serialize;
+
+
+/// The MessagePack extension type used for time stamps.
+const TIMESTAMP_EXT: i8 = -1;
+
+
+/// Decode a MessagePack time stamp extension into an RFC 3339 string.
+fn timestamp<E>(data: &[u8]) -> Result<String, E>
+where
+  E: serde::de::Error,
+{
+  let (secs, nanos) = match data.len() {
+    4 => {
+      let mut secs = [0; 4];
+      secs.copy_from_slice(data);
+      (i64::from(u32::from_be_bytes(secs)), 0)
+    },
+    8 => {
+      let mut value = [0; 8];
+      value.copy_from_slice(data);
+      let value = u64::from_be_bytes(value);
+      // The upper 30 bits contain nanoseconds, the lower 34 bits
+      // seconds.
+      ((value & 0x3_ffff_ffff) as i64, (value >> 34) as u32)
+    },
+    12 => {
+      let mut nanos = [0; 4];
+      nanos.copy_from_slice(&data[..4]);
+      let mut secs = [0; 8];
+      secs.copy_from_slice(&data[4..]);
+      (i64::from_be_bytes(secs), u32::from_be_bytes(nanos))
+    },
+    len => {
+      return Err(E::custom(format!(
+        "encountered MessagePack time stamp of invalid length {len}"
+      )))
+    },
+  };
+
+  let time = DateTime::from_timestamp(secs, nanos).ok_or_else(|| {
+    E::custom(format!(
+      "MessagePack time stamp {secs}.{nanos:09} is out of range"
+    ))
+  })?;
+  Ok(time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+
+/// A visitor for the contents of a MessagePack extension value, which
+/// `rmp_serde` reports as a sequence of the type and the data.
+struct ExtVisitor;
+
+impl<'de> Visitor<'de> for ExtVisitor {
+  type Value = String;
+
+  fn expecting(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.write_str("a MessagePack time stamp")
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let type_ = seq
+      .next_element::<i8>()?
+      .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+    if type_ != TIMESTAMP_EXT {
+      return Err(A::Error::custom(format!(
+        "encountered unsupported MessagePack extension type {type_}"
+      )))
+    }
+
+    seq
+      .next_element_seed(TimestampSeed)?
+      .ok_or_else(|| A::Error::invalid_length(1, &self))
+  }
+}
+
+
+/// A seed for decoding the data of a MessagePack time stamp extension
+/// value without an intermediate allocation.
+struct TimestampSeed;
+
+impl<'de> DeserializeSeed<'de> for TimestampSeed {
+  type Value = String;
+
+  fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    deserializer.deserialize_bytes(self)
+  }
+}
+
+impl<'de> Visitor<'de> for TimestampSeed {
+  type Value = String;
+
+  fn expecting(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt.write_str("the data of a MessagePack time stamp")
+  }
+
+  fn visit_bytes<E>(self, data: &[u8]) -> Result<Self::Value, E>
+  where
+    E: serde::de::Error,
+  {
+    timestamp(data)
+  }
+}
+
+
+/// A deserializer adapting one for MessagePack to behave like one for
+/// JSON.
+///
+/// All our message types are defined in terms of their JSON
+/// representation. Rather than requiring every one of them (including
+/// user provided bar, quote, and trade types) to understand MessagePack
+/// specifics, the adapter presents time stamps, which are reported via
+/// an extension type, as RFC 3339 strings. It also claims to be human
+/// readable, because numeric values are reported in the same textual
+/// or floating point form as in JSON.
+struct Adapter<D>(D);
+
+/// A visitor, seed, or accessor wrapped such that all deserializers it
+/// hands out are wrapped in an [`Adapter`].
+struct Wrap<T>(T);
+
+
+macro_rules! forward_deserialize {
+  ($($method:ident($($arg:ident: $type:ty),*)),* $(,)?) => {
+    $(
+      #[inline]
+      fn $method<V>(self, $($arg: $type,)* visitor: V) -> Result<V::Value, Self::Error>
+      where
+        V: Visitor<'de>,
+      {
+        self.0.$method($($arg,)* Wrap(visitor))
+      }
+    )*
+  };
+}
+
+impl<'de, D> serde::Deserializer<'de> for Adapter<D>
+where
+  D: serde::Deserializer<'de>,
+{
+  type Error = D::Error;
+
+  forward_deserialize! {
+    deserialize_any(),
+    deserialize_bool(),
+    deserialize_i8(),
+    deserialize_i16(),
+    deserialize_i32(),
+    deserialize_i64(),
+    deserialize_i128(),
+    deserialize_u8(),
+    deserialize_u16(),
+    deserialize_u32(),
+    deserialize_u64(),
+    deserialize_u128(),
+    deserialize_f32(),
+    deserialize_f64(),
+    deserialize_char(),
+    deserialize_str(),
+    deserialize_string(),
+    deserialize_bytes(),
+    deserialize_byte_buf(),
+    deserialize_option(),
+    deserialize_unit(),
+    deserialize_unit_struct(name: &'static str),
+    deserialize_seq(),
+    deserialize_tuple(len: usize),
+    deserialize_tuple_struct(name: &'static str, len: usize),
+    deserialize_map(),
+    deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+    deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+    deserialize_identifier(),
+    deserialize_ignored_any(),
+  }
+
+  #[inline]
+  fn deserialize_newtype_struct<V>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    // Newtypes are encoded just like the type they wrap. `rmp_serde`
+    // only treats them specially when they represent extension values,
+    // which is not the case for any of our types.
+    visitor.visit_newtype_struct(self)
+  }
+
+  #[inline]
+  fn is_human_readable(&self) -> bool {
+    true
+  }
+}
+
+
+macro_rules! forward_visit {
+  ($($method:ident($type:ty)),* $(,)?) => {
+    $(
+      #[inline]
+      fn $method<E>(self, value: $type) -> Result<Self::Value, E>
+      where
+        E: serde::de::Error,
+      {
+        self.0.$method(value)
+      }
+    )*
+  };
+}
+
+impl<'de, V> Visitor<'de> for Wrap<V>
+where
+  V: Visitor<'de>,
+{
+  type Value = V::Value;
+
+  fn expecting(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    self.0.expecting(fmt)
+  }
+
+  forward_visit! {
+    visit_bool(bool),
+    visit_i8(i8),
+    visit_i16(i16),
+    visit_i32(i32),
+    visit_i64(i64),
+    visit_i128(i128),
+    visit_u8(u8),
+    visit_u16(u16),
+    visit_u32(u32),
+    visit_u64(u64),
+    visit_u128(u128),
+    visit_f32(f32),
+    visit_f64(f64),
+    visit_char(char),
+    visit_str(&str),
+    visit_borrowed_str(&'de str),
+    visit_string(String),
+    visit_bytes(&[u8]),
+    visit_borrowed_bytes(&'de [u8]),
+    visit_byte_buf(Vec<u8>),
+  }
+
+  #[inline]
+  fn visit_none<E>(self) -> Result<Self::Value, E>
+  where
+    E: serde::de::Error,
+  {
+    self.0.visit_none()
+  }
+
+  #[inline]
+  fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    self.0.visit_some(Adapter(deserializer))
+  }
+
+  #[inline]
+  fn visit_unit<E>(self) -> Result<Self::Value, E>
+  where
+    E: serde::de::Error,
+  {
+    self.0.visit_unit()
+  }
+
+  fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    // `rmp_serde` reports extension values as newtypes and we only
+    // ever ask for newtypes directly (see
+    // `Adapter::deserialize_newtype_struct`).
+    let timestamp = deserializer.deserialize_any(ExtVisitor)?;
+    self.0.visit_string(timestamp)
+  }
+
+  #[inline]
+  fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    self.0.visit_seq(Wrap(seq))
+  }
+
+  #[inline]
+  fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+  where
+    A: MapAccess<'de>,
+  {
+    self.0.visit_map(Wrap(map))
+  }
+
+  #[inline]
+  fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+  where
+    A: EnumAccess<'de>,
+  {
+    self.0.visit_enum(Wrap(data))
+  }
+}
+
+impl<'de, S> DeserializeSeed<'de> for Wrap<S>
+where
+  S: DeserializeSeed<'de>,
+{
+  type Value = S::Value;
+
+  #[inline]
+  fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    self.0.deserialize(Adapter(deserializer))
+  }
+}
+
+impl<'de, A> SeqAccess<'de> for Wrap<A>
+where
+  A: SeqAccess<'de>,
+{
+  type Error = A::Error;
+
+  #[inline]
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    self.0.next_element_seed(Wrap(seed))
+  }
+
+  #[inline]
+  fn size_hint(&self) -> Option<usize> {
+    self.0.size_hint()
+  }
+}
+
+impl<'de, A> MapAccess<'de> for Wrap<A>
+where
+  A: MapAccess<'de>,
+{
+  type Error = A::Error;
+
+  #[inline]
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    self.0.next_key_seed(Wrap(seed))
+  }
+
+  #[inline]
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    self.0.next_value_seed(Wrap(seed))
+  }
+
+  #[inline]
+  fn size_hint(&self) -> Option<usize> {
+    self.0.size_hint()
+  }
+}
+
+impl<'de, A> EnumAccess<'de> for Wrap<A>
+where
+  A: EnumAccess<'de>,
+{
+  type Error = A::Error;
+  type Variant = Wrap<A::Variant>;
+
+  #[inline]
+  fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    self
+      .0
+      .variant_seed(Wrap(seed))
+      .map(|(value, variant)| (value, Wrap(variant)))
+  }
+}
+
+impl<'de, A> VariantAccess<'de> for Wrap<A>
+where
+  A: VariantAccess<'de>,
+{
+  type Error = A::Error;
+
+  #[inline]
+  fn unit_variant(self) -> Result<(), Self::Error> {
+    self.0.unit_variant()
+  }
+
+  #[inline]
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    self.0.newtype_variant_seed(Wrap(seed))
+  }
+
+  #[inline]
+  fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    self.0.tuple_variant(len, Wrap(visitor))
+  }
+
+  #[inline]
+  fn struct_variant<V>(
+    self,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Self::Error>
+  where
+    V: Visitor<'de>,
+  {
+    self.0.struct_variant(fields, Wrap(visitor))
+  }
+}
+
+
+/// Deserialize an instance of type `T` from MessagePack encoded data.
+pub(crate) fn from_slice<T>(data: &[u8]) -> Result<T, DecodeError>
+where
+  T: DeserializeOwned,
+{
+  let mut deserializer = Deserializer::from_read_ref(data);
+  T::deserialize(Adapter(&mut deserializer))
+}
+
+
+/// Serialize the given value as MessagePack.
+pub(crate) fn to_vec<T>(value: &T) -> Result<Vec<u8>, EncodeError>
+where
+  T: Serialize,
+{
+  to_vec_named(value)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+  use chrono::Utc;
+
+  use num_decimal::Num;
+
+  use serde::Deserialize;
+
+  use serde_json::json;
+  use serde_json::Value as JsonValue;
+
+
+  /// A type mirroring the way our message types are defined.
+  #[derive(Debug, Deserialize, PartialEq)]
+  #[serde(tag = "T")]
+  enum Message {
+    #[serde(rename = "t")]
+    Trade {
+      #[serde(rename = "S")]
+      symbol: String,
+      #[serde(rename = "p")]
+      price: Num,
+      #[serde(rename = "t")]
+      timestamp: DateTime<Utc>,
+      #[serde(rename = "c", default)]
+      conditions: Option<Vec<String>>,
+    },
+  }
+
+
+  /// Check that we can decode all MessagePack time stamp formats.
+  #[test]
+  fn decode_timestamps() {
+    // 2021-02-22T15:51:44Z
+    let secs = 1614009104u32;
+    let mut data = vec![0xd6, 0xff];
+    data.extend_from_slice(&secs.to_be_bytes());
+    let time = from_slice::<DateTime<Utc>>(&data).unwrap();
+    assert_eq!(
+      time,
+      DateTime::<Utc>::from_str("2021-02-22T15:51:44Z").unwrap()
+    );
+
+    let expected = DateTime::<Utc>::from_str("2021-02-22T15:51:44.208Z").unwrap();
+    let nanos = 208_000_000u64;
+    let mut data = vec![0xd7, 0xff];
+    data.extend_from_slice(&((nanos << 34) | u64::from(secs)).to_be_bytes());
+    assert_eq!(from_slice::<DateTime<Utc>>(&data).unwrap(), expected);
+
+    let mut data = vec![0xc7, 12, 0xff];
+    data.extend_from_slice(&(nanos as u32).to_be_bytes());
+    data.extend_from_slice(&i64::from(secs).to_be_bytes());
+    assert_eq!(from_slice::<DateTime<Utc>>(&data).unwrap(), expected);
+
+    let err = from_slice::<DateTime<Utc>>(&[0xd5, 0xff, 0, 0]).unwrap_err();
+    assert!(err.to_string().contains("invalid length 2"), "{err}");
+
+    let err = from_slice::<DateTime<Utc>>(&[0xd6, 0x01, 0, 0, 0, 0]).unwrap_err();
+    assert!(err.to_string().contains("extension type 1"), "{err}");
+  }
+
+  /// Check that we can decode messages with embedded time stamps.
+  #[test]
+  fn decode_message() {
+    let mut data = to_vec(&json!([{"T": "t", "S": "SPY", "p": 387.62, "c": null}])).unwrap();
+    // Splice in a time stamp, increasing the number of fields of the
+    // fixmap following the fixarray marker.
+    assert_eq!(data[1], 0x84);
+    data[1] = 0x85;
+    data.extend_from_slice(&[0xa1, b't', 0xd6, 0xff]);
+    data.extend_from_slice(&1614009104u32.to_be_bytes());
+
+    let messages = from_slice::<Vec<Message>>(&data).unwrap();
+    let expected = Message::Trade {
+      symbol: "SPY".to_string(),
+      price: Num::new(38762, 100),
+      timestamp: DateTime::<Utc>::from_str("2021-02-22T15:51:44Z").unwrap(),
+      conditions: None,
+    };
+    assert_eq!(messages, vec![expected]);
+  }
+
+  /// Check that we can round trip values through MessagePack.
+  #[test]
+  fn encode_decode() {
+    let value = json!([{
+      "T": "b",
+      "S": "SPY",
+      "o": 388.985,
+      "v": 49378,
+      "d": -1,
+      "c": [" ", "T"],
+      "r": true,
+      "n": null,
+    }]);
+
+    let data = to_vec(&value).unwrap();
+    let decoded = from_slice::<JsonValue>(&data).unwrap();
+    assert_eq!(decoded, value);
+  }
+
+  /// Check that we report errors for malformed data.
+  #[test]
+  fn decode_invalid() {
+    let err = from_slice::<JsonValue>(&[0x92, 0x01]).unwrap_err();
+    assert!(matches!(err, DecodeError::InvalidMarkerRead(..)), "{err:?}");
+
+    // NaN is not a valid price.
+    let data = to_vec(&f64::NAN).unwrap();
+    assert!(from_slice::<Num>(&data).is_err());
+  }
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::data::v2::last_orderbooks;
+use crate::data::v2::stream;
+
+
+/// A single price level of an order book.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Level {
+  /// The price of the level.
+  #[serde(rename = "p")]
+  pub price: Num,
+  /// The aggregate size available at the price.
+  ///
+  /// In incremental updates a size of zero indicates that the level
+  /// was removed.
+  #[serde(rename = "s")]
+  pub size: Num,
+}
+
+
+/// An in-memory order book for a single symbol, maintained by applying
+/// the updates received over the `orderbooks` channel of a realtime
+/// data stream.
+///
+/// The book is reset whenever an update marked as such (i.e., a
+/// snapshot) is applied. Alpaca sends such a snapshot right after
+/// subscribing to the `orderbooks` channel.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Book {
+  /// The bids, keyed by price in descending order.
+  bids: BTreeMap<Reverse<Num>, Num>,
+  /// The asks, keyed by price in ascending order.
+  asks: BTreeMap<Num, Num>,
+  /// The time stamp of the most recently applied update.
+  timestamp: Option<DateTime<Utc>>,
+  /// Whether a snapshot was applied.
+  synced: bool,
+}
+
+impl Book {
+  /// Create a book from a snapshot as retrieved through the
+  /// [`last_orderbooks::Get`] endpoint.
+  pub fn from_snapshot(snapshot: &last_orderbooks::Orderbook) -> Self {
+    let mut book = Self::default();
+    let () = book.reset(&snapshot.bids, &snapshot.asks, snapshot.time);
+    book
+  }
+
+  /// Replace the contents of the book with the provided levels.
+  fn reset(&mut self, bids: &[Level], asks: &[Level], timestamp: DateTime<Utc>) {
+    self.bids.clear();
+    self.asks.clear();
+    self.synced = true;
+    self.update(bids, asks, timestamp)
+  }
+
+  /// Update the book with the provided levels.
+  fn update(&mut self, bids: &[Level], asks: &[Level], timestamp: DateTime<Utc>) {
+    for level in bids {
+      if level.size.is_zero() {
+        let _size = self.bids.remove(&Reverse(level.price.clone()));
+      } else {
+        let _size = self
+          .bids
+          .insert(Reverse(level.price.clone()), level.size.clone());
+      }
+    }
+
+    for level in asks {
+      if level.size.is_zero() {
+        let _size = self.asks.remove(&level.price);
+      } else {
+        let _size = self.asks.insert(level.price.clone(), level.size.clone());
+      }
+    }
+
+    self.timestamp = Some(timestamp);
+  }
+
+  /// Apply an update received over a realtime data stream.
+  ///
+  /// Updates marked as resets replace the book's contents, while all
+  /// others are applied incrementally, with levels of size zero being
+  /// removed.
+  pub fn apply(&mut self, update: &stream::Orderbook) {
+    if update.reset {
+      self.reset(&update.bids, &update.asks, update.timestamp)
+    } else {
+      self.update(&update.bids, &update.asks, update.timestamp)
+    }
+  }
+
+  /// Retrieve the best (i.e., highest) bid.
+  #[inline]
+  pub fn best_bid(&self) -> Option<Level> {
+    self.bids(1).next()
+  }
+
+  /// Retrieve the best (i.e., lowest) ask.
+  #[inline]
+  pub fn best_ask(&self) -> Option<Level> {
+    self.asks(1).next()
+  }
+
+  /// Retrieve up to `levels` bids, best one first.
+  pub fn bids(&self, levels: usize) -> impl Iterator<Item = Level> + '_ {
+    self
+      .bids
+      .iter()
+      .take(levels)
+      .map(|(Reverse(price), size)| Level {
+        price: price.clone(),
+        size: size.clone(),
+      })
+  }
+
+  /// Retrieve up to `levels` asks, best one first.
+  pub fn asks(&self, levels: usize) -> impl Iterator<Item = Level> + '_ {
+    self.asks.iter().take(levels).map(|(price, size)| Level {
+      price: price.clone(),
+      size: size.clone(),
+    })
+  }
+
+  /// Retrieve the bids and asks of the top `levels` levels of the book,
+  /// best ones first.
+  pub fn depth(&self, levels: usize) -> (Vec<Level>, Vec<Level>) {
+    (self.bids(levels).collect(), self.asks(levels).collect())
+  }
+
+  /// Retrieve the time stamp of the most recently applied update.
+  #[inline]
+  pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+    self.timestamp
+  }
+
+  /// Check whether the book is in a consistent state.
+  ///
+  /// A book is consistent if it was initialized from a snapshot, all
+  /// its levels have a positive size, and it is not crossed, i.e., the
+  /// best bid is below the best ask.
+  pub fn is_consistent(&self) -> bool {
+    let positive = self
+      .bids
+      .values()
+      .chain(self.asks.values())
+      .all(Num::is_positive);
+    let crossed = match (self.bids.keys().next(), self.asks.keys().next()) {
+      (Some(Reverse(bid)), Some(ask)) => bid >= ask,
+      _ => false,
+    };
+
+    self.synced && positive && !crossed
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+
+
+  /// Create a `Level` object.
+  fn level(price: i32, size: i32) -> Level {
+    Level {
+      price: Num::from(price),
+      size: Num::from(size),
+    }
+  }
+
+  /// Create an update for the realtime data stream.
+  fn update(bids: Vec<Level>, asks: Vec<Level>, reset: bool) -> stream::Orderbook {
+    stream::Orderbook {
+      symbol: "BTC/USD".to_string(),
+      timestamp: DateTime::from_str("2024-01-02T15:04:05Z").unwrap(),
+      bids,
+      asks,
+      reset,
+    }
+  }
+
+
+  /// Check that we can apply snapshots and incremental updates.
+  #[test]
+  fn apply_updates() {
+    let mut book = Book::default();
+    assert!(!book.is_consistent());
+    assert_eq!(book.best_bid(), None);
+    assert_eq!(book.best_ask(), None);
+
+    let snapshot = update(
+      vec![level(99, 1), level(100, 2), level(98, 3)],
+      vec![level(101, 4), level(103, 5), level(102, 6)],
+      true,
+    );
+    let () = book.apply(&snapshot);
+    assert!(book.is_consistent());
+    assert_eq!(book.best_bid(), Some(level(100, 2)));
+    assert_eq!(book.best_ask(), Some(level(101, 4)));
+    assert_eq!(
+      book.depth(2),
+      (
+        vec![level(100, 2), level(99, 1)],
+        vec![level(101, 4), level(102, 6)]
+      )
+    );
+
+    // Remove the best bid, change a size, and add a new best ask.
+    let () = book.apply(&update(
+      vec![level(100, 0), level(98, 7)],
+      vec![level(100, 8)],
+      false,
+    ));
+    assert!(book.is_consistent());
+    assert_eq!(
+      book.depth(10),
+      (
+        vec![level(99, 1), level(98, 7)],
+        vec![level(100, 8), level(101, 4), level(102, 6), level(103, 5)]
+      )
+    );
+
+    // A reset replaces everything.
+    let () = book.apply(&update(vec![level(50, 1)], vec![level(51, 1)], true));
+    assert_eq!(book.depth(10), (vec![level(50, 1)], vec![level(51, 1)]));
+    assert_eq!(
+      book.timestamp(),
+      Some(DateTime::from_str("2024-01-02T15:04:05Z").unwrap())
+    );
+  }
+
+  /// Check that we detect inconsistent books.
+  #[test]
+  fn detect_inconsistency() {
+    let mut book = Book::default();
+    // Without a snapshot we cannot know the full book.
+    let () = book.apply(&update(vec![level(99, 1)], vec![level(101, 1)], false));
+    assert!(!book.is_consistent());
+
+    let () = book.apply(&update(vec![level(99, 1)], vec![level(101, 1)], true));
+    assert!(book.is_consistent());
+
+    // A crossed book is inconsistent.
+    let () = book.apply(&update(vec![level(102, 1)], Vec::new(), false));
+    assert!(!book.is_consistent());
+
+    // And so is one with negative sizes.
+    let () = book.apply(&update(vec![level(102, 0)], vec![level(105, -1)], false));
+    assert!(!book.is_consistent());
+  }
+
+  /// Check that we can create a book from a snapshot retrieved through
+  /// the REST API.
+  #[test]
+  fn from_rest_snapshot() {
+    let snapshot = last_orderbooks::Orderbook {
+      time: DateTime::from_str("2024-01-02T15:04:05Z").unwrap(),
+      bids: vec![level(99, 1), level(100, 2)],
+      asks: vec![level(101, 3)],
+      _non_exhaustive: (),
+    };
+
+    let book = Book::from_snapshot(&snapshot);
+    assert!(book.is_consistent());
+    assert_eq!(book.best_bid(), Some(level(100, 2)));
+    assert_eq!(book.best_ask(), Some(level(101, 3)));
+  }
+}
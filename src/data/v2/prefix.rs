@@ -1,21 +1,97 @@
 use std::fmt::Display;
-use serde::{Serialize, Deserialize};
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 
+use serde::Deserialize;
+use serde::Serialize;
+
+
+/// An enumeration of the locations crypto currency market data can be
+/// retrieved for.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum CryptoLocation {
+  /// Alpaca US.
+  #[default]
+  #[serde(rename = "us")]
+  Us,
+  /// Kraken US.
+  #[serde(rename = "us-1")]
+  Us1,
+}
+
+impl CryptoLocation {
+  /// Retrieve the location's identifier, as used in URL paths.
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Us => "us",
+      Self::Us1 => "us-1",
+    }
+  }
+}
+
+
+/// The market to retrieve data for, determining the path prefix of
+/// market data endpoints.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum MarketPrefix {
+  /// The US stock market.
   Stocks,
-  Crypto
+  /// The crypto currency market at the given location.
+  Crypto(CryptoLocation),
+  /// The US options market.
+  Options,
 }
+
+impl MarketPrefix {
+  /// Create the path to the endpoint providing the latest data of the
+  /// given kind (e.g., "quotes" or "trades").
+  pub(crate) fn latest(&self, kind: &str) -> String {
+    match self {
+      Self::Stocks | Self::Options => format!("{self}{kind}/latest"),
+      Self::Crypto(..) => format!("{self}latest/{kind}"),
+    }
+  }
+}
+
 impl Display for MarketPrefix {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
     match self {
       MarketPrefix::Stocks => write!(f, "/v2/stocks/"),
-      MarketPrefix::Crypto => write!(f, "/v1beta3/crypto/us/")
+      MarketPrefix::Crypto(location) => write!(f, "/v1beta3/crypto/{}/", location.as_str()),
+      MarketPrefix::Options => write!(f, "/v1beta1/options/"),
     }
   }
 }
+
 impl Default for MarketPrefix {
   fn default() -> Self {
     MarketPrefix::Stocks
   }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we create the expected paths for the various markets.
+  #[test]
+  fn paths() {
+    let prefix = MarketPrefix::Stocks;
+    assert_eq!(prefix.to_string(), "/v2/stocks/");
+    assert_eq!(prefix.latest("quotes"), "/v2/stocks/quotes/latest");
+
+    let prefix = MarketPrefix::Crypto(CryptoLocation::default());
+    assert_eq!(prefix.to_string(), "/v1beta3/crypto/us/");
+    assert_eq!(prefix.latest("trades"), "/v1beta3/crypto/us/latest/trades");
+
+    let prefix = MarketPrefix::Crypto(CryptoLocation::Us1);
+    assert_eq!(prefix.to_string(), "/v1beta3/crypto/us-1/");
+
+    let prefix = MarketPrefix::Options;
+    assert_eq!(prefix.to_string(), "/v1beta1/options/");
+    assert_eq!(prefix.latest("quotes"), "/v1beta1/options/quotes/latest");
+  }
+}
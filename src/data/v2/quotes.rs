@@ -1,6 +1,8 @@
 // Copyright (C) 2022-2024 The apca Developers
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::collections::BTreeMap;
+
 use chrono::DateTime;
 use chrono::Utc;
 
@@ -8,10 +10,13 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_urlencoded::to_string as to_query;
 
+use crate::data::v2::prefix::MarketPrefix;
+use crate::data::v2::symbol_items::SymbolItems;
 use crate::data::v2::Feed;
 use crate::data::DATA_BASE_URL;
-use crate::data::v2::prefix::MarketPrefix;
-use crate::util::vec_from_str;
+use crate::util::map_from_str;
+use crate::util::string_slice_to_str;
+use crate::Paginated;
 use crate::Str;
 
 /// A quote as returned by the /v2/stocks/{symbol}/quotes endpoint.
@@ -21,24 +26,51 @@ pub use super::last_quotes::Quote;
 /// A collection of quotes as returned by the API. This is one page of
 /// quotes.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(from = "RawQuotes")]
 pub struct Quotes {
   /// The list of returned quotes.
-  #[serde(rename = "quotes", deserialize_with = "vec_from_str")]
   pub quotes: Vec<Quote>,
   /// The symbol the quotes correspond to.
-  #[serde(rename = "symbol")]
+  ///
+  /// For crypto currencies this member is empty if no quotes were
+  /// reported.
   pub symbol: String,
   /// The token to provide to a request to get the next page of quotes
   /// for this request.
-  #[serde(rename = "next_page_token")]
   pub next_page_token: Option<String>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
-  #[serde(skip)]
   pub _non_exhaustive: (),
 }
 
 
+/// The representation of a page of quotes as reported by the API.
+#[derive(Deserialize)]
+struct RawQuotes {
+  /// The returned quotes.
+  #[serde(rename = "quotes")]
+  quotes: SymbolItems<Quote>,
+  /// The symbol the quotes correspond to, if reported separately.
+  #[serde(rename = "symbol")]
+  symbol: Option<String>,
+  /// The token to provide to a request to get the next page of quotes.
+  #[serde(rename = "next_page_token")]
+  next_page_token: Option<String>,
+}
+
+impl From<RawQuotes> for Quotes {
+  fn from(raw: RawQuotes) -> Self {
+    let SymbolItems { symbol, items } = raw.quotes;
+    Self {
+      quotes: items,
+      symbol: raw.symbol.or(symbol).unwrap_or_default(),
+      next_page_token: raw.next_page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
 /// A helper for initializing [`ListReq`] objects.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct ListReqInit {
@@ -56,7 +88,13 @@ pub struct ListReqInit {
 impl ListReqInit {
   /// Create a [`ListReq`] from a `ListReqInit`.
   #[inline]
-  pub fn init<S>(self, symbol: S, prefix: MarketPrefix, start: DateTime<Utc>, end: DateTime<Utc>) -> ListReq
+  pub fn init<S>(
+    self,
+    symbol: S,
+    prefix: MarketPrefix,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> ListReq
   where
     S: Into<String>,
   {
@@ -79,7 +117,7 @@ impl ListReqInit {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct ListReq {
   /// The symbol to retrieve quotes for.
-  #[serde(skip)]
+  #[serde(rename = "symbols")]
   pub symbol: String,
   /// The path prefix based on the market (e.g. stocks or crypto)
   #[serde(skip)]
@@ -128,7 +166,154 @@ Endpoint! {
 
   #[inline]
   fn path(input: &Self::Input) -> Str {
-    format!("{}{}/quotes", input.prefix, input.symbol).into()
+    format!("{}quotes", input.prefix).into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+
+impl Paginated for List {
+  type Item = Quote;
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.quotes, output.next_page_token)
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/quotes endpoint,
+/// retrieving quotes for multiple symbols at once.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct MultiListReq {
+  /// The symbols for which to retrieve market data.
+  #[serde(rename = "symbols", serialize_with = "string_slice_to_str")]
+  pub symbols: Vec<String>,
+  /// The path prefix based on the market (e.g. stocks or crypto)
+  #[serde(skip)]
+  pub prefix: MarketPrefix,
+  /// The maximum number of quotes to be returned in total, i.e.,
+  /// across all symbols.
+  ///
+  /// It can be between 1 and 10000. Defaults to 1000 if the provided
+  /// value is `None`.
+  #[serde(rename = "limit")]
+  pub limit: Option<usize>,
+  /// Filter quotes equal to or after this time.
+  #[serde(rename = "start")]
+  pub start: DateTime<Utc>,
+  /// Filter quotes equal to or before this time.
+  #[serde(rename = "end")]
+  pub end: DateTime<Utc>,
+  /// The data feed to use.
+  ///
+  /// Defaults to [`IEX`][Feed::IEX] for free users and
+  /// [`SIP`][Feed::SIP] for users with an unlimited subscription.
+  #[serde(rename = "feed")]
+  pub feed: Option<Feed>,
+  /// If provided we will pass a page token to continue where we left off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`MultiListReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MultiListReqInit {
+  /// See `MultiListReq::limit`.
+  pub limit: Option<usize>,
+  /// See `MultiListReq::feed`.
+  pub feed: Option<Feed>,
+  /// See `MultiListReq::page_token`.
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl MultiListReqInit {
+  /// Create a [`MultiListReq`] from a `MultiListReqInit`.
+  #[inline]
+  pub fn init<I, S>(
+    self,
+    symbols: I,
+    prefix: MarketPrefix,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> MultiListReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    MultiListReq {
+      symbols: symbols.into_iter().map(S::into).collect(),
+      prefix,
+      start,
+      end,
+      limit: self.limit,
+      feed: self.feed,
+      page_token: self.page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// A collection of quotes for multiple symbols as returned by the
+/// API. This is one page of quotes.
+///
+/// A page may contain quotes for only a subset of the requested
+/// symbols.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct MultiQuotes {
+  /// A mapping from symbols to the quotes returned for them.
+  #[serde(rename = "quotes", deserialize_with = "map_from_str")]
+  pub quotes: BTreeMap<String, Vec<Quote>>,
+  /// The token to provide to a request to get the next page of quotes
+  /// for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/stocks/quotes endpoint.
+  pub MultiList(MultiListReq),
+  Ok => MultiQuotes, [
+    /// The market data was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => MultiListError, [
+    /// A query parameter was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("{}quotes", input.prefix).into()
   }
 
   fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
@@ -137,21 +322,88 @@ Endpoint! {
 }
 
 
+impl Paginated for MultiList {
+  /// A quote along with the symbol it belongs to.
+  type Item = (String, Quote);
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    let quotes = output
+      .quotes
+      .into_iter()
+      .flat_map(|(symbol, quotes)| quotes.into_iter().map(move |quote| (symbol.clone(), quote)))
+      .collect();
+    (quotes, output.next_page_token)
+  }
+}
+
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
   use std::str::FromStr as _;
 
+  use http_endpoint::Endpoint;
+
   use num_decimal::Num;
 
+  use serde_json::from_str as from_json;
+
   use test_log::test;
 
   use crate::api_info::ApiInfo;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::test::CRYPTO;
   use crate::Client;
   use crate::RequestError;
 
 
+  /// Check that we serialize all symbols of a multi-symbol request.
+  #[test]
+  fn serialize_multi_symbols() {
+    let start = DateTime::from_str("2022-01-04T13:35:59Z").unwrap();
+    let end = DateTime::from_str("2022-01-04T13:36:00Z").unwrap();
+    let request =
+      MultiListReqInit::default().init(["SPY", "AAPL", "MSFT"], MarketPrefix::Stocks, start, end);
+
+    assert_eq!(&*MultiList::path(&request), "/v2/stocks/quotes");
+    let query = MultiList::query(&request).unwrap().unwrap();
+    assert!(query.contains("symbols=SPY%2CAAPL%2CMSFT"), "{query}");
+  }
+
+  /// Verify that we can properly parse a reference multi-symbol quotes
+  /// response.
+  #[test]
+  fn parse_reference_multi_quotes() {
+    let response = r#"{
+    "quotes": {
+      "SPY": [
+        {"t": "2022-01-04T13:35:59.011Z", "ax": "V", "ap": 478.1, "as": 2, "bx": "V", "bp": 478.05, "bs": 1, "c": ["R"], "z": "B"}
+      ],
+      "AAPL": []
+    },
+    "next_page_token": "U1BZfDIwMjItMDEtMDRUMTM6MzU6NTkuMDExWg=="
+}"#;
+
+    let res = from_json::<<MultiList as Endpoint>::Output>(response).unwrap();
+    assert_eq!(res.quotes.len(), 2);
+    assert_eq!(res.quotes["SPY"][0].ask_price, Num::new(4781, 10));
+    assert_eq!(res.quotes["SPY"][0].bid_size, Num::from(1));
+    assert!(res.quotes["AAPL"].is_empty());
+    assert!(res.next_page_token.is_some());
+  }
+
   /// Check that we can retrieve quotes for a specific time frame.
   #[test(tokio::test)]
   async fn request_quotes() {
@@ -170,8 +422,8 @@ mod tests {
       assert!(quote.time <= end, "{}", quote.time);
       assert_ne!(quote.ask_price, Num::from(0));
       assert_ne!(quote.bid_price, Num::from(0));
-      assert_ne!(quote.ask_size, 0);
-      assert_ne!(quote.bid_size, 0);
+      assert_ne!(quote.ask_size, Num::from(0));
+      assert_ne!(quote.bid_size, Num::from(0));
     }
   }
 
@@ -257,4 +509,33 @@ mod tests {
       last_quotes = Some(quotes);
     }
   }
+
+  /// Check that we can retrieve historic quotes for a crypto currency
+  /// pair.
+  #[test(tokio::test)]
+  async fn crypto_quotes() {
+    let start = DateTime::from_str("2024-01-02T00:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T00:00:05Z").unwrap();
+    let request = ListReqInit::default().init("BTC/USD", CRYPTO, start, end);
+
+    let quotes = crypto_client().issue::<List>(&request).await.unwrap();
+    assert_eq!(quotes.symbol, "BTC/USD");
+    assert_eq!(quotes.quotes.len(), 1);
+    assert_eq!(quotes.quotes[0].ask_price, Num::new(450301, 10));
+    assert_eq!(quotes.quotes[0].ask_size, Num::new(5, 10));
+    assert_eq!(quotes.quotes[0].bid_size, Num::new(2514, 10000));
+  }
+
+  /// Check that we report no data for a crypto currency pair without
+  /// quotes in the requested time frame.
+  #[test(tokio::test)]
+  async fn crypto_no_quotes() {
+    let start = DateTime::from_str("2014-01-02T00:00:00Z").unwrap();
+    let end = DateTime::from_str("2014-01-02T00:00:05Z").unwrap();
+    let request = ListReqInit::default().init("BTC/USD", CRYPTO, start, end);
+
+    let quotes = crypto_client().issue::<List>(&request).await.unwrap();
+    assert_eq!(quotes.quotes, Vec::new());
+    assert_eq!(quotes.next_page_token, None);
+  }
 }
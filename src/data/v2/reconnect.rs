@@ -0,0 +1,523 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use futures::future::select;
+use futures::future::Either;
+use futures::stream::FusedStream;
+use futures::Future;
+use futures::FutureExt as _;
+use futures::Stream;
+use futures::StreamExt as _;
+
+use serde::de::DeserializeOwned;
+
+use crate::data::v2::stream::Data;
+use crate::data::v2::stream::DataStream;
+use crate::data::v2::stream::DataSubscription;
+use crate::data::v2::stream::MarketData;
+use crate::data::v2::stream::RealtimeData;
+use crate::data::v2::stream::Source;
+use crate::reconnect::convert;
+use crate::reconnect::Connection;
+use crate::reconnect::Established;
+use crate::reconnect::Message;
+use crate::reconnect::Polled;
+use crate::reconnect::ReconnectFuture;
+use crate::reconnect::Reconnected;
+use crate::retry::reconnect;
+use crate::subscribable::Subscribable;
+use crate::ApiInfo;
+use crate::Error;
+use crate::RetryPolicy;
+
+
+type Item<B, Q, T> = Result<Event<B, Q, T>, Error>;
+type Buffer<B, Q, T> = VecDeque<Item<B, Q, T>>;
+type DataConnection<B, Q, T> =
+  Connection<DataStream<B, Q, T>, DataSubscription<B, Q, T>, Buffer<B, Q, T>>;
+type DataReconnectFuture<B, Q, T> =
+  ReconnectFuture<DataStream<B, Q, T>, DataSubscription<B, Q, T>, Buffer<B, Q, T>>;
+type Session<B, Q, T> = (
+  DataStream<B, Q, T>,
+  DataSubscription<B, Q, T>,
+  Buffer<B, Q, T>,
+);
+
+
+/// A description of a period during which no connection to the
+/// server existed and, hence, market data may have been missed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Gap {
+  /// The time at which the loss of the connection was detected.
+  pub disconnected_at: DateTime<Utc>,
+  /// The time at which the connection was re-established.
+  pub reconnected_at: DateTime<Utc>,
+  /// The number of connection attempts it took to re-establish the
+  /// connection.
+  pub attempts: usize,
+  /// The market data subscriptions that were restored.
+  pub subscriptions: MarketData,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// An event as emitted by a [`ReconnectingData`] stream.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event<B, Q, T> {
+  /// A data item as received over the websocket channel.
+  Data(Data<B, Q, T>),
+  /// The connection was lost and has since been re-established.
+  ///
+  /// Data items published while no connection existed have been
+  /// missed.
+  Reconnected(Gap),
+}
+
+
+/// Drive a future to completion while polling the provided stream,
+/// similar to [`drive`][crate::data::v2::stream::drive], but buffering
+/// instead of discarding all data received in the process.
+async fn drive_buffered<F, St, B, Q, T>(
+  mut future: F,
+  stream: &mut St,
+  buffer: &mut Buffer<B, Q, T>,
+) -> Result<F::Output, Error>
+where
+  F: Future + Unpin,
+  St: FusedStream<Item = Message<Data<B, Q, T>, Error>> + Unpin,
+{
+  loop {
+    match select(future, stream.next()).await {
+      Either::Left((output, _next)) => break Ok(output),
+      Either::Right((Some(message), next)) => {
+        if let Some(result) = convert(message) {
+          buffer.push_back(result.map(Event::Data));
+        }
+        future = next;
+      },
+      // Once the stream has ended the future is expected to resolve
+      // right away.
+      Either::Right((None, next)) => {
+        break next
+          .now_or_never()
+          .ok_or_else(|| Error::Str("stream was closed before operation completed".into()))
+      },
+    }
+  }
+}
+
+
+/// Connect to the server and subscribe to the provided market data.
+async fn establish<S, B, Q, T>(
+  api_info: &ApiInfo,
+  subscriptions: &MarketData,
+) -> Result<Session<B, Q, T>, Error>
+where
+  S: Source,
+  B: Send + Unpin + Debug + DeserializeOwned,
+  Q: Send + Unpin + Debug + DeserializeOwned,
+  T: Send + Unpin + Debug + DeserializeOwned,
+{
+  let (mut stream, mut subscription) = RealtimeData::<S, B, Q, T>::connect(api_info).await?;
+  let mut buffer = VecDeque::new();
+
+  if subscriptions != &MarketData::default() {
+    let subscribe = Box::pin(subscription.subscribe(subscriptions));
+    let () = drive_buffered(subscribe, &mut stream, &mut buffer)
+      .await?
+      .map_err(Error::WebSocket)??;
+  }
+  Ok((stream, subscription, buffer))
+}
+
+
+/// Create a future re-establishing the connection to the server and
+/// restoring the provided market data subscriptions.
+fn reestablish<S, B, Q, T>(
+  api_info: &ApiInfo,
+  policy: RetryPolicy,
+  subscriptions: &MarketData,
+) -> DataReconnectFuture<B, Q, T>
+where
+  S: Source + 'static,
+  B: Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  let api_info = api_info.clone();
+  let subscriptions = subscriptions.clone();
+
+  async move {
+    let api_info = &api_info;
+    let subscriptions = &subscriptions;
+    let ((stream, subscription, buffer), attempts) = reconnect(&policy, move || {
+      establish::<S, B, Q, T>(api_info, subscriptions)
+    })
+    .await?;
+
+    let established = Established {
+      stream,
+      subscription,
+      attempts,
+      extra: buffer,
+    };
+    Ok(established)
+  }
+  .boxed()
+}
+
+
+/// A real time market data stream that transparently re-establishes
+/// the connection to the server when it is lost.
+///
+/// After reconnecting, the stream subscribes to the last confirmed
+/// market data again and emits an [`Event::Reconnected`] event,
+/// informing the consumer that data may have been missed. The stream
+/// only ends once re-establishing the connection failed for good, as
+/// governed by the [`RetryPolicy`] in use, in which case the last
+/// error is reported before.
+///
+/// The bar (`B`), quote (`Q`), and trade (`T`) types can be customized
+/// just as for [`RealtimeData`].
+pub struct ReconnectingData<
+  S: Source,
+  B = <S as Source>::Bar,
+  Q = <S as Source>::Quote,
+  T = <S as Source>::Trade,
+> {
+  /// The information used for connecting to the server.
+  api_info: ApiInfo,
+  /// The policy governing how to re-establish the connection.
+  policy: RetryPolicy,
+  /// The last confirmed market data subscriptions.
+  subscriptions: MarketData,
+  /// Items received but not yet emitted.
+  buffer: Buffer<B, Q, T>,
+  /// The connection to the server.
+  connection: DataConnection<B, Q, T>,
+  /// Phantom data to make sure that we "use" `S`.
+  _phantom: PhantomData<fn() -> S>,
+}
+
+impl<S, B, Q, T> ReconnectingData<S, B, Q, T>
+where
+  S: Source + 'static,
+  B: Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  /// Connect to the server to stream real time market data.
+  ///
+  /// The initial connection attempt is not retried: any error is
+  /// reported directly.
+  pub async fn connect(api_info: ApiInfo, policy: RetryPolicy) -> Result<Self, Error> {
+    let (stream, subscription, buffer) =
+      establish::<S, B, Q, T>(&api_info, &MarketData::default()).await?;
+
+    let slf = Self {
+      api_info,
+      policy,
+      subscriptions: MarketData::default(),
+      buffer,
+      connection: Connection::new(stream, subscription),
+      _phantom: PhantomData,
+    };
+    Ok(slf)
+  }
+
+  /// Handle the re-establishment of the connection.
+  fn reconnected(&mut self, reconnected: Reconnected<Buffer<B, Q, T>>) {
+    let Reconnected {
+      disconnected_at,
+      reconnected_at,
+      attempts,
+      extra: buffer,
+    } = reconnected;
+
+    // SANITY: The connection was just re-established.
+    let (_stream, subscription) = self.connection.get_mut().unwrap();
+    self.subscriptions = subscription.subscriptions().clone();
+
+    let gap = Gap {
+      disconnected_at,
+      reconnected_at,
+      attempts,
+      subscriptions: self.subscriptions.clone(),
+      _non_exhaustive: (),
+    };
+    self.buffer.push_back(Ok(Event::Reconnected(gap)));
+    self.buffer.extend(buffer);
+  }
+
+  /// Retrieve the current connection, waiting for it to be
+  /// re-established if necessary.
+  async fn connection(
+    &mut self,
+  ) -> Result<
+    (
+      &mut DataStream<B, Q, T>,
+      &mut DataSubscription<B, Q, T>,
+      &mut Buffer<B, Q, T>,
+    ),
+    Error,
+  > {
+    if let Some(result) = self.connection.settle().await {
+      let () = self.reconnected(result?);
+    }
+
+    let (stream, subscription) = self.connection.get_mut()?;
+    Ok((stream, subscription, &mut self.buffer))
+  }
+
+  /// Subscribe to the provided market data.
+  ///
+  /// Contained in `subscribe` are the *additional* symbols to subscribe
+  /// to. If the connection is currently being re-established, the
+  /// function waits for that to finish first.
+  pub async fn subscribe(&mut self, subscribe: &MarketData) -> Result<(), Error> {
+    let (stream, subscription, buffer) = self.connection().await?;
+    let future = Box::pin(subscription.subscribe(subscribe));
+    let () = drive_buffered(future, stream, buffer)
+      .await?
+      .map_err(Error::WebSocket)??;
+
+    self.subscriptions = subscription.subscriptions().clone();
+    Ok(())
+  }
+
+  /// Unsubscribe from receiving market data for the provided symbols.
+  ///
+  /// If the connection is currently being re-established, the function
+  /// waits for that to finish first.
+  pub async fn unsubscribe(&mut self, unsubscribe: &MarketData) -> Result<(), Error> {
+    let (stream, subscription, buffer) = self.connection().await?;
+    let future = Box::pin(subscription.unsubscribe(unsubscribe));
+    let () = drive_buffered(future, stream, buffer)
+      .await?
+      .map_err(Error::WebSocket)??;
+
+    self.subscriptions = subscription.subscriptions().clone();
+    Ok(())
+  }
+
+  /// Inquire the last confirmed market data subscriptions, which are
+  /// the ones restored after re-establishing the connection.
+  #[inline]
+  pub fn subscriptions(&self) -> &MarketData {
+    &self.subscriptions
+  }
+}
+
+impl<S, B, Q, T> Debug for ReconnectingData<S, B, Q, T>
+where
+  S: Source,
+{
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    fmt
+      .debug_struct("ReconnectingData")
+      .field("subscriptions", &self.subscriptions)
+      .field("state", &self.connection.state())
+      .finish()
+  }
+}
+
+impl<S, B, Q, T> Stream for ReconnectingData<S, B, Q, T>
+where
+  S: Source + 'static,
+  B: Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  type Item = Item<B, Q, T>;
+
+  fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::get_mut(self);
+
+    loop {
+      if let Some(item) = this.buffer.pop_front() {
+        break Poll::Ready(Some(item))
+      }
+
+      let api_info = &this.api_info;
+      let policy = this.policy;
+      let subscriptions = &this.subscriptions;
+      let polled = this.connection.poll_next(ctx, || {
+        reestablish::<S, B, Q, T>(api_info, policy, subscriptions)
+      });
+
+      match polled {
+        Poll::Ready(Some(Ok(Polled::Message(data)))) => {
+          break Poll::Ready(Some(Ok(Event::Data(data))))
+        },
+        Poll::Ready(Some(Ok(Polled::Reconnected(reconnected)))) => {
+          let () = this.reconnected(reconnected);
+        },
+        Poll::Ready(Some(Err(err))) => break Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => break Poll::Ready(None),
+        Poll::Pending => break Poll::Pending,
+      }
+    }
+  }
+}
+
+impl<S, B, Q, T> FusedStream for ReconnectingData<S, B, Q, T>
+where
+  S: Source + 'static,
+  B: Send + Unpin + Debug + DeserializeOwned + 'static,
+  Q: Send + Unpin + Debug + DeserializeOwned + 'static,
+  T: Send + Unpin + Debug + DeserializeOwned + 'static,
+{
+  #[inline]
+  fn is_terminated(&self) -> bool {
+    self.buffer.is_empty() && self.connection.is_terminated()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::time::Duration;
+
+  use futures::SinkExt as _;
+
+  use num_decimal::Num;
+
+  use test_log::test;
+
+  use websocket_util::test::WebSocketStream;
+  use websocket_util::tungstenite::Error as WebSocketError;
+  use websocket_util::tungstenite::Message;
+
+  use crate::data::v2::stream::IEX;
+  use crate::websocket::test::data_handshake;
+  use crate::websocket::test::mock_api_info;
+  use crate::websocket::test::mock_server_multi;
+
+
+  const SUB_REQ: &str = r#"{"action":"subscribe","bars":["AAPL"],"quotes":[],"trades":[]}"#;
+  const SUB_RESP: &str = r#"[{"T":"subscription","bars":["AAPL"]}]"#;
+  const BAR1: &str = r#"[{"T":"b","S":"AAPL","o":185.1,"h":185.3,"l":185.0,"c":185.2,"v":1200,"t":"2024-03-12T14:30:00Z","n":34,"vw":185.15}]"#;
+  const BAR2: &str = r#"[{"T":"b","S":"AAPL","o":185.4,"h":185.6,"l":185.3,"c":185.5,"v":900,"t":"2024-03-12T14:33:00Z","n":21,"vw":185.47}]"#;
+
+
+  /// Create a `RetryPolicy` that reconnects quickly.
+  fn policy(max_retries: usize) -> RetryPolicy {
+    RetryPolicy {
+      max_retries,
+      initial_backoff: Duration::from_millis(1),
+      jitter: false,
+      ..Default::default()
+    }
+  }
+
+  /// Serve a subscription request on the server side.
+  async fn subscription(stream: &mut WebSocketStream) -> Result<(), WebSocketError> {
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(SUB_REQ.to_string()),
+    );
+    stream.send(Message::Text(SUB_RESP.to_string())).await
+  }
+
+
+  /// Check that we re-establish a lost connection, restore the
+  /// subscriptions, and report the gap.
+  #[test(tokio::test)]
+  async fn reconnect_and_resubscribe() {
+    let addr = mock_server_multi(|idx, mut stream| async move {
+      let () = data_handshake(&mut stream).await?;
+      let () = subscription(&mut stream).await?;
+
+      if idx == 0 {
+        stream.send(Message::Text(BAR1.to_string())).await?;
+        stream.send(Message::Close(None)).await?;
+      } else {
+        stream.send(Message::Text(BAR2.to_string())).await?;
+        // Keep the connection open until the client goes away.
+        while stream.next().await.is_some() {}
+      }
+      Ok(())
+    })
+    .await;
+
+    let mut stream = ReconnectingData::<IEX>::connect(mock_api_info(addr), policy(3))
+      .await
+      .unwrap();
+
+    let mut data = MarketData::default();
+    data.set_bars(["AAPL"]);
+    let () = stream.subscribe(&data).await.unwrap();
+    assert_eq!(stream.subscriptions(), &data);
+
+    match stream.next().await.unwrap().unwrap() {
+      Event::Data(Data::Bar(bar)) => assert_eq!(bar.close_price, Num::new(1852, 10)),
+      event => panic!("received unexpected event: {event:?}"),
+    }
+
+    match stream.next().await.unwrap().unwrap() {
+      Event::Reconnected(gap) => {
+        assert_eq!(gap.attempts, 1);
+        assert_eq!(gap.subscriptions, data);
+        assert!(gap.disconnected_at <= gap.reconnected_at);
+      },
+      event => panic!("received unexpected event: {event:?}"),
+    }
+
+    match stream.next().await.unwrap().unwrap() {
+      Event::Data(Data::Bar(bar)) => assert_eq!(bar.close_price, Num::new(1855, 10)),
+      event => panic!("received unexpected event: {event:?}"),
+    }
+    assert_eq!(stream.subscriptions(), &data);
+  }
+
+  /// Check that the stream ends after re-establishing the connection
+  /// failed for good.
+  #[test(tokio::test)]
+  async fn reconnect_give_up() {
+    let addr = mock_server_multi(|idx, mut stream| async move {
+      // Only the first connection is served properly, all subsequent
+      // ones are closed right away.
+      if idx == 0 {
+        let () = data_handshake(&mut stream).await?;
+        stream.send(Message::Close(None)).await?;
+      }
+      Ok(())
+    })
+    .await;
+
+    let mut stream = ReconnectingData::<IEX>::connect(mock_api_info(addr), policy(2))
+      .await
+      .unwrap();
+
+    let err = stream.next().await.unwrap().unwrap_err();
+    match err {
+      Error::Str(..) | Error::WebSocket(..) => (),
+      _ => panic!("received unexpected error: {err:?}"),
+    }
+    assert!(stream.next().await.is_none());
+    assert!(stream.is_terminated());
+
+    let err = stream.subscribe(&MarketData::default()).await.unwrap_err();
+    match err {
+      Error::Str(ref e) if e == "stream has terminated" => (),
+      _ => panic!("received unexpected error: {err:?}"),
+    }
+  }
+}
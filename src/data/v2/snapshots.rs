@@ -0,0 +1,279 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::from_slice as from_json;
+use serde_urlencoded::to_string as to_query;
+
+use crate::data::v2::bars::Bar;
+use crate::data::v2::last_quotes::Quote;
+use crate::data::v2::trades::Trade;
+use crate::data::DATA_BASE_URL;
+use crate::Str;
+
+/// A GET request to be made to the snapshots endpoint of the market
+/// selected by its prefix, e.g., /v2/stocks/snapshots or
+/// /v1beta3/crypto/{loc}/snapshots.
+pub use super::last_quotes::GetReq;
+/// A helper for initializing [`GetReq`] objects.
+pub use super::last_quotes::GetReqInit;
+
+
+/// A snapshot of the market data of a single symbol, as returned by
+/// the snapshots endpoint.
+///
+/// Members are `None` if no data are available, e.g., for a symbol
+/// that has not been traded yet.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Snapshot {
+  /// The latest trade.
+  #[serde(rename = "latestTrade")]
+  pub latest_trade: Option<Trade>,
+  /// The latest quote.
+  #[serde(rename = "latestQuote")]
+  pub latest_quote: Option<Quote>,
+  /// The most recent minute bar.
+  #[serde(rename = "minuteBar")]
+  pub minute_bar: Option<Bar>,
+  /// The daily bar of the current (or most recent) trading day.
+  #[serde(rename = "dailyBar")]
+  pub daily_bar: Option<Bar>,
+  /// The daily bar of the trading day before that of `daily_bar`.
+  #[serde(rename = "prevDailyBar")]
+  pub prev_daily_bar: Option<Bar>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+EndpointNoParse! {
+  /// The representation of a GET request to the snapshots endpoint
+  /// of a market.
+  pub Get(GetReq),
+  Ok => BTreeMap<String, Snapshot>, [
+    /// The snapshots were retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => GetError, [
+    /// The provided symbol was invalid or not found or the data feed is
+    /// not supported.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("{}snapshots", input.prefix).into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+
+  fn parse(body: &[u8]) -> Result<Self::Output, Self::ConversionError> {
+    /// A helper object for parsing the response to a `Get` request.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Response {
+      /// Snapshots of crypto currencies are wrapped in an object.
+      Crypto { snapshots: BTreeMap<String, Snapshot> },
+      /// Snapshots of stocks are reported as is.
+      Stocks(BTreeMap<String, Snapshot>),
+    }
+
+    from_json::<Response>(body)
+      .map(|response| match response {
+        Response::Crypto { snapshots } | Response::Stocks(snapshots) => snapshots,
+      })
+      .map_err(Self::ConversionError::from)
+  }
+
+  fn parse_err(body: &[u8]) -> Result<Self::ApiError, Vec<u8>> {
+    from_json::<Self::ApiError>(body).map_err(|_| body.to_vec())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use chrono::DateTime;
+  use chrono::Duration;
+  use chrono::Utc;
+
+  use http_endpoint::Endpoint as _;
+
+  use num_decimal::Num;
+
+  use test_log::test;
+
+  use crate::api_info::ApiInfo;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::test::CRYPTO;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  /// Check that we can parse the reference snapshots from the
+  /// documentation.
+  #[test]
+  fn parse_reference_snapshots() {
+    let response = br#"{
+      "AAPL": {
+        "latestTrade": {
+          "t": "2022-04-12T17:05:17.428334819Z",
+          "x": "V",
+          "p": 167.86,
+          "s": 99,
+          "c": ["@"],
+          "i": 7980,
+          "z": "C"
+        },
+        "latestQuote": {
+          "t": "2022-04-12T17:05:36.648751994Z",
+          "ax": "V",
+          "ap": 167.87,
+          "as": 1,
+          "bx": "V",
+          "bp": 167.86,
+          "bs": 3,
+          "c": ["R"],
+          "z": "C"
+        },
+        "minuteBar": {
+          "t": "2022-04-12T17:04:00Z",
+          "o": 167.9,
+          "h": 167.93,
+          "l": 167.85,
+          "c": 167.86,
+          "v": 4263,
+          "n": 41,
+          "vw": 167.89
+        },
+        "dailyBar": {
+          "t": "2022-04-12T04:00:00Z",
+          "o": 168.02,
+          "h": 169.87,
+          "l": 166.64,
+          "c": 167.86,
+          "v": 937226,
+          "n": 8558,
+          "vw": 168.3
+        },
+        "prevDailyBar": {
+          "t": "2022-04-11T04:00:00Z",
+          "o": 168.71,
+          "h": 169.03,
+          "l": 165.5,
+          "c": 165.79,
+          "v": 1076134,
+          "n": 10027,
+          "vw": 166.89
+        }
+      },
+      "NEWIPO": {
+        "latestTrade": null,
+        "latestQuote": null,
+        "minuteBar": null,
+        "dailyBar": null,
+        "prevDailyBar": null
+      }
+    }"#;
+
+    let snapshots = Get::parse(response).unwrap();
+    assert_eq!(snapshots.len(), 2);
+
+    let aapl = &snapshots["AAPL"];
+    let trade = aapl.latest_trade.as_ref().unwrap();
+    assert_eq!(
+      trade.timestamp,
+      DateTime::parse_from_rfc3339("2022-04-12T17:05:17.428334819Z").unwrap()
+    );
+    assert_eq!(trade.price, Num::new(16786, 100));
+    let quote = aapl.latest_quote.as_ref().unwrap();
+    assert_eq!(quote.ask_price, Num::new(16787, 100));
+    assert_eq!(quote.bid_size, Num::from(3));
+    assert_eq!(aapl.minute_bar.as_ref().unwrap().volume, Num::from(4263));
+    assert_eq!(aapl.daily_bar.as_ref().unwrap().open, Num::new(16802, 100));
+    assert_eq!(
+      aapl.prev_daily_bar.as_ref().unwrap().close,
+      Num::new(16579, 100)
+    );
+
+    let ipo = &snapshots["NEWIPO"];
+    assert_eq!(ipo.latest_trade, None);
+    assert_eq!(ipo.latest_quote, None);
+    assert_eq!(ipo.minute_bar, None);
+    assert_eq!(ipo.daily_bar, None);
+    assert_eq!(ipo.prev_daily_bar, None);
+  }
+
+  /// Verify that we can retrieve snapshots for multiple assets.
+  #[test(tokio::test)]
+  async fn request_snapshots() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = GetReqInit::default().init(["SPY", "AAPL"]);
+    let snapshots = client.issue::<Get>(&req).await.unwrap();
+    assert_eq!(
+      snapshots.keys().map(String::as_str).collect::<Vec<_>>(),
+      vec!["AAPL", "SPY"]
+    );
+
+    for snapshot in snapshots.values() {
+      let trade = snapshot.latest_trade.as_ref().unwrap();
+      assert!(trade.timestamp >= Utc::now() - Duration::try_weeks(2).unwrap());
+      assert!(snapshot.latest_quote.is_some());
+      assert!(snapshot.daily_bar.is_some());
+      assert!(snapshot.prev_daily_bar.is_some());
+    }
+  }
+
+  /// Verify that we error out as expected when attempting to retrieve
+  /// a snapshot for an invalid symbol.
+  #[test(tokio::test)]
+  async fn invalid_symbol() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+
+    let req = GetReqInit::default().init(["ABC123"]);
+    let err = client.issue::<Get>(&req).await.unwrap_err();
+    match err {
+      RequestError::Endpoint(GetError::InvalidInput(_)) => (),
+      _ => panic!("Received unexpected error: {err:?}"),
+    };
+  }
+
+  /// Check that we can retrieve snapshots for crypto currency pairs.
+  #[test(tokio::test)]
+  async fn crypto_snapshots() {
+    let request = GetReqInit {
+      prefix: CRYPTO,
+      ..Default::default()
+    }
+    .init(["BTC/USD"]);
+
+    let snapshots = crypto_client().issue::<Get>(&request).await.unwrap();
+    assert_eq!(snapshots.len(), 1);
+
+    let snapshot = &snapshots["BTC/USD"];
+    let trade = snapshot.latest_trade.as_ref().unwrap();
+    assert_eq!(trade.size, Num::new(75, 100000));
+    let quote = snapshot.latest_quote.as_ref().unwrap();
+    assert_eq!(quote.bid_size, Num::new(1735, 10000));
+    let bar = snapshot.minute_bar.as_ref().unwrap();
+    assert_eq!(bar.volume, Num::new(215, 1000));
+    let bar = snapshot.daily_bar.as_ref().unwrap();
+    assert_eq!(bar.open, Num::new(4465230, 100));
+    let bar = snapshot.prev_daily_bar.as_ref().unwrap();
+    assert_eq!(bar.close, Num::new(4465230, 100));
+  }
+}
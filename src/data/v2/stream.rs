@@ -23,6 +23,12 @@ use futures::FutureExt as _;
 use futures::Sink;
 use futures::StreamExt as _;
 
+#[cfg(feature = "msgpack")]
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+#[cfg(feature = "msgpack")]
+use http::HeaderValue;
+
 use num_decimal::Num;
 
 use serde::de::DeserializeOwned;
@@ -34,7 +40,6 @@ use serde::Serialize;
 use serde_json::from_slice as json_from_slice;
 use serde_json::from_str as json_from_str;
 use serde_json::to_string as to_json;
-use serde_json::Error as JsonError;
 
 use thiserror::Error as ThisError;
 
@@ -43,6 +48,7 @@ use tokio::net::TcpStream;
 use tungstenite::MaybeTlsStream;
 use tungstenite::WebSocketStream;
 
+use url::ParseError;
 use url::Url;
 
 use websocket_util::subscribe;
@@ -51,10 +57,16 @@ use websocket_util::tungstenite::Error as WebSocketError;
 use websocket_util::wrap;
 use websocket_util::wrap::Wrapper;
 
+#[cfg(feature = "msgpack")]
+use super::msgpack;
+use super::orderbook::Level;
 use super::unfold::Unfold;
 
+use crate::data::v1beta1::news::NewsArticle;
+use crate::data::v2::prefix::CryptoLocation;
+
 use crate::subscribable::Subscribable;
-use crate::websocket::connect;
+use crate::websocket::connect_with_headers;
 use crate::websocket::MessageResult;
 use crate::ApiInfo;
 use crate::Error;
@@ -80,7 +92,7 @@ where
 }
 
 
-mod private {
+pub(crate) mod private {
   pub trait Sealed {}
 }
 
@@ -91,19 +103,49 @@ pub enum SourceVariant {
   /// The source provided is a path component to be appended to an
   /// already present base URL.
   PathComponent(&'static str),
+  /// The source provided is a complete path to be used with an already
+  /// present base URL.
+  Path(String),
   /// The source provided is a complete URL.
   Url(String),
 }
 
 
+/// An enumeration of the supported wire encodings of real time data
+/// streams.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Encoding {
+  /// Messages are encoded as JSON.
+  Json,
+  /// Messages are encoded as MessagePack.
+  #[cfg(feature = "msgpack")]
+  MessagePack,
+}
+
+
 /// A trait representing the source from which to stream real time data.
 // TODO: Once we can use enumerations as const generic parameters we
 //       should probably switch over to repurposing `data::v2::Feed`
 //       here instead.
 pub trait Source: private::Sealed {
+  /// The type of aggregate bars provided by the source by default.
+  type Bar: Send + Unpin + Debug + DeserializeOwned;
+  /// The type of quotes provided by the source by default.
+  type Quote: Send + Unpin + Debug + DeserializeOwned;
+  /// The type of trades provided by the source by default.
+  type Trade: Send + Unpin + Debug + DeserializeOwned;
+
   /// Return the source.
   #[doc(hidden)]
   fn source() -> SourceVariant;
+
+  /// Return the wire encoding to use for messages exchanged with the
+  /// source.
+  #[inline]
+  fn encoding() -> Encoding {
+    Encoding::Json
+  }
 }
 
 
@@ -115,6 +157,10 @@ pub trait Source: private::Sealed {
 pub enum IEX {}
 
 impl Source for IEX {
+  type Bar = Bar;
+  type Quote = Quote;
+  type Trade = Trade;
+
   #[inline]
   fn source() -> SourceVariant {
     SourceVariant::PathComponent("iex")
@@ -132,6 +178,10 @@ impl private::Sealed for IEX {}
 pub enum SIP {}
 
 impl Source for SIP {
+  type Bar = Bar;
+  type Quote = Quote;
+  type Trade = Trade;
+
   #[inline]
   fn source() -> SourceVariant {
     SourceVariant::PathComponent("sip")
@@ -141,6 +191,73 @@ impl Source for SIP {
 impl private::Sealed for SIP {}
 
 
+/// A trait representing the location for which to stream crypto
+/// currency data.
+// TODO: Once we can use enumerations as const generic parameters we
+//       should switch over to using `CryptoLocation` directly.
+pub trait Location: private::Sealed {
+  /// Return the location.
+  fn location() -> CryptoLocation;
+}
+
+
+/// Stream crypto currency data for the Alpaca US location.
+#[derive(Clone, Copy, Debug)]
+pub enum Us {}
+
+impl Location for Us {
+  #[inline]
+  fn location() -> CryptoLocation {
+    CryptoLocation::Us
+  }
+}
+
+impl private::Sealed for Us {}
+
+
+/// Stream crypto currency data for the Kraken US location.
+#[derive(Clone, Copy, Debug)]
+pub enum Us1 {}
+
+impl Location for Us1 {
+  #[inline]
+  fn location() -> CryptoLocation {
+    CryptoLocation::Us1
+  }
+}
+
+impl private::Sealed for Us1 {}
+
+
+/// Use Alpaca's crypto currency market data, as provided for the
+/// location `L`, as the data source.
+///
+/// This source is available unconditionally. It provides data in the
+/// form of [`CryptoBar`], [`CryptoQuote`], and [`CryptoTrade`] objects
+/// by default.
+#[derive(Clone, Copy, Debug)]
+pub struct Crypto<L = Us> {
+  _phantom: PhantomData<L>,
+}
+
+impl<L> Source for Crypto<L>
+where
+  L: Location,
+{
+  type Bar = CryptoBar;
+  type Quote = CryptoQuote;
+  type Trade = CryptoTrade;
+
+  #[inline]
+  fn source() -> SourceVariant {
+    let location = L::location();
+    SourceVariant::Path(format!("v1beta3/crypto/{}", location.as_str()))
+  }
+}
+
+impl<L> private::Sealed for Crypto<L> {}
+
+
 /// A realtime data source that uses a custom URL.
 ///
 /// This type provides a way to stream realtime data from a custom URL.
@@ -152,14 +269,13 @@ impl private::Sealed for SIP {}
 /// # use apca::Client;
 /// # use apca::data::v2::stream::CustomUrl;
 /// # use apca::data::v2::stream::RealtimeData;
-/// // The v1beta3 crypto API happens to be using the same handshake as
-/// // the v2 stock APIs.
+/// // A server relaying market data, e.g., as part of a test setup.
 /// #[derive(Default)]
-/// struct Crypto;
+/// struct Relay;
 ///
-/// impl ToString for Crypto {
+/// impl ToString for Relay {
 ///   fn to_string(&self) -> String {
-///     "wss://stream.data.alpaca.markets/v1beta3/crypto/us".into()
+///     "ws://localhost:8080/v2/iex".into()
 ///   }
 /// }
 ///
@@ -167,7 +283,7 @@ impl private::Sealed for SIP {}
 /// let client = Client::new(api_info);
 /// # tokio::runtime::Runtime::new().unwrap().block_on(async move {
 /// let (mut stream, mut subscription) = client
-///   .subscribe::<RealtimeData<CustomUrl<Crypto>>>()
+///   .subscribe::<RealtimeData<CustomUrl<Relay>>>()
 ///   .await
 ///   .unwrap();
 /// # })
@@ -184,6 +300,10 @@ impl<URL> Source for CustomUrl<URL>
 where
   URL: Default + ToString,
 {
+  type Bar = Bar;
+  type Quote = Quote;
+  type Trade = Trade;
+
   #[inline]
   fn source() -> SourceVariant {
     let url = URL::default();
@@ -194,6 +314,62 @@ where
 impl<URL> private::Sealed for CustomUrl<URL> {}
 
 
+/// A realtime data source adapter that streams data from the wrapped
+/// source using MessagePack instead of JSON encoded messages.
+///
+/// This type is only available with the `msgpack` feature enabled.
+///
+/// MessagePack is more compact than JSON, which matters for high
+/// volume streams. Some endpoints, e.g., the one for options, support
+/// only MessagePack; they can be used by wrapping a [`CustomUrl`]
+/// source. The bar, quote, and trade types in use, including custom
+/// ones, are decoded just as they are from JSON. Usage could be as
+/// follows:
+/// ```no_run
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// # use apca::data::v2::stream::MessagePack;
+/// # use apca::data::v2::stream::RealtimeData;
+/// # use apca::data::v2::stream::SIP;
+/// let api_info = ApiInfo::from_env().unwrap();
+/// let client = Client::new(api_info);
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async move {
+/// let (mut stream, mut subscription) = client
+///   .subscribe::<RealtimeData<MessagePack<SIP>>>()
+///   .await
+///   .unwrap();
+/// # })
+/// ```
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug)]
+pub struct MessagePack<S> {
+  _phantom: PhantomData<S>,
+}
+
+#[cfg(feature = "msgpack")]
+impl<S> Source for MessagePack<S>
+where
+  S: Source,
+{
+  type Bar = S::Bar;
+  type Quote = S::Quote;
+  type Trade = S::Trade;
+
+  #[inline]
+  fn source() -> SourceVariant {
+    S::source()
+  }
+
+  #[inline]
+  fn encoding() -> Encoding {
+    Encoding::MessagePack
+  }
+}
+
+#[cfg(feature = "msgpack")]
+impl<S> private::Sealed for MessagePack<S> {}
+
+
 /// A symbol.
 pub type Symbol = Str;
 
@@ -321,6 +497,231 @@ pub struct Trade {
 }
 
 
+/// Aggregate data for a crypto currency.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CryptoBar {
+  /// The bar's symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The bar's open price.
+  #[serde(rename = "o")]
+  pub open_price: Num,
+  /// The bar's high price.
+  #[serde(rename = "h")]
+  pub high_price: Num,
+  /// The bar's low price.
+  #[serde(rename = "l")]
+  pub low_price: Num,
+  /// The bar's close price.
+  #[serde(rename = "c")]
+  pub close_price: Num,
+  /// The bar's volume.
+  #[serde(rename = "v")]
+  pub volume: Num,
+  /// The number of trades in the bar.
+  #[serde(rename = "n")]
+  pub trade_count: u64,
+  /// The bar's volume weighted average price.
+  #[serde(rename = "vw")]
+  pub weighted_average: Num,
+  /// The bar's time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
+/// A quote for a crypto currency.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CryptoQuote {
+  /// The quote's symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The bid's price.
+  #[serde(rename = "bp")]
+  pub bid_price: Num,
+  /// The bid's size.
+  #[serde(rename = "bs")]
+  pub bid_size: Num,
+  /// The ask's price.
+  #[serde(rename = "ap")]
+  pub ask_price: Num,
+  /// The ask's size.
+  #[serde(rename = "as")]
+  pub ask_size: Num,
+  /// The quote's time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
+/// A trade for a crypto currency.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CryptoTrade {
+  /// The trade's symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The trade's ID.
+  #[serde(rename = "i")]
+  pub trade_id: u64,
+  /// The trade's price.
+  #[serde(rename = "p")]
+  pub trade_price: Num,
+  /// The trade's size.
+  #[serde(rename = "s")]
+  pub trade_size: Num,
+  /// The trade's time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
+/// An order book update for a crypto currency.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Orderbook {
+  /// The order book's symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The update's time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+  /// The updated bid levels.
+  ///
+  /// A level with a size of zero indicates that it got removed.
+  #[serde(rename = "b")]
+  pub bids: Vec<Level>,
+  /// The updated ask levels.
+  ///
+  /// A level with a size of zero indicates that it got removed.
+  #[serde(rename = "a")]
+  pub asks: Vec<Level>,
+  /// Whether the update is a snapshot of the full order book, in which
+  /// case any previously received state should be discarded.
+  #[serde(rename = "r", default)]
+  pub reset: bool,
+}
+
+
+/// A trading status update for an equity, e.g., a halt or a
+/// resumption of trading.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Status {
+  /// The status' symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The status code, e.g., `H` for a trading halt or `T` for a
+  /// resumption of trading.
+  #[serde(rename = "sc")]
+  pub status_code: String,
+  /// A message describing the status.
+  #[serde(rename = "sm")]
+  pub status_message: String,
+  /// The code of the reason for the status.
+  #[serde(rename = "rc")]
+  pub reason_code: String,
+  /// A message describing the reason for the status.
+  #[serde(rename = "rm")]
+  pub reason_message: String,
+  /// The status' time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
+/// Limit Up - Limit Down (LULD) price bands for an equity.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Luld {
+  /// The LULD bands' symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The upper price band.
+  #[serde(rename = "u")]
+  pub limit_up_price: Num,
+  /// The lower price band.
+  #[serde(rename = "d")]
+  pub limit_down_price: Num,
+  /// The indicator describing the bands' state.
+  #[serde(rename = "i")]
+  pub indicator: String,
+  /// The LULD bands' time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
+/// A correction of a previously reported trade for an equity.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Correction {
+  /// The correction's symbol.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The ID of the original trade.
+  #[serde(rename = "oi")]
+  pub original_trade_id: u64,
+  /// The price of the original trade.
+  #[serde(rename = "op")]
+  pub original_trade_price: Num,
+  /// The size of the original trade.
+  #[serde(rename = "os")]
+  pub original_trade_size: Num,
+  /// The conditions of the original trade.
+  #[serde(rename = "oc", default)]
+  pub original_conditions: Vec<String>,
+  /// The ID of the corrected trade.
+  #[serde(rename = "ci")]
+  pub corrected_trade_id: u64,
+  /// The price of the corrected trade.
+  #[serde(rename = "cp")]
+  pub corrected_trade_price: Num,
+  /// The size of the corrected trade.
+  #[serde(rename = "cs")]
+  pub corrected_trade_size: Num,
+  /// The conditions of the corrected trade.
+  #[serde(rename = "cc", default)]
+  pub corrected_conditions: Vec<String>,
+  /// The correction's time stamp.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
+/// The action a [`CancelError`] describes.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub enum CancelErrorAction {
+  /// The trade was canceled.
+  #[serde(rename = "C")]
+  Cancel,
+  /// The trade was reported in error.
+  #[serde(rename = "E")]
+  Error,
+}
+
+
+/// The cancellation of a previously reported trade for an equity, or
+/// the notice that it was reported in error.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CancelError {
+  /// The symbol of the affected trade.
+  #[serde(rename = "S")]
+  pub symbol: String,
+  /// The ID of the affected trade.
+  #[serde(rename = "i")]
+  pub trade_id: u64,
+  /// The price of the affected trade.
+  #[serde(rename = "p")]
+  pub trade_price: Num,
+  /// The size of the affected trade.
+  #[serde(rename = "s")]
+  pub trade_size: Num,
+  /// The action taken on the trade.
+  #[serde(rename = "a")]
+  pub action: CancelErrorAction,
+  /// The time stamp of the cancellation or error.
+  #[serde(rename = "t")]
+  pub timestamp: DateTime<Utc>,
+}
+
+
 /// An error as reported by the Alpaca Stream API.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, ThisError)]
 #[error("{message} ({code})")]
@@ -344,12 +745,40 @@ pub enum DataMessage<B = Bar, Q = Quote, T = Trade> {
   /// A variant representing aggregate data for a given symbol.
   #[serde(rename = "b")]
   Bar(B),
+  /// A variant representing aggregate data for a given symbol for the
+  /// current day.
+  #[serde(rename = "d")]
+  DailyBar(B),
+  /// A variant representing aggregate data for a given symbol that was
+  /// updated due to late trades.
+  #[serde(rename = "u")]
+  UpdatedBar(B),
   /// A variant representing a quote for a given symbol.
   #[serde(rename = "q")]
   Quote(Q),
   /// A variant representing a trade for a given symbol.
   #[serde(rename = "t")]
   Trade(T),
+  /// A variant representing an order book update for a given symbol.
+  #[serde(rename = "o")]
+  Orderbook(Orderbook),
+  /// A variant representing a news article.
+  #[serde(rename = "n")]
+  News(NewsArticle),
+  /// A variant representing a trading status update for a given
+  /// symbol.
+  #[serde(rename = "s")]
+  Status(Status),
+  /// A variant representing LULD price bands for a given symbol.
+  #[serde(rename = "l")]
+  Luld(Luld),
+  /// A variant representing a trade correction for a given symbol.
+  #[serde(rename = "c")]
+  Correction(Correction),
+  /// A variant representing a trade cancellation or error for a given
+  /// symbol.
+  #[serde(rename = "x")]
+  CancelError(CancelError),
   /// A control message describing the current list of subscriptions.
   #[serde(rename = "subscription")]
   Subscription(MarketData),
@@ -369,19 +798,51 @@ pub enum DataMessage<B = Bar, Q = Quote, T = Trade> {
 pub enum Data<B = Bar, Q = Quote, T = Trade> {
   /// A variant representing aggregate data for a given symbol.
   Bar(B),
+  /// A variant representing aggregate data for a given symbol for the
+  /// current day.
+  DailyBar(B),
+  /// A variant representing aggregate data for a given symbol that was
+  /// updated due to late trades.
+  UpdatedBar(B),
   /// A variant representing quote data for a given symbol.
   Quote(Q),
   /// A variant representing trade data for a given symbol.
   Trade(T),
+  /// A variant representing an order book update for a given symbol.
+  Orderbook(Orderbook),
+  /// A variant representing a news article.
+  News(NewsArticle),
+  /// A variant representing a trading status update, e.g., a halt,
+  /// for a given symbol.
+  Status(Status),
+  /// A variant representing LULD price bands for a given symbol.
+  Luld(Luld),
+  /// A variant representing a trade correction for a given symbol.
+  Correction(Correction),
+  /// A variant representing a trade cancellation or error for a given
+  /// symbol.
+  CancelError(CancelError),
 }
 
-impl Data {
+impl<B, Q, T> Data<B, Q, T> {
   /// Check whether this object is of the `Bar` variant.
   #[inline]
   pub fn is_bar(&self) -> bool {
     matches!(self, Self::Bar(..))
   }
 
+  /// Check whether this object is of the `DailyBar` variant.
+  #[inline]
+  pub fn is_daily_bar(&self) -> bool {
+    matches!(self, Self::DailyBar(..))
+  }
+
+  /// Check whether this object is of the `UpdatedBar` variant.
+  #[inline]
+  pub fn is_updated_bar(&self) -> bool {
+    matches!(self, Self::UpdatedBar(..))
+  }
+
   /// Check whether this object is of the `Quote` variant.
   #[inline]
   pub fn is_quote(&self) -> bool {
@@ -393,12 +854,49 @@ impl Data {
   pub fn is_trade(&self) -> bool {
     matches!(self, Self::Trade(..))
   }
+
+  /// Check whether this object is of the `Orderbook` variant.
+  #[inline]
+  pub fn is_orderbook(&self) -> bool {
+    matches!(self, Self::Orderbook(..))
+  }
+
+  /// Check whether this object is of the `News` variant.
+  #[inline]
+  pub fn is_news(&self) -> bool {
+    matches!(self, Self::News(..))
+  }
+
+  /// Check whether this object is of the `Status` variant.
+  #[inline]
+  pub fn is_status(&self) -> bool {
+    matches!(self, Self::Status(..))
+  }
+
+  /// Check whether this object is of the `Luld` variant.
+  #[inline]
+  pub fn is_luld(&self) -> bool {
+    matches!(self, Self::Luld(..))
+  }
+
+  /// Check whether this object is of the `Correction` variant.
+  #[inline]
+  pub fn is_correction(&self) -> bool {
+    matches!(self, Self::Correction(..))
+  }
+
+  /// Check whether this object is of the `CancelError` variant.
+  #[inline]
+  pub fn is_cancel_error(&self) -> bool {
+    matches!(self, Self::CancelError(..))
+  }
 }
 
 
 /// An enumeration of the supported control messages.
 #[derive(Debug)]
 #[doc(hidden)]
+#[allow(clippy::large_enum_variant)]
 pub enum ControlMessage {
   /// A control message describing the current list of subscriptions.
   Subscription(MarketData),
@@ -411,23 +909,44 @@ pub enum ControlMessage {
 
 
 /// A websocket message that we tried to parse.
-type ParsedMessage<B, Q, T> =
-  MessageResult<Result<DataMessage<B, Q, T>, JsonError>, WebSocketError>;
+type ParsedMessage<B, Q, T> = MessageResult<Result<DataMessage<B, Q, T>, Error>, WebSocketError>;
 
 impl<B, Q, T> subscribe::Message for ParsedMessage<B, Q, T> {
-  type UserMessage = Result<Result<Data<B, Q, T>, JsonError>, WebSocketError>;
+  type UserMessage = Result<Result<Data<B, Q, T>, Error>, WebSocketError>;
   type ControlMessage = ControlMessage;
 
   fn classify(self) -> subscribe::Classification<Self::UserMessage, Self::ControlMessage> {
     match self {
       MessageResult::Ok(Ok(message)) => match message {
         DataMessage::Bar(bar) => subscribe::Classification::UserMessage(Ok(Ok(Data::Bar(bar)))),
+        DataMessage::DailyBar(bar) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::DailyBar(bar))))
+        },
+        DataMessage::UpdatedBar(bar) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::UpdatedBar(bar))))
+        },
         DataMessage::Quote(quote) => {
           subscribe::Classification::UserMessage(Ok(Ok(Data::Quote(quote))))
         },
         DataMessage::Trade(trade) => {
           subscribe::Classification::UserMessage(Ok(Ok(Data::Trade(trade))))
         },
+        DataMessage::Orderbook(orderbook) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::Orderbook(orderbook))))
+        },
+        DataMessage::News(article) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::News(article))))
+        },
+        DataMessage::Status(status) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::Status(status))))
+        },
+        DataMessage::Luld(luld) => subscribe::Classification::UserMessage(Ok(Ok(Data::Luld(luld)))),
+        DataMessage::Correction(correction) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::Correction(correction))))
+        },
+        DataMessage::CancelError(cancel_error) => {
+          subscribe::Classification::UserMessage(Ok(Ok(Data::CancelError(cancel_error))))
+        },
         DataMessage::Subscription(data) => {
           subscribe::Classification::ControlMessage(ControlMessage::Subscription(data))
         },
@@ -436,7 +955,7 @@ impl<B, Q, T> subscribe::Message for ParsedMessage<B, Q, T> {
           subscribe::Classification::ControlMessage(ControlMessage::Error(error))
         },
       },
-      // JSON errors are directly passed through.
+      // Decoding errors are directly passed through.
       MessageResult::Ok(Err(err)) => subscribe::Classification::UserMessage(Ok(Err(err))),
       // WebSocket errors are also directly pushed through.
       MessageResult::Err(err) => subscribe::Classification::UserMessage(Err(err)),
@@ -445,7 +964,7 @@ impl<B, Q, T> subscribe::Message for ParsedMessage<B, Q, T> {
 
   #[inline]
   fn is_error(user_message: &Self::UserMessage) -> bool {
-    // Both outer `WebSocketError` and inner `JsonError` errors
+    // Both outer `WebSocketError` and inner decoding errors
     // constitute errors in our sense. Note, however, that an API error
     // does not. It's just a regular control message from our
     // perspective.
@@ -587,6 +1106,53 @@ pub struct MarketData {
   /// The trades to subscribe to.
   #[serde(default)]
   pub trades: Symbols,
+  /// The daily bars to subscribe to.
+  #[serde(
+    rename = "dailyBars",
+    default,
+    skip_serializing_if = "Symbols::is_empty"
+  )]
+  pub daily_bars: Symbols,
+  /// The updated bars to subscribe to.
+  #[serde(
+    rename = "updatedBars",
+    default,
+    skip_serializing_if = "Symbols::is_empty"
+  )]
+  pub updated_bars: Symbols,
+  /// The order books to subscribe to.
+  ///
+  /// Order books are only available for crypto currencies.
+  #[serde(default, skip_serializing_if = "Symbols::is_empty")]
+  pub orderbooks: Symbols,
+  /// The news to subscribe to.
+  ///
+  /// News are only available when streaming from
+  /// [`NewsSource`][crate::data::v1beta1::news::NewsSource].
+  #[serde(default, skip_serializing_if = "Symbols::is_empty")]
+  pub news: Symbols,
+  /// The trading statuses to subscribe to.
+  #[serde(default, skip_serializing_if = "Symbols::is_empty")]
+  pub statuses: Symbols,
+  /// The LULD price bands to subscribe to.
+  #[serde(default, skip_serializing_if = "Symbols::is_empty")]
+  pub lulds: Symbols,
+  /// The trade corrections to subscribe to.
+  ///
+  /// The server subscribes to corrections implicitly along with
+  /// [`trades`][MarketData::trades].
+  #[serde(default, skip_serializing_if = "Symbols::is_empty")]
+  pub corrections: Symbols,
+  /// The trade cancellations and errors to subscribe to.
+  ///
+  /// The server subscribes to cancellations and errors implicitly
+  /// along with [`trades`][MarketData::trades].
+  #[serde(
+    rename = "cancelErrors",
+    default,
+    skip_serializing_if = "Symbols::is_empty"
+  )]
+  pub cancel_errors: Symbols,
 }
 
 impl MarketData {
@@ -619,20 +1185,100 @@ impl MarketData {
   {
     self.trades = Symbols::List(symbols.into());
   }
-}
 
+  /// A convenience function for setting the
+  /// [`daily_bars`][MarketData::daily_bars] member.
+  #[inline]
+  pub fn set_daily_bars<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.daily_bars = Symbols::List(symbols.into());
+  }
 
-/// A control message "request" sent over a websocket channel.
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
-#[doc(hidden)]
-#[serde(tag = "action")]
-pub enum Request<'d> {
-  /// A control message indicating whether or not we were authenticated
-  /// successfully.
-  #[serde(rename = "auth")]
-  Authenticate {
-    #[serde(rename = "key")]
-    key_id: Cow<'d, str>,
+  /// A convenience function for setting the
+  /// [`updated_bars`][MarketData::updated_bars] member.
+  #[inline]
+  pub fn set_updated_bars<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.updated_bars = Symbols::List(symbols.into());
+  }
+
+  /// A convenience function for setting the
+  /// [`orderbooks`][MarketData::orderbooks] member.
+  #[inline]
+  pub fn set_orderbooks<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.orderbooks = Symbols::List(symbols.into());
+  }
+
+  /// A convenience function for setting the [`news`][MarketData::news]
+  /// member.
+  #[inline]
+  pub fn set_news<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.news = Symbols::List(symbols.into());
+  }
+
+  /// A convenience function for setting the
+  /// [`statuses`][MarketData::statuses] member.
+  #[inline]
+  pub fn set_statuses<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.statuses = Symbols::List(symbols.into());
+  }
+
+  /// A convenience function for setting the [`lulds`][MarketData::lulds]
+  /// member.
+  #[inline]
+  pub fn set_lulds<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.lulds = Symbols::List(symbols.into());
+  }
+
+  /// A convenience function for setting the
+  /// [`corrections`][MarketData::corrections] member.
+  #[inline]
+  pub fn set_corrections<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.corrections = Symbols::List(symbols.into());
+  }
+
+  /// A convenience function for setting the
+  /// [`cancel_errors`][MarketData::cancel_errors] member.
+  #[inline]
+  pub fn set_cancel_errors<S>(&mut self, symbols: S)
+  where
+    S: Into<SymbolList>,
+  {
+    self.cancel_errors = Symbols::List(symbols.into());
+  }
+}
+
+
+/// A control message "request" sent over a websocket channel.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[doc(hidden)]
+#[serde(tag = "action")]
+pub enum Request<'d> {
+  /// A control message indicating whether or not we were authenticated
+  /// successfully.
+  #[serde(rename = "auth")]
+  Authenticate {
+    #[serde(rename = "key")]
+    key_id: Cow<'d, str>,
     #[serde(rename = "secret")]
     secret: Cow<'d, str>,
   },
@@ -662,17 +1308,34 @@ pub struct Subscription<S, B, Q, T> {
   subscription: subscribe::Subscription<S, ParsedMessage<B, Q, T>, wrap::Message>,
   /// The currently active individual market data subscriptions.
   subscriptions: MarketData,
+  /// The encoding to use for requests.
+  encoding: Encoding,
 }
 
 impl<S, B, Q, T> Subscription<S, B, Q, T> {
   /// Create a `Subscription` object wrapping the `websocket_util` based one.
   #[inline]
-  fn new(subscription: subscribe::Subscription<S, ParsedMessage<B, Q, T>, wrap::Message>) -> Self {
+  fn new(
+    subscription: subscribe::Subscription<S, ParsedMessage<B, Q, T>, wrap::Message>,
+    encoding: Encoding,
+  ) -> Self {
     Self {
       subscription,
       subscriptions: MarketData::default(),
+      encoding,
     }
   }
+
+  /// Encode a request as a message in the encoding in use.
+  #[allow(clippy::result_large_err)]
+  fn encode(&self, request: &Request<'_>) -> Result<wrap::Message, Error> {
+    let message = match self.encoding {
+      Encoding::Json => wrap::Message::Text(to_json(request)?),
+      #[cfg(feature = "msgpack")]
+      Encoding::MessagePack => wrap::Message::Binary(msgpack::to_vec(request)?),
+    };
+    Ok(message)
+  }
 }
 
 impl<S, B, Q, T> Subscription<S, B, Q, T>
@@ -689,11 +1352,10 @@ where
       key_id: key_id.into(),
       secret: secret.into(),
     };
-    let json = match to_json(&request) {
-      Ok(json) => json,
-      Err(err) => return Ok(Err(Error::Json(err))),
+    let message = match self.encode(&request) {
+      Ok(message) => message,
+      Err(err) => return Ok(Err(err)),
     };
-    let message = wrap::Message::Text(json);
     let response = self.subscription.send(message).await?;
 
     match response {
@@ -722,11 +1384,10 @@ where
     &mut self,
     request: &Request<'_>,
   ) -> Result<Result<(), Error>, S::Error> {
-    let json = match to_json(request) {
-      Ok(json) => json,
-      Err(err) => return Ok(Err(Error::Json(err))),
+    let message = match self.encode(request) {
+      Ok(message) => message,
+      Err(err) => return Ok(Err(err)),
     };
-    let message = wrap::Message::Text(json);
     let response = self.subscription.send(message).await?;
 
     match response {
@@ -783,28 +1444,59 @@ where
 
 type ParseFn<B, Q, T> = fn(
   Result<wrap::Message, WebSocketError>,
-) -> Result<Result<Vec<DataMessage<B, Q, T>>, JsonError>, WebSocketError>;
+) -> Result<Result<Vec<DataMessage<B, Q, T>>, Error>, WebSocketError>;
 type MapFn<B, Q, T> =
-  fn(Result<Result<DataMessage<B, Q, T>, JsonError>, WebSocketError>) -> ParsedMessage<B, Q, T>;
+  fn(Result<Result<DataMessage<B, Q, T>, Error>, WebSocketError>) -> ParsedMessage<B, Q, T>;
 type Stream<B, Q, T> = Map<
   Unfold<
     Map<Wrapper<WebSocketStream<MaybeTlsStream<TcpStream>>>, ParseFn<B, Q, T>>,
     DataMessage<B, Q, T>,
-    JsonError,
+    Error,
   >,
   MapFn<B, Q, T>,
 >;
+/// The type of stream provided by [`RealtimeData`].
+pub(crate) type DataStream<B, Q, T> =
+  Fuse<MessageStream<SplitStream<Stream<B, Q, T>>, ParsedMessage<B, Q, T>>>;
+/// The type of subscription provided by [`RealtimeData`].
+pub(crate) type DataSubscription<B, Q, T> =
+  Subscription<SplitSink<Stream<B, Q, T>, wrap::Message>, B, Q, T>;
+
+
+/// Create the URL to stream data from the given source from.
+fn stream_url(source: SourceVariant, base_url: &Url) -> Result<Url, ParseError> {
+  let url = match source {
+    SourceVariant::PathComponent(component) => {
+      let mut url = base_url.clone();
+      url.set_path(&format!("v2/{}", component));
+      url
+    },
+    SourceVariant::Path(path) => {
+      let mut url = base_url.clone();
+      url.set_path(&path);
+      url
+    },
+    SourceVariant::Url(url) => Url::parse(&url)?,
+  };
+  Ok(url)
+}
 
 
 /// A type used for requesting a subscription to real time market
 /// data.
 ///
 /// The bar (`B`), quote (`Q`), and trade (`T`) types used can be
-/// overwritten to extend/customize the default types ([`Bar`],
-/// [`Quote`], and [`Trade`], respectively) that are provided by the
-/// library.
+/// overwritten to extend/customize the default types provided by the
+/// library, which depend on the source ([`Bar`], [`Quote`], and
+/// [`Trade`] for stocks and [`CryptoBar`], [`CryptoQuote`], and
+/// [`CryptoTrade`] for crypto currencies).
 #[derive(Debug)]
-pub struct RealtimeData<S, B = Bar, Q = Quote, T = Trade> {
+pub struct RealtimeData<
+  S: Source,
+  B = <S as Source>::Bar,
+  Q = <S as Source>::Quote,
+  T = <S as Source>::Trade,
+> {
   /// Phantom data to make sure that we "use" `S`.
   _phantom: PhantomData<(S, B, Q, T)>,
 }
@@ -818,21 +1510,47 @@ where
   T: Send + Unpin + Debug + DeserializeOwned,
 {
   type Input = ApiInfo;
-  type Subscription = Subscription<SplitSink<Stream<B, Q, T>, wrap::Message>, B, Q, T>;
-  type Stream = Fuse<MessageStream<SplitStream<Stream<B, Q, T>>, ParsedMessage<B, Q, T>>>;
+  type Subscription = DataSubscription<B, Q, T>;
+  type Stream = DataStream<B, Q, T>;
 
   async fn connect(api_info: &Self::Input) -> Result<(Self::Stream, Self::Subscription), Error> {
+    #[allow(clippy::result_large_err)]
     fn parse<B, Q, T>(
       result: Result<wrap::Message, WebSocketError>,
-    ) -> Result<Result<Vec<DataMessage<B, Q, T>>, JsonError>, WebSocketError>
+    ) -> Result<Result<Vec<DataMessage<B, Q, T>>, Error>, WebSocketError>
     where
       B: DeserializeOwned,
       Q: DeserializeOwned,
       T: DeserializeOwned,
     {
       result.map(|message| match message {
-        wrap::Message::Text(string) => json_from_str::<Vec<DataMessage<B, Q, T>>>(&string),
-        wrap::Message::Binary(data) => json_from_slice::<Vec<DataMessage<B, Q, T>>>(&data),
+        wrap::Message::Text(string) => {
+          json_from_str::<Vec<DataMessage<B, Q, T>>>(&string).map_err(Error::from)
+        },
+        wrap::Message::Binary(data) => {
+          json_from_slice::<Vec<DataMessage<B, Q, T>>>(&data).map_err(Error::from)
+        },
+      })
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[allow(clippy::result_large_err)]
+    fn parse_msgpack<B, Q, T>(
+      result: Result<wrap::Message, WebSocketError>,
+    ) -> Result<Result<Vec<DataMessage<B, Q, T>>, Error>, WebSocketError>
+    where
+      B: DeserializeOwned,
+      Q: DeserializeOwned,
+      T: DeserializeOwned,
+    {
+      result.map(|message| match message {
+        // Text messages are always JSON encoded.
+        wrap::Message::Text(string) => {
+          json_from_str::<Vec<DataMessage<B, Q, T>>>(&string).map_err(Error::from)
+        },
+        wrap::Message::Binary(data) => {
+          msgpack::from_slice::<Vec<DataMessage<B, Q, T>>>(&data).map_err(Error::from)
+        },
       })
     }
 
@@ -840,33 +1558,40 @@ where
       data_stream_base_url: url,
       key_id,
       secret,
+      proxy,
       ..
     } = api_info;
 
-    let url = match S::source() {
-      SourceVariant::PathComponent(component) => {
-        let mut url = url.clone();
-        url.set_path(&format!("v2/{}", component));
-        url
+    let url = stream_url(S::source(), url)?;
+    let encoding = S::encoding();
+    let (headers, parse) = match encoding {
+      Encoding::Json => (HeaderMap::new(), parse::<B, Q, T> as ParseFn<_, _, _>),
+      #[cfg(feature = "msgpack")]
+      Encoding::MessagePack => {
+        let mut headers = HeaderMap::new();
+        let _prev = headers.insert(
+          CONTENT_TYPE,
+          HeaderValue::from_static("application/msgpack"),
+        );
+        (headers, parse_msgpack::<B, Q, T> as ParseFn<_, _, _>)
       },
-      SourceVariant::Url(url) => Url::parse(&url)?,
     };
 
     let stream = Unfold::new(
-      connect(&url)
+      connect_with_headers(&url, proxy.as_ref(), headers)
         .await?
-        .map(parse::<B, Q, T> as ParseFn<_, _, _>),
+        .map(parse),
     )
     .map(MessageResult::from as MapFn<B, Q, T>);
     let (send, recv) = stream.split();
     let (stream, subscription) = subscribe::subscribe(recv, send);
     let mut stream = stream.fuse();
-    let mut subscription = Subscription::new(subscription);
+    let mut subscription = Subscription::new(subscription, encoding);
 
     let connect = subscription.subscription.read().boxed();
     let message = drive(connect, &mut stream).await.map_err(|result| {
       result
-        .map(|result| Error::Json(result.unwrap_err()))
+        .map(|result| result.unwrap_err())
         .map_err(Error::WebSocket)
         .unwrap_or_else(|err| err)
     })?;
@@ -889,7 +1614,7 @@ where
     let authenticate = subscription.authenticate(key_id, secret).boxed();
     let () = drive(authenticate, &mut stream).await.map_err(|result| {
       result
-        .map(|result| Error::Json(result.unwrap_err()))
+        .map(|result| result.unwrap_err())
         .map_err(Error::WebSocket)
         .unwrap_or_else(|err| err)
     })???;
@@ -908,21 +1633,39 @@ mod tests {
 
   use chrono::DateTime;
 
+  use futures::future::join;
+  use futures::future::ready;
   use futures::SinkExt as _;
   use futures::TryStreamExt as _;
 
   use serial_test::serial;
 
   use serde_json::from_str as json_from_str;
+  #[cfg(feature = "msgpack")]
+  use serde_json::json;
 
   use test_log::test;
 
+  #[cfg(feature = "msgpack")]
+  use tokio::net::TcpListener;
+  #[cfg(feature = "msgpack")]
+  use tokio::spawn;
   use tokio::time::timeout;
 
+  #[cfg(feature = "msgpack")]
+  use tungstenite::accept_hdr_async;
+  #[cfg(feature = "msgpack")]
+  use tungstenite::tungstenite::handshake::server::Request as HandshakeRequest;
+  #[cfg(feature = "msgpack")]
+  use tungstenite::tungstenite::handshake::server::Response as HandshakeResponse;
+
   use websocket_util::test::WebSocketStream;
   use websocket_util::tungstenite::Message;
 
   use crate::api::API_BASE_URL;
+  use crate::data::v2::orderbook::Book;
+  #[cfg(feature = "msgpack")]
+  use crate::websocket::test::mock_api_info;
   use crate::websocket::test::mock_stream;
   use crate::Client;
 
@@ -1225,6 +1968,274 @@ mod tests {
     );
   }
 
+  /// Check that we can deserialize and serialize the
+  /// [`DataMessage::DailyBar`] and [`DataMessage::UpdatedBar`]
+  /// variants.
+  #[test]
+  fn serialize_deserialize_daily_updated_bar() {
+    let json = r#"{"T":"d","S":"SPY","o":388.985,"h":389.13,"l":388.975,"c":389.12,"v":49378,"t":"2021-02-22T00:00:00Z"}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let bar = match &message {
+      DataMessage::DailyBar(bar) => bar,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(bar.symbol, "SPY");
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+
+    let json = r#"{"T":"u","S":"SPY","o":388.985,"h":389.13,"l":388.975,"c":389.12,"v":49379,"t":"2021-02-22T19:15:00Z"}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let bar = match &message {
+      DataMessage::UpdatedBar(bar) => bar,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(bar.volume, Num::from(49379));
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+  }
+
+  /// Check that we can deserialize and serialize crypto currency
+  /// bars.
+  #[test]
+  fn serialize_deserialize_crypto_bar() {
+    let json = r#"{
+  "T": "b",
+  "S": "BTC/USD",
+  "o": 71856.1435,
+  "h": 71856.1435,
+  "l": 71856.1435,
+  "c": 71856.1435,
+  "v": 0.000772,
+  "t": "2024-03-12T10:37:00Z",
+  "n": 1,
+  "vw": 71856.1435
+}"#;
+
+    type Message = DataMessage<CryptoBar, CryptoQuote, CryptoTrade>;
+
+    let message = json_from_str::<Message>(json).unwrap();
+    let bar = match &message {
+      DataMessage::Bar(bar) => bar,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(bar.symbol, "BTC/USD");
+    assert_eq!(bar.open_price, Num::new(718561435, 10000));
+    assert_eq!(bar.volume, Num::new(772, 1000000));
+    assert_eq!(bar.trade_count, 1);
+    assert_eq!(bar.weighted_average, Num::new(718561435, 10000));
+    assert_eq!(
+      bar.timestamp,
+      DateTime::<Utc>::from_str("2024-03-12T10:37:00Z").unwrap()
+    );
+
+    assert_eq!(
+      json_from_str::<Message>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+  }
+
+  /// Check that we can deserialize and serialize crypto currency
+  /// quotes.
+  #[test]
+  fn serialize_deserialize_crypto_quote() {
+    let json = r#"{
+  "T": "q",
+  "S": "BAT/USD",
+  "bp": 0.35718,
+  "bs": 13445.46,
+  "ap": 0.3581,
+  "as": 13561.902,
+  "t": "2024-03-12T10:29:43.111588173Z"
+}"#;
+
+    type Message = DataMessage<CryptoBar, CryptoQuote, CryptoTrade>;
+
+    let message = json_from_str::<Message>(json).unwrap();
+    let quote = match &message {
+      DataMessage::Quote(quote) => quote,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(quote.symbol, "BAT/USD");
+    assert_eq!(quote.bid_price, Num::new(35718, 100000));
+    assert_eq!(quote.bid_size, Num::new(1344546, 100));
+    assert_eq!(quote.ask_price, Num::new(3581, 10000));
+    assert_eq!(quote.ask_size, Num::new(13561902, 1000));
+
+    assert_eq!(
+      json_from_str::<Message>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+  }
+
+  /// Check that we can deserialize and serialize crypto currency
+  /// trades.
+  #[test]
+  fn serialize_deserialize_crypto_trade() {
+    let json = r#"{
+  "T": "t",
+  "S": "AVAX/USD",
+  "p": 47.299,
+  "s": 0.2057,
+  "t": "2024-03-12T10:27:48.858228144Z",
+  "i": 3447222699101865076,
+  "tks": "S"
+}"#;
+
+    type Message = DataMessage<CryptoBar, CryptoQuote, CryptoTrade>;
+
+    let message = json_from_str::<Message>(json).unwrap();
+    let trade = match &message {
+      DataMessage::Trade(trade) => trade,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(trade.symbol, "AVAX/USD");
+    assert_eq!(trade.trade_id, 3447222699101865076);
+    assert_eq!(trade.trade_price, Num::new(47299, 1000));
+    assert_eq!(trade.trade_size, Num::new(2057, 10000));
+
+    assert_eq!(
+      json_from_str::<Message>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+  }
+
+  /// Check that we create the expected URLs for the various sources.
+  #[test]
+  fn source_urls() {
+    let base_url = Url::parse("wss://stream.data.alpaca.markets").unwrap();
+
+    let url = stream_url(IEX::source(), &base_url).unwrap();
+    assert_eq!(url.as_str(), "wss://stream.data.alpaca.markets/v2/iex");
+
+    let url = stream_url(<Crypto>::source(), &base_url).unwrap();
+    assert_eq!(
+      url.as_str(),
+      "wss://stream.data.alpaca.markets/v1beta3/crypto/us"
+    );
+
+    let url = stream_url(Crypto::<Us1>::source(), &base_url).unwrap();
+    assert_eq!(
+      url.as_str(),
+      "wss://stream.data.alpaca.markets/v1beta3/crypto/us-1"
+    );
+  }
+
+  /// Check that we can deserialize and serialize the
+  /// [`DataMessage::Orderbook`] variant.
+  #[test]
+  fn serialize_deserialize_orderbook() {
+    let json = r#"{
+  "T": "o",
+  "S": "BTC/USD",
+  "t": "2024-03-12T10:38:50.79613221Z",
+  "b": [{"p": 71859.53, "s": 0.27994}],
+  "a": [{"p": 71939.7, "s": 0}, {"p": 71940.1, "s": 0.4}],
+  "r": true
+}"#;
+
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let orderbook = match &message {
+      DataMessage::Orderbook(orderbook) => orderbook,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(orderbook.symbol, "BTC/USD");
+    assert_eq!(
+      orderbook.timestamp,
+      DateTime::<Utc>::from_str("2024-03-12T10:38:50.79613221Z").unwrap()
+    );
+    assert_eq!(orderbook.bids.len(), 1);
+    assert_eq!(orderbook.bids[0].price, Num::new(7185953, 100));
+    assert_eq!(orderbook.bids[0].size, Num::new(27994, 100000));
+    assert_eq!(orderbook.asks.len(), 2);
+    assert_eq!(orderbook.asks[0].size, Num::from(0));
+    assert!(orderbook.reset);
+
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+
+    // Incremental updates usually lack the reset flag.
+    let json = r#"{"T":"o","S":"BTC/USD","t":"2024-03-12T10:38:50.79613221Z","b":[],"a":[]}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    match message {
+      DataMessage::Orderbook(orderbook) => assert!(!orderbook.reset),
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    }
+  }
+
+  /// Check that we can deserialize and serialize trading status and
+  /// LULD messages.
+  #[test]
+  fn serialize_deserialize_status_luld() {
+    let json = r#"{"T":"s","S":"AAPL","sc":"H","sm":"Trading Halt","rc":"T12","rm":"Trading Halted; For information requested by NASDAQ","t":"2021-02-22T15:51:44.208Z","z":"C"}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let status = match &message {
+      DataMessage::Status(status) => status,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(status.symbol, "AAPL");
+    assert_eq!(status.status_code, "H");
+    assert_eq!(status.reason_code, "T12");
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+
+    let json = r#"{"T":"l","S":"AAPL","u":135.12,"d":121.38,"i":"B","t":"2021-02-22T15:51:44.208Z","z":"C"}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let luld = match &message {
+      DataMessage::Luld(luld) => luld,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(luld.limit_up_price, Num::new(13512, 100));
+    assert_eq!(luld.limit_down_price, Num::new(12138, 100));
+    assert_eq!(luld.indicator, "B");
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+  }
+
+  /// Check that we can deserialize and serialize trade correction and
+  /// cancel/error messages.
+  #[test]
+  fn serialize_deserialize_correction_cancel_error() {
+    let json = r#"{"T":"c","S":"AAPL","x":"V","oi":52983525033527,"op":155.45,"os":100,"oc":[" ","T"],"ci":52983525034417,"cp":155.46,"cs":100,"cc":[" ","T"],"t":"2021-02-22T15:51:44.208Z","z":"C"}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let correction = match &message {
+      DataMessage::Correction(correction) => correction,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(correction.original_trade_id, 52983525033527);
+    assert_eq!(correction.original_trade_price, Num::new(15545, 100));
+    assert_eq!(correction.corrected_trade_id, 52983525034417);
+    assert_eq!(correction.corrected_trade_price, Num::new(15546, 100));
+    assert_eq!(correction.corrected_conditions, vec![" ", "T"]);
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+
+    let json = r#"{"T":"x","S":"AAPL","i":52983525033527,"x":"V","p":155.45,"s":100,"a":"C","t":"2021-02-22T15:51:44.208Z","z":"C"}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    let cancel_error = match &message {
+      DataMessage::CancelError(cancel_error) => cancel_error,
+      _ => panic!("Decoded unexpected message variant: {message:?}"),
+    };
+    assert_eq!(cancel_error.trade_id, 52983525033527);
+    assert_eq!(cancel_error.trade_size, Num::from(100));
+    assert_eq!(cancel_error.action, CancelErrorAction::Cancel);
+    assert_eq!(
+      json_from_str::<DataMessage>(&to_json(&message).unwrap()).unwrap(),
+      message
+    );
+  }
+
   /// Check that we can serialize and deserialize the
   /// [`DataMessage::Success`] variant.
   #[test]
@@ -1305,6 +2316,43 @@ mod tests {
     assert_eq!(json_from_str::<Request<'_>>(&json).unwrap(), request);
   }
 
+  /// Check that we include order book subscriptions in requests only
+  /// when there are any.
+  #[test]
+  fn serialize_deserialize_orderbooks_subscribe_request() {
+    let mut data = MarketData::default();
+    data.set_orderbooks(["BTC/USD"]);
+    let request = Request::Subscribe(Cow::Borrowed(&data));
+
+    let json = to_json(&request).unwrap();
+    let expected =
+      r#"{"action":"subscribe","bars":[],"quotes":[],"trades":[],"orderbooks":["BTC/USD"]}"#;
+    assert_eq!(json, expected);
+    assert_eq!(json_from_str::<Request<'_>>(&json).unwrap(), request);
+  }
+
+  /// Check that we include status and LULD subscriptions in requests
+  /// and parse implicit correction and cancel/error subscriptions.
+  #[test]
+  fn serialize_deserialize_status_subscriptions() {
+    let mut data = MarketData::default();
+    data.set_trades(["AAPL"]);
+    data.set_statuses(["AAPL"]);
+    data.set_lulds(["AAPL"]);
+    let request = Request::Subscribe(Cow::Borrowed(&data));
+
+    let json = to_json(&request).unwrap();
+    let expected = r#"{"action":"subscribe","bars":[],"quotes":[],"trades":["AAPL"],"statuses":["AAPL"],"lulds":["AAPL"]}"#;
+    assert_eq!(json, expected);
+    assert_eq!(json_from_str::<Request<'_>>(&json).unwrap(), request);
+
+    let json = r#"{"T":"subscription","trades":["AAPL"],"quotes":[],"bars":[],"statuses":["AAPL"],"lulds":["AAPL"],"corrections":["AAPL"],"cancelErrors":["AAPL"]}"#;
+    let message = json_from_str::<DataMessage>(json).unwrap();
+    data.set_corrections(["AAPL"]);
+    data.set_cancel_errors(["AAPL"]);
+    assert_eq!(message, DataMessage::Subscription(data));
+  }
+
   /// Check that we can serialize and deserialize the
   /// [`Request::Subscribe`] variant properly.
   #[test]
@@ -1391,7 +2439,7 @@ mod tests {
 
     stream
       .map_err(Error::WebSocket)
-      .try_for_each(|result| async { result.map(|_data| ()).map_err(Error::Json) })
+      .try_for_each(|result| async { result.map(|_data| ()) })
       .await
       .unwrap();
   }
@@ -1437,6 +2485,260 @@ mod tests {
     }
   }
 
+  /// Check that we can stream MessagePack encoded data into custom
+  /// types.
+  #[cfg(feature = "msgpack")]
+  #[test(tokio::test)]
+  async fn stream_msgpack() {
+    /// Encode a JSON value as a binary MessagePack message.
+    fn binary(json: &str) -> Message {
+      let value = json_from_str::<serde_json::Value>(json).unwrap();
+      Message::Binary(msgpack::to_vec(&value).unwrap())
+    }
+
+    /// Decode a binary MessagePack message into a JSON value.
+    fn decode(message: Message) -> serde_json::Value {
+      match message {
+        Message::Binary(data) => msgpack::from_slice(&data).unwrap(),
+        message => panic!("received unexpected message: {message:?}"),
+      }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = async move {
+      let (stream, _addr) = listener.accept().await.unwrap();
+      let check = |request: &HandshakeRequest, response: HandshakeResponse| {
+        assert_eq!(
+          request.headers().get(CONTENT_TYPE).unwrap(),
+          "application/msgpack"
+        );
+        Ok(response)
+      };
+      let mut stream = accept_hdr_async(MaybeTlsStream::Plain(stream), check)
+        .await
+        .unwrap();
+
+      stream.send(binary(CONN_RESP)).await.unwrap();
+      let request = decode(stream.next().await.unwrap().unwrap());
+      assert_eq!(
+        request,
+        json_from_str::<serde_json::Value>(AUTH_REQ).unwrap()
+      );
+      stream.send(binary(AUTH_RESP)).await.unwrap();
+
+      let request = decode(stream.next().await.unwrap().unwrap());
+      assert_eq!(
+        request,
+        json!({"action": "subscribe", "bars": [], "quotes": [], "trades": ["SPY"]})
+      );
+      let response = r#"[{"T":"subscription","trades":["SPY"]}]"#;
+      stream.send(binary(response)).await.unwrap();
+
+      let trade = r#"[{
+        "T": "t",
+        "S": "SPY",
+        "i": 96921,
+        "x": "D",
+        "p": 387.62,
+        "s": 100,
+        "c": ["@", "I"],
+        "z": "B"
+      }]"#;
+      let mut data = match binary(trade) {
+        Message::Binary(data) => data,
+        _ => unreachable!(),
+      };
+      // Splice in the time stamp, which has no JSON representation,
+      // increasing the number of fields of the map following the array
+      // marker.
+      assert_eq!(data[1], 0x88);
+      data[1] = 0x89;
+      data.extend_from_slice(&[0xa1, b't', 0xd7, 0xff]);
+      // 2021-02-22T15:51:44.208Z as a MessagePack time stamp.
+      data.extend_from_slice(&((208_000_000u64 << 34) | 1614009104).to_be_bytes());
+      stream.send(Message::Binary(data)).await.unwrap();
+      // A truncated array, which cannot be decoded.
+      stream
+        .send(Message::Binary(vec![0x92, 0x01]))
+        .await
+        .unwrap();
+
+      // Keep the connection open until the client goes away.
+      while stream.next().await.is_some() {}
+    };
+    let _handle = spawn(server);
+
+    let api_info = mock_api_info(addr);
+    let (mut stream, mut subscription) =
+      RealtimeData::<MessagePack<IEX>, Bar, Quote, DetailedTrade>::connect(&api_info)
+        .await
+        .unwrap();
+
+    let mut data = MarketData::default();
+    data.set_trades(["SPY"]);
+
+    let subscribe = subscription.subscribe(&data).boxed();
+    let () = drive(subscribe, &mut stream)
+      .await
+      .unwrap()
+      .unwrap()
+      .unwrap();
+    assert_eq!(subscription.subscriptions(), &data);
+
+    let trade = match stream.next().await.unwrap().unwrap().unwrap() {
+      Data::Trade(trade) => trade,
+      data => panic!("received unexpected data: {data:?}"),
+    };
+    assert_eq!(trade.symbol, "SPY");
+    assert_eq!(trade.trade_id, 96921);
+    assert_eq!(trade.trade_price, Num::new(38762, 100));
+    assert_eq!(trade.trade_size, 100);
+    assert_eq!(trade.conditions, vec!["@", "I"]);
+    assert_eq!(
+      trade.timestamp,
+      DateTime::<Utc>::from_str("2021-02-22T15:51:44.208Z").unwrap()
+    );
+
+    let err = stream.next().await.unwrap().unwrap().unwrap_err();
+    assert!(matches!(err, Error::MessagePackDecode(..)), "{err:?}");
+  }
+
+  /// Check that we can maintain an order book based on the updates
+  /// received over a stream.
+  #[test(tokio::test)]
+  async fn stream_orderbooks() {
+    const SUB_REQ: &str =
+      r#"{"action":"subscribe","bars":[],"quotes":[],"trades":[],"orderbooks":["BTC/USD"]}"#;
+    const SUB_RESP: &str = r#"[{"T":"subscription","orderbooks":["BTC/USD"]}]"#;
+    const SNAPSHOT: &str = r#"[{"T":"o","S":"BTC/USD","t":"2024-03-12T10:38:50.79613221Z","b":[{"p":100,"s":1},{"p":99,"s":2}],"a":[{"p":101,"s":3}],"r":true}]"#;
+    const UPDATE: &str = r#"[{"T":"o","S":"BTC/USD","t":"2024-03-12T10:38:51.10813221Z","b":[{"p":100,"s":0}],"a":[{"p":100.5,"s":4}]}]"#;
+
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      stream.send(Message::Text(CONN_RESP.to_string())).await?;
+      // Authentication.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(AUTH_REQ.to_string()),
+      );
+      stream.send(Message::Text(AUTH_RESP.to_string())).await?;
+
+      // Subscription.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(SUB_REQ.to_string()),
+      );
+      stream.send(Message::Text(SUB_RESP.to_string())).await?;
+      stream.send(Message::Text(SNAPSHOT.to_string())).await?;
+      stream.send(Message::Text(UPDATE.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let (stream, mut subscription) = mock_stream::<RealtimeData<IEX>, _, _>(test).await.unwrap();
+
+    let mut data = MarketData::default();
+    data.set_orderbooks(["BTC/USD"]);
+
+    // The snapshot may arrive right after the subscription
+    // confirmation, so we have to keep collecting data while
+    // subscribing instead of using `drive`, which discards it.
+    let subscribe = subscription.subscribe(&data);
+    let collect = stream
+      .map_err(Error::WebSocket)
+      .and_then(ready)
+      .try_collect::<Vec<_>>();
+    let (result, updates) = join(subscribe, collect).await;
+    let () = result.unwrap().unwrap();
+    let updates = updates.unwrap();
+
+    assert_eq!(subscription.subscriptions(), &data);
+
+    let mut book = Book::default();
+
+    assert_eq!(updates.len(), 2);
+    for data in updates {
+      match data {
+        Data::Orderbook(update) => book.apply(&update),
+        _ => panic!("received unexpected data: {data:?}"),
+      }
+    }
+
+    assert!(book.is_consistent());
+    let (bids, asks) = book.depth(5);
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].price, Num::from(99));
+    assert_eq!(asks.len(), 2);
+    assert_eq!(asks[0].price, Num::new(1005, 10));
+    assert_eq!(asks[0].size, Num::from(4));
+  }
+
+  /// Check that we can stream crypto currency data, including daily
+  /// bars.
+  #[test(tokio::test)]
+  async fn stream_crypto_bars() {
+    const SUB_REQ: &str = r#"{"action":"subscribe","bars":["BTC/USD"],"quotes":[],"trades":[],"dailyBars":["BTC/USD"]}"#;
+    const SUB_RESP: &str = r#"[{"T":"subscription","bars":["BTC/USD"],"dailyBars":["BTC/USD"]}]"#;
+    const DATA: &str = r#"[{"T":"b","S":"BTC/USD","o":71856.1,"h":71860,"l":71850.5,"c":71859.9,"v":0.5,"t":"2024-03-12T10:37:00Z","n":12,"vw":71857.2},{"T":"d","S":"BTC/USD","o":70001,"h":72000,"l":69850.5,"c":71859.9,"v":812.25,"t":"2024-03-12T00:00:00Z","n":20413,"vw":71102.7}]"#;
+
+    async fn test(mut stream: WebSocketStream) -> Result<(), WebSocketError> {
+      stream.send(Message::Text(CONN_RESP.to_string())).await?;
+      // Authentication.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(AUTH_REQ.to_string()),
+      );
+      stream.send(Message::Text(AUTH_RESP.to_string())).await?;
+
+      // Subscription.
+      assert_eq!(
+        stream.next().await.unwrap()?,
+        Message::Text(SUB_REQ.to_string()),
+      );
+      stream.send(Message::Text(SUB_RESP.to_string())).await?;
+      stream.send(Message::Text(DATA.to_string())).await?;
+      stream.send(Message::Close(None)).await?;
+      Ok(())
+    }
+
+    let (stream, mut subscription) = mock_stream::<RealtimeData<Crypto>, _, _>(test)
+      .await
+      .unwrap();
+
+    let mut data = MarketData::default();
+    data.set_bars(["BTC/USD"]);
+    data.set_daily_bars(["BTC/USD"]);
+
+    let subscribe = subscription.subscribe(&data);
+    let collect = stream
+      .map_err(Error::WebSocket)
+      .and_then(ready)
+      .try_collect::<Vec<_>>();
+    let (result, updates) = join(subscribe, collect).await;
+    let () = result.unwrap().unwrap();
+    let updates = updates.unwrap();
+
+    assert_eq!(subscription.subscriptions(), &data);
+    assert_eq!(updates.len(), 2);
+
+    match &updates[0] {
+      Data::Bar(bar) => {
+        assert_eq!(bar.volume, Num::new(5, 10));
+        assert_eq!(bar.trade_count, 12);
+      },
+      data => panic!("received unexpected data: {data:?}"),
+    }
+
+    match &updates[1] {
+      Data::DailyBar(bar) => {
+        assert_eq!(bar.volume, Num::new(81225, 100));
+        assert_eq!(bar.weighted_average, Num::new(711027, 10));
+      },
+      data => panic!("received unexpected data: {data:?}"),
+    }
+  }
+
   /// Check that we can adjust the current market data subscription on
   /// the fly.
   #[test(tokio::test)]
@@ -1501,11 +2803,9 @@ mod tests {
     let read = stream
       .map_err(Error::WebSocket)
       .try_for_each(|result| async {
-        result
-          .map(|data| {
-            assert!(data.is_bar());
-          })
-          .map_err(Error::Json)
+        result.map(|data| {
+          assert!(data.is_bar());
+        })
       });
 
     if timeout(Duration::from_millis(100), read).await.is_ok() {
@@ -1542,11 +2842,9 @@ mod tests {
       let read = stream
         .map_err(Error::WebSocket)
         .try_for_each(|result| async {
-          result
-            .map(|data| {
-              assert!(data.is_quote());
-            })
-            .map_err(Error::Json)
+          result.map(|data| {
+            assert!(data.is_quote());
+          })
         });
 
       if timeout(Duration::from_millis(100), read).await.is_ok() {
@@ -1568,6 +2866,40 @@ mod tests {
     test::<CustomUrl<IexWithUrl>>().await;
   }
 
+  /// Check that we can stream realtime crypto currency quotes.
+  ///
+  /// Crypto currencies are traded around the clock, so we expect to
+  /// receive updates in a timely manner.
+  #[test(tokio::test)]
+  #[serial(realtime_data)]
+  async fn stream_crypto_quotes() {
+    let api_info = ApiInfo::from_env().unwrap();
+    let client = Client::new(api_info);
+    let (mut stream, mut subscription) = client.subscribe::<RealtimeData<Crypto>>().await.unwrap();
+
+    let mut data = MarketData::default();
+    data.set_quotes(["BTC/USD"]);
+
+    let subscribe = subscription.subscribe(&data).boxed_local();
+    let () = drive(subscribe, &mut stream)
+      .await
+      .unwrap()
+      .unwrap()
+      .unwrap();
+
+    let quote = timeout(Duration::from_secs(30), stream.next())
+      .await
+      .unwrap()
+      .unwrap()
+      .unwrap()
+      .unwrap();
+
+    match quote {
+      Data::Quote(quote) => assert_eq!(quote.symbol, "BTC/USD"),
+      data => panic!("received unexpected data: {data:?}"),
+    }
+  }
+
   /// Check that we can stream realtime stock trades.
   #[test(tokio::test)]
   #[serial(realtime_data)]
@@ -1589,11 +2921,9 @@ mod tests {
     let read = stream
       .map_err(Error::WebSocket)
       .try_for_each(|result| async {
-        result
-          .map(|data| {
-            assert!(data.is_trade());
-          })
-          .map_err(Error::Json)
+        result.map(|data| {
+          assert!(data.is_trade());
+        })
       });
 
     if timeout(Duration::from_millis(100), read).await.is_ok() {
@@ -0,0 +1,103 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use serde::de::Error as _;
+use serde::Deserialize;
+use serde::Deserializer;
+
+
+/// The items reported for a single symbol.
+///
+/// Depending on the market, Alpaca reports the items of a single
+/// symbol either as a plain list or as a map from the symbol to such a
+/// list. The latter is the case for crypto currencies, for which only
+/// endpoints accepting multiple symbols exist.
+#[derive(Debug)]
+pub(crate) struct SymbolItems<T> {
+  /// The symbol the items belong to, if it was reported.
+  pub symbol: Option<String>,
+  /// The reported items.
+  pub items: Vec<T>,
+}
+
+impl<'de, T> Deserialize<'de> for SymbolItems<T>
+where
+  T: Deserialize<'de>,
+{
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    /// The forms the items may be reported in.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Items<T> {
+      List(Vec<T>),
+      Map(BTreeMap<String, Vec<T>>),
+    }
+
+    let items = match Option::<Items<T>>::deserialize(deserializer)? {
+      None => Self {
+        symbol: None,
+        items: Vec::new(),
+      },
+      Some(Items::List(items)) => Self {
+        symbol: None,
+        items,
+      },
+      Some(Items::Map(map)) => {
+        if map.len() > 1 {
+          return Err(D::Error::custom(format!(
+            "expected items for a single symbol but found {}",
+            map.len()
+          )))
+        }
+
+        match map.into_iter().next() {
+          Some((symbol, items)) => Self {
+            symbol: Some(symbol),
+            items,
+          },
+          None => Self {
+            symbol: None,
+            items: Vec::new(),
+          },
+        }
+      },
+    };
+    Ok(items)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::from_str as from_json;
+
+
+  /// Check that we can deserialize items in all supported forms.
+  #[test]
+  fn deserialize_items() {
+    let items = from_json::<SymbolItems<u64>>("[1,2]").unwrap();
+    assert_eq!(items.symbol, None);
+    assert_eq!(items.items, vec![1, 2]);
+
+    let items = from_json::<SymbolItems<u64>>(r#"{"BTC/USD":[3]}"#).unwrap();
+    assert_eq!(items.symbol.as_deref(), Some("BTC/USD"));
+    assert_eq!(items.items, vec![3]);
+
+    let items = from_json::<SymbolItems<u64>>("{}").unwrap();
+    assert_eq!(items.symbol, None);
+    assert!(items.items.is_empty());
+
+    let items = from_json::<SymbolItems<u64>>("null").unwrap();
+    assert!(items.items.is_empty());
+
+    let result = from_json::<SymbolItems<u64>>(r#"{"BTC/USD":[3],"ETH/USD":[4]}"#);
+    assert!(result.is_err());
+  }
+}
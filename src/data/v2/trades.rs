@@ -1,6 +1,8 @@
 // Copyright (C) 2022-2024 The apca Developers
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::collections::BTreeMap;
+
 use chrono::DateTime;
 use chrono::Utc;
 
@@ -10,10 +12,13 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_urlencoded::to_string as to_query;
 
+use crate::data::v2::prefix::MarketPrefix;
+use crate::data::v2::symbol_items::SymbolItems;
 use crate::data::v2::Feed;
 use crate::data::DATA_BASE_URL;
-use crate::data::v2::prefix::MarketPrefix;
-use crate::util::vec_from_str;
+use crate::util::map_from_str;
+use crate::util::string_slice_to_str;
+use crate::Paginated;
 use crate::Str;
 
 
@@ -21,7 +26,7 @@ use crate::Str;
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct ListReq {
   /// The symbol for which to retrieve market data.
-  #[serde(skip)]
+  #[serde(rename = "symbols")]
   pub symbol: String,
   /// The path prefix based on the market (e.g. stocks or crypto)
   #[serde(skip)]
@@ -71,7 +76,13 @@ pub struct ListReqInit {
 impl ListReqInit {
   /// Create a [`ListReq`] from a `ListReqInit`.
   #[inline]
-  pub fn init<S>(self, symbol: S, prefix: MarketPrefix, start: DateTime<Utc>, end: DateTime<Utc>) -> ListReq
+  pub fn init<S>(
+    self,
+    symbol: S,
+    prefix: MarketPrefix,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> ListReq
   where
     S: Into<String>,
   {
@@ -100,7 +111,10 @@ pub struct Trade {
   pub price: Num,
   /// The size of the trade.
   #[serde(rename = "s")]
-  pub size: usize,
+  pub size: Num,
+  /// The ID of the trade, if reported.
+  #[serde(rename = "i", default)]
+  pub id: Option<u64>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   #[serde(skip)]
@@ -108,24 +122,53 @@ pub struct Trade {
 }
 
 
-/// A collection of trades as returned by the API. This is one page of trades.
+/// A collection of trades as returned by the API. This is one page of
+/// trades.
 #[derive(Debug, Deserialize, Eq, PartialEq)]
+#[serde(from = "RawTrades")]
 pub struct Trades {
   /// The list of returned trades.
-  #[serde(rename = "trades", deserialize_with = "vec_from_str")]
   pub trades: Vec<Trade>,
   /// The symbol the trades correspond to.
-  #[serde(rename = "symbol")]
+  ///
+  /// For crypto currencies this member is empty if no trades were
+  /// reported.
   pub symbol: String,
-  /// The token to provide to a request to get the next page of trades for this request.
-  #[serde(rename = "next_page_token")]
+  /// The token to provide to a request to get the next page of trades
+  /// for this request.
   pub next_page_token: Option<String>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
-  #[serde(skip)]
   pub _non_exhaustive: (),
 }
 
+
+/// The representation of a page of trades as reported by the API.
+#[derive(Deserialize)]
+struct RawTrades {
+  /// The returned trades.
+  #[serde(rename = "trades")]
+  trades: SymbolItems<Trade>,
+  /// The symbol the trades correspond to, if reported separately.
+  #[serde(rename = "symbol")]
+  symbol: Option<String>,
+  /// The token to provide to a request to get the next page of trades.
+  #[serde(rename = "next_page_token")]
+  next_page_token: Option<String>,
+}
+
+impl From<RawTrades> for Trades {
+  fn from(raw: RawTrades) -> Self {
+    let SymbolItems { symbol, items } = raw.trades;
+    Self {
+      trades: items,
+      symbol: raw.symbol.or(symbol).unwrap_or_default(),
+      next_page_token: raw.next_page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
 Endpoint! {
   /// The representation of a GET request to the /v2/stocks/{symbol}/trades endpoint.
   pub List(ListReq),
@@ -143,7 +186,7 @@ Endpoint! {
   }
 
   fn path(input: &Self::Input) -> Str {
-    format!("{}{}/bars", input.prefix, input.symbol).into()
+    format!("{}trades", input.prefix).into()
   }
 
   fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
@@ -152,6 +195,179 @@ Endpoint! {
 }
 
 
+impl Paginated for List {
+  type Item = Trade;
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    (output.trades, output.next_page_token)
+  }
+}
+
+
+/// A GET request to be issued to the /v2/stocks/trades endpoint,
+/// retrieving trades for multiple symbols at once.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct MultiListReq {
+  /// The symbols for which to retrieve market data.
+  #[serde(rename = "symbols", serialize_with = "string_slice_to_str")]
+  pub symbols: Vec<String>,
+  /// The path prefix based on the market (e.g. stocks or crypto)
+  #[serde(skip)]
+  pub prefix: MarketPrefix,
+  /// The maximum number of trades to be returned in total, i.e.,
+  /// across all symbols.
+  ///
+  /// It can be between 1 and 10000. Defaults to 1000 if the provided
+  /// value is `None`.
+  #[serde(rename = "limit")]
+  pub limit: Option<usize>,
+  /// Filter trades equal to or after this time.
+  #[serde(rename = "start")]
+  pub start: DateTime<Utc>,
+  /// Filter trades equal to or before this time.
+  #[serde(rename = "end")]
+  pub end: DateTime<Utc>,
+  /// The data feed to use.
+  ///
+  /// Defaults to [`IEX`][Feed::IEX] for free users and
+  /// [`SIP`][Feed::SIP] for users with an unlimited subscription.
+  #[serde(rename = "feed")]
+  pub feed: Option<Feed>,
+  /// If provided we will pass a page token to continue where we left off.
+  #[serde(rename = "page_token", skip_serializing_if = "Option::is_none")]
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A helper for initializing [`MultiListReq`] objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MultiListReqInit {
+  /// See `MultiListReq::limit`.
+  pub limit: Option<usize>,
+  /// See `MultiListReq::feed`.
+  pub feed: Option<Feed>,
+  /// See `MultiListReq::page_token`.
+  pub page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl MultiListReqInit {
+  /// Create a [`MultiListReq`] from a `MultiListReqInit`.
+  #[inline]
+  pub fn init<I, S>(
+    self,
+    symbols: I,
+    prefix: MarketPrefix,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> MultiListReq
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    MultiListReq {
+      symbols: symbols.into_iter().map(S::into).collect(),
+      prefix,
+      start,
+      end,
+      limit: self.limit,
+      feed: self.feed,
+      page_token: self.page_token,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// A collection of trades for multiple symbols as returned by the
+/// API. This is one page of trades.
+///
+/// A page may contain trades for only a subset of the requested
+/// symbols.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct MultiTrades {
+  /// A mapping from symbols to the trades returned for them.
+  #[serde(rename = "trades", deserialize_with = "map_from_str")]
+  pub trades: BTreeMap<String, Vec<Trade>>,
+  /// The token to provide to a request to get the next page of trades
+  /// for this request.
+  #[serde(rename = "next_page_token")]
+  pub next_page_token: Option<String>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  #[serde(skip)]
+  pub _non_exhaustive: (),
+}
+
+Endpoint! {
+  /// The representation of a GET request to the /v2/stocks/trades endpoint.
+  pub MultiList(MultiListReq),
+  Ok => MultiTrades, [
+    /// The market data was retrieved successfully.
+    /* 200 */ OK,
+  ],
+  Err => MultiListError, [
+    /// A query parameter was invalid.
+    /* 400 */ BAD_REQUEST => InvalidInput,
+  ]
+
+  fn base_url() -> Option<Str> {
+    Some(DATA_BASE_URL.into())
+  }
+
+  fn path(input: &Self::Input) -> Str {
+    format!("{}trades", input.prefix).into()
+  }
+
+  fn query(input: &Self::Input) -> Result<Option<Str>, Self::ConversionError> {
+    Ok(Some(to_query(input)?.into()))
+  }
+}
+
+
+impl Paginated for MultiList {
+  /// A trade along with the symbol it belongs to.
+  type Item = (String, Trade);
+
+  #[inline]
+  fn page_token(input: &Self::Input) -> Option<&str> {
+    input.page_token.as_deref()
+  }
+
+  #[inline]
+  fn set_page_token(input: &mut Self::Input, token: Option<String>) {
+    input.page_token = token;
+  }
+
+  #[inline]
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>) {
+    let trades = output
+      .trades
+      .into_iter()
+      .flat_map(|(symbol, trades)| trades.into_iter().map(move |trade| (symbol.clone(), trade)))
+      .collect();
+    (trades, output.next_page_token)
+  }
+}
+
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -165,6 +381,8 @@ mod tests {
   use test_log::test;
 
   use crate::api_info::ApiInfo;
+  use crate::data::v2::test::crypto_client;
+  use crate::data::v2::test::CRYPTO;
   use crate::Client;
   use crate::RequestError;
 
@@ -210,11 +428,43 @@ mod tests {
     let timestamp = trades[0].timestamp.to_rfc3339();
     assert!(timestamp.starts_with(expected_time), "{timestamp}");
     assert_eq!(trades[0].price, Num::new(38762, 100));
-    assert_eq!(trades[0].size, 100);
+    assert_eq!(trades[0].size, Num::from(100));
     assert_eq!(res.symbol, "SPY".to_string());
     assert!(res.next_page_token.is_some())
   }
 
+  /// Verify that we can properly parse a reference multi-symbol trades
+  /// response.
+  #[test]
+  fn parse_reference_multi_trades() {
+    let response = r#"{
+    "trades": {
+      "SPY": [
+        {"t": "2021-02-06T13:04:56.334320128Z", "x": "C", "p": 387.62, "s": 100, "i": 52983525029461, "z": "B"}
+      ],
+      "AAPL": [
+        {"t": "2021-02-06T13:05:12.118000000Z", "x": "P", "p": 136.91, "s": 5, "i": 1234, "z": "C"},
+        {"t": "2021-02-06T13:05:13.118000000Z", "x": "P", "p": 136.92, "s": 10, "i": 1235, "z": "C"}
+      ]
+    },
+    "next_page_token": null
+}"#;
+
+    let res = from_json::<<MultiList as Endpoint>::Output>(response).unwrap();
+    assert_eq!(res.trades.len(), 2);
+    assert_eq!(res.trades["SPY"][0].price, Num::new(38762, 100));
+    assert_eq!(res.trades["AAPL"][1].size, Num::from(10));
+    assert_eq!(res.next_page_token, None);
+
+    let (trades, next) = MultiList::into_items(res);
+    let symbols = trades
+      .iter()
+      .map(|(symbol, _trade)| symbol.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(symbols, vec!["AAPL", "AAPL", "SPY"]);
+    assert_eq!(next, None);
+  }
+
   /// Check that we can decode a response containing no trades correctly.
   #[test(tokio::test)]
   async fn no_trades() {
@@ -249,7 +499,7 @@ mod tests {
     let timestamp = trades[0].timestamp.to_rfc3339();
     assert!(timestamp.starts_with(expected_time), "{timestamp}");
     assert_eq!(trades[0].price, Num::new(4608, 25));
-    assert_eq!(trades[0].size, 6);
+    assert_eq!(trades[0].size, Num::from(6));
     assert_eq!(res.symbol, "AAPL".to_string());
     assert!(res.next_page_token.is_some())
   }
@@ -343,4 +593,20 @@ mod tests {
       _ => panic!("Received unexpected error: {err:?}"),
     };
   }
+
+  /// Check that we can retrieve historic trades for a crypto currency
+  /// pair.
+  #[test(tokio::test)]
+  async fn crypto_trades() {
+    let start = DateTime::from_str("2024-01-02T00:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T00:00:05Z").unwrap();
+    let request = ListReqInit::default().init("BTC/USD", CRYPTO, start, end);
+
+    let trades = crypto_client().issue::<List>(&request).await.unwrap();
+    assert_eq!(trades.symbol, "BTC/USD");
+    assert_eq!(trades.trades.len(), 2);
+    assert_eq!(trades.trades[0].price, Num::new(450205, 10));
+    assert_eq!(trades.trades[0].size, Num::new(12, 10000));
+    assert_eq!(trades.trades[1].size, Num::new(3, 10));
+  }
 }
@@ -11,11 +11,18 @@ use std::str::from_utf8;
 use http::Error as HttpError;
 use http::StatusCode as HttpStatusCode;
 use hyper::Error as HyperError;
+#[cfg(feature = "msgpack")]
+use rmp_serde::decode::Error as MessagePackDecodeError;
+#[cfg(feature = "msgpack")]
+use rmp_serde::encode::Error as MessagePackEncodeError;
 use serde_json::Error as JsonError;
 use thiserror::Error;
 use url::ParseError;
 use websocket_util::tungstenite::Error as WebSocketError;
 
+use crate::timeout::TimeoutPhase;
+use crate::transport::TransportError;
+use crate::ResponseMeta;
 use crate::Str;
 
 
@@ -39,6 +46,34 @@ pub enum RequestError<E> {
     #[source]
     IoError,
   ),
+  /// An error reported by a custom transport.
+  #[error("the transport reported an error")]
+  Transport(#[source] TransportError),
+  /// The request timed out.
+  ///
+  /// Unless the timeout occurred while connecting, the outcome of the
+  /// request is unknown: the server may or may not have acted on it.
+  #[error("the request timed out while {0}")]
+  Timeout(TimeoutPhase),
+}
+
+
+/// An error encountered while issuing a request, along with metadata
+/// about the response, if one was received.
+#[derive(Debug, Error)]
+#[error("failed to issue request")]
+pub struct RequestErrorWithMeta<E> {
+  /// The actual error.
+  #[source]
+  pub error: RequestError<E>,
+  /// Metadata about the response, if any.
+  pub meta: Option<ResponseMeta>,
+}
+
+impl<E> From<RequestError<E>> for RequestErrorWithMeta<E> {
+  fn from(error: RequestError<E>) -> Self {
+    Self { error, meta: None }
+  }
 }
 
 
@@ -77,6 +112,22 @@ pub enum Error {
     #[source]
     JsonError,
   ),
+  /// A MessagePack decoding error.
+  #[cfg(feature = "msgpack")]
+  #[error("failed to decode MessagePack data")]
+  MessagePackDecode(
+    #[from]
+    #[source]
+    MessagePackDecodeError,
+  ),
+  /// A MessagePack encoding error.
+  #[cfg(feature = "msgpack")]
+  #[error("failed to encode MessagePack data")]
+  MessagePackEncode(
+    #[from]
+    #[source]
+    MessagePackEncodeError,
+  ),
   /// An error directly originating in this crate.
   #[error("{0}")]
   Str(Str),
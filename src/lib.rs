@@ -100,21 +100,47 @@ pub mod api;
 
 /// A module for retrieving market data.
 pub mod data;
+#[cfg(feature = "test-server")]
+pub mod test_server;
 
 mod api_info;
+mod cassette;
 mod client;
+mod compression;
 mod error;
+mod meta;
+mod paginate;
+mod proxy;
+mod rate_limit;
+mod reconnect;
+mod retry;
 mod subscribable;
+mod timeout;
+mod tls;
+mod transport;
 mod util;
 mod websocket;
 
 use std::borrow::Cow;
 
 pub use crate::api_info::ApiInfo;
+pub use crate::cassette::Cassette;
+pub use crate::cassette::CassetteMode;
 pub use crate::client::Client;
 pub use crate::endpoint::ApiError;
 pub use crate::error::Error;
 pub use crate::error::RequestError;
+pub use crate::error::RequestErrorWithMeta;
+pub use crate::meta::ResponseMeta;
+pub use crate::paginate::Paginated;
+pub use crate::paginate::Paginator;
+pub use crate::proxy::Proxy;
+pub use crate::rate_limit::RateLimiter;
+pub use crate::retry::RetryPolicy;
 pub use crate::subscribable::Subscribable;
+pub use crate::timeout::TimeoutPhase;
+pub use crate::timeout::Timeouts;
+pub use crate::transport::Transport;
+pub use crate::transport::TransportError;
 
 type Str = Cow<'static, str>;
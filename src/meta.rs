@@ -0,0 +1,152 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::from_utf8;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use http::HeaderMap;
+use http::StatusCode;
+
+use crate::api::HDR_RATE_LIMIT_LIMIT;
+use crate::api::HDR_RATE_LIMIT_REMAINING;
+use crate::api::HDR_RATE_LIMIT_RESET;
+use crate::api::HDR_REQUEST_ID;
+
+
+/// The response headers we retain as part of a [`ResponseMeta`] object.
+const SELECTED_HEADERS: [&str; 6] = [
+  HDR_REQUEST_ID,
+  HDR_RATE_LIMIT_LIMIT,
+  HDR_RATE_LIMIT_REMAINING,
+  HDR_RATE_LIMIT_RESET,
+  "retry-after",
+  "date",
+];
+
+
+/// Metadata about a response received for a request.
+#[derive(Clone, Debug)]
+pub struct ResponseMeta {
+  /// The HTTP status of the response.
+  pub status: StatusCode,
+  /// A selection of headers of the response, including the rate limit
+  /// related ones.
+  pub headers: HeaderMap,
+  /// The ID Alpaca assigned to the request.
+  ///
+  /// Alpaca support may ask for this ID when investigating problems.
+  pub request_id: Option<String>,
+  /// The time it took from issuing the request until the response was
+  /// received in its entirety.
+  pub latency: Duration,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl ResponseMeta {
+  /// Create a `ResponseMeta` object from a response's status and
+  /// headers, along with the latency observed.
+  pub(crate) fn new(status: StatusCode, headers: &HeaderMap, latency: Duration) -> Self {
+    let headers = SELECTED_HEADERS
+      .iter()
+      .filter_map(|name| {
+        headers
+          .get(*name)
+          .map(|value| (name.parse().unwrap(), value.clone()))
+      })
+      .collect::<HeaderMap>();
+    let request_id = headers
+      .get(HDR_REQUEST_ID)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_owned);
+
+    Self {
+      status,
+      headers,
+      request_id,
+      latency,
+      _non_exhaustive: (),
+    }
+  }
+
+  /// Retrieve the value of a numeric header.
+  fn numeric_header(&self, name: &str) -> Option<i64> {
+    self
+      .headers
+      .get(name)
+      .and_then(|value| from_utf8(value.as_bytes()).ok())
+      .and_then(|value| value.trim().parse::<i64>().ok())
+  }
+
+  /// Retrieve the maximum number of requests per minute, as reported by
+  /// the server.
+  #[inline]
+  pub fn rate_limit(&self) -> Option<u64> {
+    self
+      .numeric_header(HDR_RATE_LIMIT_LIMIT)
+      .and_then(|limit| u64::try_from(limit).ok())
+  }
+
+  /// Retrieve the number of requests remaining in the current rate
+  /// limit window, as reported by the server.
+  #[inline]
+  pub fn rate_limit_remaining(&self) -> Option<u64> {
+    self
+      .numeric_header(HDR_RATE_LIMIT_REMAINING)
+      .and_then(|remaining| u64::try_from(remaining).ok())
+  }
+
+  /// Retrieve the time at which the current rate limit window ends, as
+  /// reported by the server.
+  #[inline]
+  pub fn rate_limit_reset(&self) -> Option<DateTime<Utc>> {
+    self
+      .numeric_header(HDR_RATE_LIMIT_RESET)
+      .and_then(|reset| DateTime::from_timestamp(reset, 0))
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http::header::CONTENT_TYPE;
+  use http::HeaderValue;
+
+  use test_log::test;
+
+
+  /// Check that we retain and decode the expected headers.
+  #[test]
+  fn select_headers() {
+    let mut headers = HeaderMap::new();
+    let _ = headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let _ = headers.insert(HDR_REQUEST_ID, HeaderValue::from_static("8fb2cbd3e8f2c8a1"));
+    let _ = headers.insert(HDR_RATE_LIMIT_LIMIT, HeaderValue::from_static("200"));
+    let _ = headers.insert(HDR_RATE_LIMIT_REMAINING, HeaderValue::from_static("199"));
+    let _ = headers.insert(HDR_RATE_LIMIT_RESET, HeaderValue::from_static("1704189600"));
+
+    let latency = Duration::from_millis(42);
+    let meta = ResponseMeta::new(StatusCode::OK, &headers, latency);
+    assert_eq!(meta.status, StatusCode::OK);
+    assert_eq!(meta.latency, latency);
+    assert_eq!(meta.headers.len(), 4);
+    assert_eq!(meta.headers.get(CONTENT_TYPE), None);
+    assert_eq!(meta.request_id.as_deref(), Some("8fb2cbd3e8f2c8a1"));
+    assert_eq!(meta.rate_limit(), Some(200));
+    assert_eq!(meta.rate_limit_remaining(), Some(199));
+    assert_eq!(
+      meta.rate_limit_reset(),
+      Some(
+        DateTime::parse_from_rfc3339("2024-01-02T10:00:00Z")
+          .unwrap()
+          .into()
+      )
+    );
+  }
+}
@@ -0,0 +1,558 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::vec::IntoIter;
+
+use futures::task::Context;
+use futures::task::Poll;
+use futures::Stream;
+use futures::TryStreamExt as _;
+
+use http_endpoint::Endpoint;
+
+use crate::Client;
+use crate::RequestError;
+
+
+/// The default maximum number of pages a [`Paginator`] fetches ahead
+/// of the consumer.
+const DEFAULT_MAX_PAGES: usize = 2;
+
+
+/// A trait for endpoints whose output is split into pages, with each
+/// page referring to the next one by means of a token.
+pub trait Paginated: Endpoint {
+  /// The type of the individual items contained in a page.
+  type Item;
+
+  /// Retrieve the token of the page to request.
+  ///
+  /// A token of `None` refers to the first page.
+  fn page_token(input: &Self::Input) -> Option<&str>;
+
+  /// Set the token of the page to request.
+  fn set_page_token(input: &mut Self::Input, token: Option<String>);
+
+  /// Split a page into the items it contains and the token of the
+  /// next page, if any.
+  fn into_items(output: Self::Output) -> (Vec<Self::Item>, Option<String>);
+}
+
+
+/// The future representing the retrieval of a single page.
+type PageFuture<'c, P> = Pin<
+  Box<
+    dyn Future<Output = Result<<P as Endpoint>::Output, RequestError<<P as Endpoint>::Error>>>
+      + Send
+      + 'c,
+  >,
+>;
+
+
+/// A page that was received but not yet (fully) handed out.
+struct Page<T> {
+  /// The token that was used for requesting the page.
+  token: Option<String>,
+  /// The items of the page.
+  items: IntoIter<T>,
+}
+
+
+/// A stream over the individual items of a paginated endpoint,
+/// following page tokens automatically.
+///
+/// Objects of this type are created by [`Client::paginate`]. While
+/// items are being consumed, up to a configurable number of subsequent
+/// pages are retrieved ahead of time (see [`Paginator::max_pages`]).
+///
+/// The stream ends after the last page has been consumed or after an
+/// error was reported.
+#[must_use = "streams do nothing unless polled"]
+pub struct Paginator<'c, P>
+where
+  P: Paginated,
+{
+  /// The client used for issuing requests.
+  client: &'c Client,
+  /// The input for retrieving the next page, if there is one.
+  input: Option<P::Input>,
+  /// The token used in `input`.
+  token: Option<String>,
+  /// The retrieval of the next page, if in progress.
+  fetch: Option<PageFuture<'c, P>>,
+  /// Pages received but not yet handed out, including the current one.
+  pages: VecDeque<Page<P::Item>>,
+  /// An error to report once all pages before it have been handed out.
+  error: Option<RequestError<P::Error>>,
+  /// The maximum number of pages to buffer.
+  max_pages: usize,
+}
+
+impl<'c, P> Paginator<'c, P>
+where
+  P: Paginated,
+{
+  /// Create a new `Paginator` retrieving pages starting at the one
+  /// referenced by the page token set on `input`, if any.
+  pub(crate) fn new(client: &'c Client, input: P::Input) -> Self {
+    let token = P::page_token(&input).map(str::to_string);
+    Self {
+      client,
+      input: Some(input),
+      token,
+      fetch: None,
+      pages: VecDeque::new(),
+      error: None,
+      max_pages: DEFAULT_MAX_PAGES,
+    }
+  }
+
+  /// Set the maximum number of pages to retrieve ahead of the one
+  /// whose items are currently being handed out.
+  ///
+  /// A value of zero means that a page is only requested once all items
+  /// of the previous one were consumed. The default is two.
+  #[inline]
+  pub fn max_pages(mut self, max_pages: usize) -> Self {
+    self.max_pages = max_pages;
+    self
+  }
+
+  /// Retrieve the token of the page that the most recently handed out
+  /// item belongs to.
+  ///
+  /// Setting this token on a request allows for resuming iteration
+  /// later on. Note that iteration starts at the beginning of the page
+  /// then, i.e., items of it that were already handed out will be
+  /// reported again. A token of `None` refers to the first page.
+  #[inline]
+  pub fn page_token(&self) -> Option<&str> {
+    match self.pages.front() {
+      Some(page) => page.token.as_deref(),
+      None => self.token.as_deref(),
+    }
+  }
+}
+
+impl<'c, P> Paginator<'c, P>
+where
+  P: Paginated + 'c,
+  P::Output: Send,
+  P::Error: Send,
+{
+  /// Drive the retrieval of pages as far as possible.
+  fn poll_pages(&mut self, ctx: &mut Context<'_>) {
+    loop {
+      if self.fetch.is_none() {
+        // The first page counts as the current one.
+        if self.pages.len() > self.max_pages {
+          break
+        }
+
+        match &self.input {
+          Some(input) => {
+            let future = self.client.issue::<P>(input);
+            self.fetch = Some(Box::pin(future));
+          },
+          None => break,
+        }
+      }
+
+      // SANITY: We ensured that a retrieval is in progress above.
+      let fetch = self.fetch.as_mut().unwrap();
+      match fetch.as_mut().poll(ctx) {
+        Poll::Ready(result) => {
+          self.fetch = None;
+
+          match result {
+            Ok(output) => {
+              let (items, next) = P::into_items(output);
+              let token = self.token.take();
+              let () = self.pages.push_back(Page {
+                token,
+                items: items.into_iter(),
+              });
+
+              match (&mut self.input, next) {
+                (Some(input), Some(next)) => {
+                  P::set_page_token(input, Some(next.clone()));
+                  self.token = Some(next);
+                },
+                (_, _) => self.input = None,
+              }
+            },
+            Err(err) => {
+              self.error = Some(err);
+              self.input = None;
+            },
+          }
+        },
+        Poll::Pending => break,
+      }
+    }
+  }
+}
+
+impl<'c, P, K, V> Paginator<'c, P>
+where
+  P: Paginated<Item = (K, V)> + 'c,
+  P::Output: Send,
+  P::Error: Send,
+  K: Ord,
+{
+  /// Retrieve all remaining items, grouping their values by key.
+  ///
+  /// This method is useful for endpoints reporting data for multiple
+  /// symbols, where the data of a single symbol may be spread over
+  /// multiple pages. Values are kept in the order in which they were
+  /// reported.
+  pub async fn collect_grouped(self) -> Result<BTreeMap<K, Vec<V>>, RequestError<P::Error>> {
+    self
+      .try_fold(BTreeMap::new(), |mut map, (key, value)| async move {
+        let () = map.entry(key).or_insert_with(Vec::new).push(value);
+        Ok(map)
+      })
+      .await
+  }
+}
+
+impl<'c, P> Stream for Paginator<'c, P>
+where
+  P: Paginated + 'c,
+  P::Output: Send,
+  P::Error: Send,
+{
+  type Item = Result<P::Item, RequestError<P::Error>>;
+
+  fn poll_next(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+    let () = this.poll_pages(ctx);
+
+    loop {
+      match this.pages.front_mut() {
+        Some(page) => {
+          if let Some(item) = page.items.next() {
+            break Poll::Ready(Some(Ok(item)))
+          }
+
+          // The current page is exhausted. Unless it is the last one,
+          // move on to the next.
+          if this.pages.len() > 1 || this.input.is_some() || this.error.is_some() {
+            let _page = this.pages.pop_front();
+            // We made room for another page.
+            let () = this.poll_pages(ctx);
+          } else {
+            break Poll::Ready(None)
+          }
+        },
+        None => {
+          if let Some(err) = this.error.take() {
+            break Poll::Ready(Some(Err(err)))
+          } else if this.fetch.is_some() {
+            break Poll::Pending
+          } else {
+            break Poll::Ready(None)
+          }
+        },
+      }
+    }
+  }
+}
+
+// None of our members is structurally pinned.
+impl<'c, P> Unpin for Paginator<'c, P> where P: Paginated {}
+
+impl<'c, P> Debug for Paginator<'c, P>
+where
+  P: Paginated,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("Paginator")
+      .field("token", &self.token)
+      .field("pages", &self.pages.len())
+      .field("max_pages", &self.max_pages)
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::str::FromStr as _;
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering;
+  use std::sync::Arc;
+
+  use chrono::DateTime;
+
+  use http::Request;
+  use http::Response;
+  use http::StatusCode;
+
+  use hyper::body::Bytes;
+  use hyper::Body;
+
+  use test_log::test;
+
+  use crate::data::v2::bars;
+  use crate::data::v2::bars::ListReqInit;
+  use crate::data::v2::bars::MultiListReqInit;
+  use crate::data::v2::bars::Sort;
+  use crate::data::v2::bars::TimeFrame;
+  use crate::data::v2::prefix::MarketPrefix;
+  use crate::data::v2::trades;
+  use crate::ApiInfo;
+
+
+  /// Create a `Client` serving `count` pages of two bars each, with
+  /// the page at index `fail` (if any) reporting an error.
+  ///
+  /// The returned counter tracks the number of requests issued.
+  fn client(count: usize, fail: Option<usize>) -> (Client, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+
+    let transport = move |request: Request<Bytes>| {
+      let _count = counter.fetch_add(1, Ordering::SeqCst);
+      let query = request.uri().query().unwrap_or_default();
+      let page = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("page_token=page"))
+        .map(|page| page.parse::<usize>().unwrap())
+        .unwrap_or(0);
+
+      if Some(page) == fail {
+        return Response::builder()
+          .status(StatusCode::BAD_REQUEST)
+          .body(Body::from(r#"{"message":"invalid page token"}"#))
+          .unwrap()
+      }
+
+      let bars = (0..2)
+        .map(|idx| {
+          format!(
+            r#"{{"t":"2024-01-02T14:{:02}:00Z","o":1,"h":1,"l":1,"c":1,"v":{},"vw":1}}"#,
+            page * 2 + idx,
+            page * 2 + idx,
+          )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+      let next = if page + 1 < count {
+        format!(r#""page{}""#, page + 1)
+      } else {
+        "null".to_string()
+      };
+      let body = format!(r#"{{"bars":[{bars}],"symbol":"SPY","next_page_token":{next}}}"#);
+      Response::new(Body::from(body))
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+    (client, requests)
+  }
+
+  /// Create a request for bars, starting at the page with the given
+  /// token.
+  fn request(page_token: Option<&str>) -> bars::ListReq {
+    let start = DateTime::from_str("2024-01-02T14:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T15:00:00Z").unwrap();
+    let init = ListReqInit {
+      page_token: page_token.map(str::to_string),
+      ..Default::default()
+    };
+    init.init(
+      "SPY",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneMinute,
+      Sort::Asc,
+    )
+  }
+
+
+  /// Check that we follow page tokens until the last page.
+  #[test(tokio::test)]
+  async fn follow_pages() {
+    let (client, requests) = client(3, None);
+    let volumes = client
+      .paginate::<bars::List>(request(None))
+      .map_ok(|bar| bar.volume.to_u64().unwrap())
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+
+    assert_eq!(volumes, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+  }
+
+  /// Check that we can follow the pages of trades for a single symbol.
+  #[test(tokio::test)]
+  async fn follow_trade_pages() {
+    let transport = move |request: Request<Bytes>| {
+      let query = request.uri().query().unwrap_or_default();
+      assert_eq!(request.uri().path(), "/v2/stocks/trades");
+      assert!(query.starts_with("symbols=SPY&"), "{query}");
+
+      let page = usize::from(query.contains("page_token=page1"));
+      let next = if page == 0 { r#""page1""# } else { "null" };
+      let body = format!(
+        r#"{{"trades":{{"SPY":[{{"t":"2024-01-02T14:00:0{page}Z","p":1,"s":{page}}}]}},"next_page_token":{next}}}"#
+      );
+      Response::new(Body::from(body))
+    };
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+
+    let start = DateTime::from_str("2024-01-02T14:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T15:00:00Z").unwrap();
+    let request = trades::ListReqInit::default().init("SPY", MarketPrefix::Stocks, start, end);
+
+    let sizes = client
+      .paginate::<trades::List>(request)
+      .map_ok(|trade| trade.size.to_u64().unwrap())
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+    assert_eq!(sizes, vec![0, 1]);
+  }
+
+  /// Check that we can resume iteration from a page token.
+  #[test(tokio::test)]
+  async fn resume_from_token() {
+    let (client, _requests) = client(3, None);
+    let mut stream = client.paginate::<bars::List>(request(None));
+    assert_eq!(stream.page_token(), None);
+
+    let mut token = None;
+    while let Some(bar) = stream.try_next().await.unwrap() {
+      if bar.volume.to_u64() == Some(3) {
+        token = stream.page_token().map(str::to_string);
+        break
+      }
+    }
+    assert_eq!(token.as_deref(), Some("page1"));
+
+    let volumes = client
+      .paginate::<bars::List>(request(token.as_deref()))
+      .map_ok(|bar| bar.volume.to_u64().unwrap())
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+    assert_eq!(volumes, vec![2, 3, 4, 5]);
+  }
+
+  /// Check that an error is reported after all items preceding it and
+  /// that it ends the stream.
+  #[test(tokio::test)]
+  async fn report_error() {
+    let (client, _requests) = client(3, Some(1));
+    let mut stream = client.paginate::<bars::List>(request(None));
+
+    assert_eq!(
+      stream.try_next().await.unwrap().unwrap().volume.to_u64(),
+      Some(0)
+    );
+    assert_eq!(
+      stream.try_next().await.unwrap().unwrap().volume.to_u64(),
+      Some(1)
+    );
+
+    let err = stream.try_next().await.unwrap_err();
+    match err {
+      RequestError::Endpoint(bars::ListError::InvalidInput(..)) => (),
+      _ => panic!("received unexpected error: {err:?}"),
+    }
+    assert!(stream.try_next().await.unwrap().is_none());
+  }
+
+  /// Check that we can merge the pages of a multi-symbol request into
+  /// per-symbol lists.
+  #[test(tokio::test)]
+  async fn collect_grouped_pages() {
+    let pages = [
+      (
+        r#"{"AAPL":[{"t":"2024-01-02T14:00:00Z","o":1,"h":1,"l":1,"c":1,"v":0,"vw":1},{"t":"2024-01-02T14:01:00Z","o":1,"h":1,"l":1,"c":1,"v":1,"vw":1}]}"#,
+        r#""page1""#,
+      ),
+      (
+        r#"{"AAPL":[{"t":"2024-01-02T14:02:00Z","o":1,"h":1,"l":1,"c":1,"v":2,"vw":1}],"MSFT":[{"t":"2024-01-02T14:00:00Z","o":1,"h":1,"l":1,"c":1,"v":10,"vw":1}]}"#,
+        "null",
+      ),
+    ];
+
+    let transport = move |request: Request<Bytes>| {
+      let query = request.uri().query().unwrap_or_default();
+      assert_eq!(request.uri().path(), "/v2/stocks/bars");
+      assert!(query.contains("symbols=AAPL%2CMSFT"), "{query}");
+
+      let page = usize::from(query.contains("page_token=page1"));
+      let (bars, next) = pages[page];
+      let body = format!(r#"{{"bars":{bars},"next_page_token":{next}}}"#);
+      Response::new(Body::from(body))
+    };
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+
+    let start = DateTime::from_str("2024-01-02T14:00:00Z").unwrap();
+    let end = DateTime::from_str("2024-01-02T15:00:00Z").unwrap();
+    let request = MultiListReqInit::default().init(
+      ["AAPL", "MSFT"],
+      MarketPrefix::Stocks,
+      start,
+      end,
+      TimeFrame::OneMinute,
+      Sort::Asc,
+    );
+
+    let bars = client
+      .paginate::<bars::MultiList>(request)
+      .collect_grouped()
+      .await
+      .unwrap();
+    let volumes = bars
+      .into_iter()
+      .map(|(symbol, bars)| {
+        (
+          symbol,
+          bars
+            .into_iter()
+            .map(|bar| bar.volume.to_u64().unwrap())
+            .collect(),
+        )
+      })
+      .collect::<Vec<(_, Vec<_>)>>();
+    assert_eq!(
+      volumes,
+      vec![
+        ("AAPL".to_string(), vec![0, 1, 2]),
+        ("MSFT".to_string(), vec![10]),
+      ]
+    );
+  }
+
+  /// Check that we honor the maximum number of pages to retrieve ahead
+  /// of time.
+  #[test(tokio::test)]
+  async fn limit_pages_in_flight() {
+    for max_pages in [0, 1, 3] {
+      let (client, requests) = client(10, None);
+      let mut stream = client
+        .paginate::<bars::List>(request(None))
+        .max_pages(max_pages);
+
+      let _bar = stream.try_next().await.unwrap().unwrap();
+      assert_eq!(requests.load(Ordering::SeqCst), 1 + max_pages);
+    }
+  }
+}
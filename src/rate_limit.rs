@@ -0,0 +1,330 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::from_utf8;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use http::HeaderMap;
+use http::Method;
+use http::Request;
+
+use hyper::body::Bytes;
+
+use tokio::time::sleep;
+use tokio::time::Instant;
+
+use tracing::debug;
+
+use crate::api::HDR_RATE_LIMIT_REMAINING;
+use crate::api::HDR_RATE_LIMIT_RESET;
+
+
+/// The priority with which a request is admitted by a [`RateLimiter`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Priority {
+  /// A priority used for bulk data retrieval.
+  Low = 0,
+  /// The priority used for the bulk of requests.
+  Normal = 1,
+  /// A priority used for order submission, change, and cancellation.
+  High = 2,
+}
+
+impl Priority {
+  /// Determine the priority of the provided request.
+  pub(crate) fn of(request: &Request<Bytes>) -> Self {
+    let path = request.uri().path();
+    match *request.method() {
+      Method::POST | Method::PATCH | Method::DELETE if path.starts_with("/v2/orders") => Self::High,
+      Method::GET if path.ends_with("/bars") => Self::Low,
+      _ => Self::Normal,
+    }
+  }
+}
+
+
+/// The internal state of a [`RateLimiter`].
+#[derive(Debug)]
+struct State {
+  /// The maximum number of tokens in the bucket.
+  capacity: f64,
+  /// The number of tokens added to the bucket per second.
+  rate: f64,
+  /// The number of tokens currently available.
+  tokens: f64,
+  /// The last time we refilled the bucket.
+  refilled: Instant,
+  /// The point in time until which the server asked us to not issue
+  /// any more requests.
+  blocked_until: Option<Instant>,
+  /// The number of requests waiting for admission, per priority.
+  waiting: [usize; 3],
+}
+
+impl State {
+  /// Add tokens to the bucket based on the time passed since the last
+  /// refill.
+  fn refill(&mut self, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.refilled);
+    self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+    self.refilled = now;
+  }
+
+  /// Try to take a token on behalf of a request of the given priority,
+  /// returning the time to wait before trying again on failure.
+  fn try_take(&mut self, priority: Priority, now: Instant) -> Result<(), Duration> {
+    self.refill(now);
+
+    if let Some(until) = self.blocked_until {
+      if until > now {
+        return Err(until - now)
+      }
+      self.blocked_until = None;
+    }
+
+    // Requests of higher priority that are waiting get to go first. So
+    // we need to wait until there are enough tokens for all of them.
+    let ahead = self.waiting[priority as usize + 1..].iter().sum::<usize>() as f64;
+    let required = ahead + 1.0;
+    if ahead == 0.0 && self.tokens >= 1.0 {
+      self.tokens -= 1.0;
+      Ok(())
+    } else {
+      let missing = (required - self.tokens).max(0.0);
+      let wait = Duration::from_secs_f64(missing / self.rate);
+      Err(wait.max(Duration::from_millis(1)))
+    }
+  }
+}
+
+
+/// A guard keeping track of a request waiting for admission.
+struct Waiting<'s> {
+  state: &'s Mutex<State>,
+  priority: Priority,
+}
+
+impl<'s> Waiting<'s> {
+  fn new(state: &'s Mutex<State>, priority: Priority) -> Self {
+    state.lock().unwrap().waiting[priority as usize] += 1;
+    Self { state, priority }
+  }
+}
+
+impl Drop for Waiting<'_> {
+  fn drop(&mut self) {
+    self.state.lock().unwrap().waiting[self.priority as usize] -= 1;
+  }
+}
+
+
+/// A client side rate limiter based on a token bucket.
+///
+/// A `RateLimiter` is shared by all requests issued through a
+/// [`Client`][crate::Client] and its clones. Requests submitting,
+/// changing, or canceling orders are admitted with precedence over
+/// other requests, while retrieval of historical bars is admitted last.
+///
+/// The limiter adapts to the rate limit information reported by the
+/// server: should fewer requests remain than it assumed, it lowers its
+/// budget, and once the server reports the limit as exhausted, no more
+/// requests are admitted until the reported reset time.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+  state: Arc<Mutex<State>>,
+}
+
+impl RateLimiter {
+  /// Create a new `RateLimiter` admitting the given number of requests
+  /// per minute.
+  ///
+  /// # Panics
+  /// This constructor panics if `requests_per_minute` is zero.
+  pub fn new(requests_per_minute: u32) -> Self {
+    assert!(
+      requests_per_minute > 0,
+      "requests per minute must not be zero"
+    );
+
+    let capacity = f64::from(requests_per_minute);
+    let state = State {
+      capacity,
+      rate: capacity / 60.0,
+      tokens: capacity,
+      refilled: Instant::now(),
+      blocked_until: None,
+      waiting: [0; 3],
+    };
+
+    Self {
+      state: Arc::new(Mutex::new(state)),
+    }
+  }
+
+  /// Wait until a request of the given priority may be issued.
+  pub(crate) async fn acquire(&self, priority: Priority) {
+    let mut waiting = None;
+
+    loop {
+      let result = self
+        .state
+        .lock()
+        .unwrap()
+        .try_take(priority, Instant::now());
+      match result {
+        Ok(()) => break,
+        Err(wait) => {
+          if waiting.is_none() {
+            debug!(
+              priority = debug(priority),
+              wait = debug(&wait),
+              "rate limiting request"
+            );
+            waiting = Some(Waiting::new(&self.state, priority));
+          }
+          let () = sleep(wait).await;
+        },
+      }
+    }
+  }
+
+  /// Update the limiter's state based on the rate limit information
+  /// reported in response headers.
+  pub(crate) fn update(&self, headers: &HeaderMap) {
+    let value = |name| {
+      headers
+        .get(name)
+        .and_then(|value| from_utf8(value.as_bytes()).ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+    };
+
+    if let Some(remaining) = value(HDR_RATE_LIMIT_REMAINING) {
+      let mut state = self.state.lock().unwrap();
+      let now = Instant::now();
+      state.refill(now);
+      state.tokens = state.tokens.min(remaining.max(0) as f64);
+
+      if remaining <= 0 {
+        let reset = value(HDR_RATE_LIMIT_RESET)
+          .and_then(|reset| DateTime::from_timestamp(reset, 0))
+          .and_then(|reset| (reset - Utc::now()).to_std().ok());
+
+        if let Some(reset) = reset {
+          state.blocked_until = Some(now + reset);
+        }
+      }
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http::HeaderValue;
+
+  use test_log::test;
+
+  use tokio::spawn;
+  use tokio::sync::mpsc::unbounded_channel;
+
+
+  /// Create a rate limiter with the provided number of requests per
+  /// minute and no tokens available.
+  fn exhausted(requests_per_minute: u32) -> RateLimiter {
+    let limiter = RateLimiter::new(requests_per_minute);
+    limiter.state.lock().unwrap().tokens = 0.0;
+    limiter
+  }
+
+  /// Check that we infer request priorities as expected.
+  #[test]
+  fn request_priorities() {
+    let request = |method, uri| {
+      Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Bytes::new())
+        .unwrap()
+    };
+
+    let create = request(Method::POST, "https://example.com/v2/orders");
+    assert_eq!(Priority::of(&create), Priority::High);
+    let cancel = request(Method::DELETE, "https://example.com/v2/orders/1234");
+    assert_eq!(Priority::of(&cancel), Priority::High);
+    let list = request(Method::GET, "https://example.com/v2/orders");
+    assert_eq!(Priority::of(&list), Priority::Normal);
+    let bars = request(Method::GET, "https://example.com/v2/stocks/bars");
+    assert_eq!(Priority::of(&bars), Priority::Low);
+  }
+
+  /// Check that requests are admitted at the configured rate.
+  #[test(tokio::test(start_paused = true))]
+  async fn admission_rate() {
+    let limiter = RateLimiter::new(120);
+    let start = Instant::now();
+
+    for _ in 0..120 {
+      let () = limiter.acquire(Priority::Normal).await;
+    }
+    assert_eq!(start.elapsed(), Duration::ZERO);
+
+    let () = limiter.acquire(Priority::Normal).await;
+    let () = limiter.acquire(Priority::Normal).await;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(1100), "{elapsed:?}");
+  }
+
+  /// Make sure that requests of higher priority are admitted first.
+  #[test(tokio::test(start_paused = true))]
+  async fn priority_admission() {
+    let limiter = exhausted(60);
+    let (send, mut recv) = unbounded_channel();
+
+    for priority in [Priority::Low, Priority::Normal, Priority::High] {
+      let limiter = limiter.clone();
+      let send = send.clone();
+      let _handle = spawn(async move {
+        let () = limiter.acquire(priority).await;
+        send.send(priority).unwrap();
+      });
+      // Make sure that the task registers as waiting before we spawn
+      // the next one.
+      let () = sleep(Duration::from_millis(10)).await;
+    }
+
+    assert_eq!(recv.recv().await, Some(Priority::High));
+    assert_eq!(recv.recv().await, Some(Priority::Normal));
+    assert_eq!(recv.recv().await, Some(Priority::Low));
+    assert_eq!(limiter.state.lock().unwrap().waiting, [0; 3]);
+  }
+
+  /// Check that we honor rate limit information reported by the
+  /// server.
+  #[test(tokio::test(start_paused = true))]
+  async fn server_reported_limit() {
+    let limiter = RateLimiter::new(6000);
+    let mut headers = HeaderMap::new();
+    let _ = headers.insert(HDR_RATE_LIMIT_REMAINING, HeaderValue::from(2));
+    limiter.update(&headers);
+    assert_eq!(limiter.state.lock().unwrap().tokens, 2.0);
+
+    let reset = Utc::now().timestamp() + 3;
+    let _ = headers.insert(HDR_RATE_LIMIT_REMAINING, HeaderValue::from(0));
+    let _ = headers.insert(HDR_RATE_LIMIT_RESET, HeaderValue::from(reset));
+    limiter.update(&headers);
+
+    let start = Instant::now();
+    let () = limiter.acquire(Priority::High).await;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+    assert!(elapsed <= Duration::from_secs(3), "{elapsed:?}");
+  }
+}
@@ -0,0 +1,258 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::mem::replace;
+use std::task::Context;
+use std::task::Poll;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use futures::future::BoxFuture;
+use futures::FutureExt as _;
+use futures::Stream;
+use futures::StreamExt as _;
+
+use serde_json::Error as JsonError;
+
+use tracing::debug;
+
+use websocket_util::tungstenite::Error as WebSocketError;
+
+use crate::Error;
+
+
+/// A message as received over a websocket channel.
+pub(crate) type Message<T, E = JsonError> = Result<Result<T, E>, WebSocketError>;
+
+/// A future re-establishing a [`Connection`].
+pub(crate) type ReconnectFuture<St, Sub, X> =
+  BoxFuture<'static, Result<Established<St, Sub, X>, Error>>;
+
+
+/// Convert a message as received over a websocket channel into an item
+/// to emit, if any.
+pub(crate) fn convert<T, E>(message: Message<T, E>) -> Option<Result<T, Error>>
+where
+  E: Into<Error>,
+{
+  match message {
+    Ok(Ok(item)) => Some(Ok(item)),
+    Ok(Err(err)) => Some(Err(err.into())),
+    Err(err) => {
+      // A websocket error does not convey anything useful to clients.
+      // The connection will be re-established once the stream ends.
+      debug!(error = display(&err), "dropping websocket error");
+      None
+    },
+  }
+}
+
+
+/// A connection as re-established by a [`ReconnectFuture`].
+pub(crate) struct Established<St, Sub, X> {
+  /// The stream of messages received over the connection.
+  pub stream: St,
+  /// The subscription belonging to the stream.
+  pub subscription: Sub,
+  /// The number of connection attempts it took to establish the
+  /// connection.
+  pub attempts: usize,
+  /// Stream specific data gathered in the process.
+  pub extra: X,
+}
+
+
+/// Information about a connection that was re-established.
+pub(crate) struct Reconnected<X> {
+  /// The time at which the loss of the connection was detected.
+  pub disconnected_at: DateTime<Utc>,
+  /// The time at which the connection was re-established.
+  pub reconnected_at: DateTime<Utc>,
+  /// The number of connection attempts it took to re-establish the
+  /// connection.
+  pub attempts: usize,
+  /// Stream specific data gathered in the process.
+  pub extra: X,
+}
+
+
+/// An event as reported when polling a [`Connection`].
+pub(crate) enum Polled<T, X> {
+  /// A message was received over the connection.
+  Message(T),
+  /// The connection was lost and has since been re-established.
+  Reconnected(Reconnected<X>),
+}
+
+
+/// The state a [`Connection`] is in.
+#[allow(clippy::large_enum_variant)]
+enum State<St, Sub, X> {
+  /// We are connected to the server.
+  Connected {
+    /// The stream of messages received over the connection.
+    stream: St,
+    /// The subscription belonging to the stream.
+    subscription: Sub,
+  },
+  /// We lost the connection and are in the process of re-establishing
+  /// it.
+  Reconnecting {
+    /// The time at which the loss of the connection was detected.
+    disconnected_at: DateTime<Utc>,
+    /// The future re-establishing the connection.
+    future: ReconnectFuture<St, Sub, X>,
+  },
+  /// We failed to re-establish the connection and gave up.
+  Terminated,
+}
+
+
+/// A websocket connection that gets re-established when lost.
+///
+/// This type captures the state machine shared by all streams
+/// transparently re-establishing their connection. How to do so is up
+/// to the stream in question, which provides a [`ReconnectFuture`]
+/// whenever necessary.
+pub(crate) struct Connection<St, Sub, X> {
+  /// The state of the connection.
+  state: State<St, Sub, X>,
+}
+
+impl<St, Sub, X> Connection<St, Sub, X> {
+  /// Create a `Connection` object from an established connection.
+  pub(crate) fn new(stream: St, subscription: Sub) -> Self {
+    Self {
+      state: State::Connected {
+        stream,
+        subscription,
+      },
+    }
+  }
+
+  /// Handle the result of an attempt to re-establish the connection.
+  #[allow(clippy::result_large_err)]
+  fn reconnected(
+    &mut self,
+    result: Result<Established<St, Sub, X>, Error>,
+  ) -> Result<Reconnected<X>, Error> {
+    let state = replace(&mut self.state, State::Terminated);
+    let disconnected_at = match state {
+      State::Reconnecting {
+        disconnected_at, ..
+      } => disconnected_at,
+      _ => unreachable!(),
+    };
+
+    let Established {
+      stream,
+      subscription,
+      attempts,
+      extra,
+    } = result.map_err(|err| {
+      debug!(error = display(&err), "giving up reconnecting");
+      err
+    })?;
+    debug!(attempts, "reconnected");
+
+    self.state = State::Connected {
+      stream,
+      subscription,
+    };
+
+    let reconnected = Reconnected {
+      disconnected_at,
+      reconnected_at: Utc::now(),
+      attempts,
+      extra,
+    };
+    Ok(reconnected)
+  }
+
+  /// Wait for an ongoing attempt to re-establish the connection to
+  /// finish, if any, reporting its outcome.
+  pub(crate) async fn settle(&mut self) -> Option<Result<Reconnected<X>, Error>> {
+    if let State::Reconnecting { future, .. } = &mut self.state {
+      let result = future.await;
+      Some(self.reconnected(result))
+    } else {
+      None
+    }
+  }
+
+  /// Retrieve the stream and subscription of the current connection.
+  ///
+  /// The connection must not currently be re-established, i.e., any
+  /// attempt to do so has to be [settled][Self::settle] before.
+  #[allow(clippy::result_large_err)]
+  pub(crate) fn get_mut(&mut self) -> Result<(&mut St, &mut Sub), Error> {
+    match &mut self.state {
+      State::Connected {
+        stream,
+        subscription,
+      } => Ok((stream, subscription)),
+      State::Reconnecting { .. } => unreachable!(),
+      State::Terminated => Err(Error::Str("stream has terminated".into())),
+    }
+  }
+
+  /// Retrieve a textual representation of the connection's state.
+  pub(crate) fn state(&self) -> &'static str {
+    match self.state {
+      State::Connected { .. } => "connected",
+      State::Reconnecting { .. } => "reconnecting",
+      State::Terminated => "terminated",
+    }
+  }
+
+  /// Check whether we gave up re-establishing the connection.
+  #[inline]
+  pub(crate) fn is_terminated(&self) -> bool {
+    matches!(self.state, State::Terminated)
+  }
+
+  /// Poll the connection for the next message, re-establishing it by
+  /// means of the future created by `reconnect` once it is lost.
+  ///
+  /// Once re-establishing the connection failed for good, the error is
+  /// reported and the connection terminates.
+  pub(crate) fn poll_next<T, E, F>(
+    &mut self,
+    ctx: &mut Context<'_>,
+    mut reconnect: F,
+  ) -> Poll<Option<Result<Polled<T, X>, Error>>>
+  where
+    St: Stream<Item = Message<T, E>> + Unpin,
+    E: Into<Error>,
+    F: FnMut() -> ReconnectFuture<St, Sub, X>,
+  {
+    loop {
+      match &mut self.state {
+        State::Connected { stream, .. } => match stream.poll_next_unpin(ctx) {
+          Poll::Ready(Some(message)) => {
+            if let Some(result) = convert(message) {
+              break Poll::Ready(Some(result.map(Polled::Message)))
+            }
+          },
+          Poll::Ready(None) => {
+            debug!("connection lost; reconnecting");
+            self.state = State::Reconnecting {
+              disconnected_at: Utc::now(),
+              future: reconnect(),
+            };
+          },
+          Poll::Pending => break Poll::Pending,
+        },
+        State::Reconnecting { future, .. } => match future.poll_unpin(ctx) {
+          Poll::Ready(result) => {
+            let result = self.reconnected(result).map(Polled::Reconnected);
+            break Poll::Ready(Some(result))
+          },
+          Poll::Pending => break Poll::Pending,
+        },
+        State::Terminated => break Poll::Ready(None),
+      }
+    }
+  }
+}
@@ -0,0 +1,359 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::BuildHasher as _;
+use std::hash::Hasher as _;
+use std::str::from_utf8;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use http::header::RETRY_AFTER;
+use http::HeaderMap;
+use http::Method;
+use http::Request;
+use http::StatusCode;
+
+use hyper::body::Bytes;
+use hyper::Error as HyperError;
+
+use serde::Deserialize;
+use serde_json::from_slice as from_json;
+
+use tokio::time::sleep;
+
+use tracing::debug;
+
+use crate::api::HDR_RATE_LIMIT_RESET;
+use crate::Error;
+
+
+/// A policy describing if and how requests are retried when they fail
+/// for transient reasons.
+///
+/// Retries are only ever attempted for requests that can be safely
+/// repeated: `GET` requests as well as order submissions that carry a
+/// client order ID (which Alpaca uses to reject duplicates). Conditions
+/// eligible for a retry are the rate limit being exceeded (HTTP status
+/// 429), a temporarily unavailable service (HTTP status 502, 503, or
+/// 504), and failure to establish a connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+  /// The maximum number of retries to perform after the initial
+  /// attempt failed.
+  pub max_retries: usize,
+  /// The back off to use before the first retry.
+  pub initial_backoff: Duration,
+  /// The upper bound for the back off between two attempts.
+  pub max_backoff: Duration,
+  /// The factor by which the back off grows with each retry.
+  pub multiplier: u32,
+  /// Whether or not to randomize back off times.
+  ///
+  /// With jitter enabled, the effective back off is chosen randomly
+  /// from the upper half of the computed back off.
+  pub jitter: bool,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl RetryPolicy {
+  /// Calculate the back off to use before retry number `retry`
+  /// (starting at zero), not taking into account any server provided
+  /// hints.
+  pub(crate) fn backoff(&self, retry: usize) -> Duration {
+    let factor = u32::try_from(retry)
+      .ok()
+      .and_then(|retry| self.multiplier.checked_pow(retry))
+      .unwrap_or(u32::MAX);
+    let backoff = self
+      .initial_backoff
+      .checked_mul(factor)
+      .unwrap_or(self.max_backoff)
+      .min(self.max_backoff);
+
+    if self.jitter {
+      let half = backoff / 2;
+      let nanos = u64::try_from(half.as_nanos()).unwrap_or(u64::MAX);
+      half + Duration::from_nanos(random() % nanos.saturating_add(1))
+    } else {
+      backoff
+    }
+  }
+
+  /// Calculate the delay to use before retry number `retry`, honoring
+  /// server provided hints in the response headers, if any.
+  ///
+  /// Server provided delays are capped at `max_backoff`, so that a
+  /// bogus header cannot stall us indefinitely.
+  pub(crate) fn delay(
+    &self,
+    retry: usize,
+    status: Option<StatusCode>,
+    headers: &HeaderMap,
+  ) -> Duration {
+    server_delay(status, headers, Utc::now())
+      .map(|delay| delay.min(self.max_backoff))
+      .unwrap_or_else(|| self.backoff(retry))
+  }
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self {
+      max_retries: 3,
+      initial_backoff: Duration::from_millis(250),
+      max_backoff: Duration::from_secs(30),
+      multiplier: 2,
+      jitter: true,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// Retrieve a random number.
+///
+/// We are not interested in high quality random numbers here, so we
+/// just piggy-back on the per-instance random keys of `RandomState`.
+fn random() -> u64 {
+  RandomState::new().build_hasher().finish()
+}
+
+
+/// Check whether the provided request can safely be issued more than
+/// once.
+pub(crate) fn is_retryable_request(request: &Request<Bytes>) -> bool {
+  /// A helper for extracting the client order ID from an order
+  /// submission request.
+  #[derive(Deserialize)]
+  struct Order {
+    #[serde(rename = "client_order_id")]
+    client_order_id: Option<String>,
+  }
+
+  match *request.method() {
+    Method::GET => true,
+    // An order submission with a client order ID will be rejected by
+    // Alpaca should the same ID have been used before. So retrying it
+    // cannot result in duplicate orders.
+    Method::POST if request.uri().path() == "/v2/orders" => from_json::<Order>(request.body())
+      .map(|order| order.client_order_id.is_some())
+      .unwrap_or(false),
+    _ => false,
+  }
+}
+
+
+/// Check whether a response with the given status warrants a retry.
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+  matches!(
+    status,
+    StatusCode::TOO_MANY_REQUESTS
+      | StatusCode::BAD_GATEWAY
+      | StatusCode::SERVICE_UNAVAILABLE
+      | StatusCode::GATEWAY_TIMEOUT
+  )
+}
+
+
+/// Check whether a `hyper` reported error is of a transient nature,
+/// such as failure to establish a connection.
+pub(crate) fn is_transient(err: &HyperError) -> bool {
+  err.is_connect() || err.is_closed() || err.is_incomplete_message()
+}
+
+
+/// Determine the delay the server asked us to wait before retrying, if
+/// any.
+fn server_delay(
+  status: Option<StatusCode>,
+  headers: &HeaderMap,
+  now: DateTime<Utc>,
+) -> Option<Duration> {
+  let until = |time: DateTime<Utc>| (time - now).to_std().unwrap_or_default();
+  let value = |name| {
+    headers
+      .get(name)
+      .and_then(|value| from_utf8(value.as_bytes()).ok())
+      .map(str::trim)
+  };
+
+  if let Some(retry_after) = value(RETRY_AFTER.as_str()) {
+    // The header may contain either a number of seconds or an HTTP
+    // date.
+    if let Ok(secs) = retry_after.parse::<u64>() {
+      return Some(Duration::from_secs(secs))
+    }
+    if let Ok(time) = DateTime::parse_from_rfc2822(retry_after) {
+      return Some(until(time.with_timezone(&Utc)))
+    }
+  }
+
+  // The rate limit reset time is reported on all responses, but it is
+  // only meaningful to us if we actually hit the limit.
+  if status == Some(StatusCode::TOO_MANY_REQUESTS) {
+    let reset = value(HDR_RATE_LIMIT_RESET)?.parse::<i64>().ok()?;
+    let time = DateTime::from_timestamp(reset, 0)?;
+    return Some(until(time))
+  }
+  None
+}
+
+
+/// Repeatedly attempt to re-establish a connection by means of
+/// `connect`, backing off before each attempt as per the provided
+/// policy.
+///
+/// On success, the connection is returned along with the number of
+/// attempts it took. If all of `1 + policy.max_retries` attempts
+/// failed, the error of the last one is reported.
+pub(crate) async fn reconnect<F, R, T>(
+  policy: &RetryPolicy,
+  mut connect: F,
+) -> Result<(T, usize), Error>
+where
+  F: FnMut() -> R,
+  R: Future<Output = Result<T, Error>>,
+{
+  let mut attempt = 0;
+
+  loop {
+    let delay = policy.backoff(attempt);
+    debug!(attempt, delay = debug(&delay), "reconnecting");
+    let () = sleep(delay).await;
+    attempt += 1;
+
+    match connect().await {
+      Ok(connection) => break Ok((connection, attempt)),
+      Err(err) if attempt > policy.max_retries => break Err(err),
+      Err(err) => {
+        debug!(attempt, error = display(&err), "failed to reconnect");
+      },
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http::HeaderValue;
+
+  use test_log::test;
+
+
+  /// Create a request with the given method, path, and body.
+  fn request(method: Method, path: &str, body: &'static [u8]) -> Request<Bytes> {
+    Request::builder()
+      .method(method)
+      .uri(format!("https://example.com{path}"))
+      .body(Bytes::from_static(body))
+      .unwrap()
+  }
+
+  /// Check that back off times grow as expected and are capped.
+  #[test]
+  fn exponential_backoff() {
+    let policy = RetryPolicy {
+      initial_backoff: Duration::from_secs(1),
+      max_backoff: Duration::from_secs(5),
+      jitter: false,
+      ..Default::default()
+    };
+
+    assert_eq!(policy.backoff(0), Duration::from_secs(1));
+    assert_eq!(policy.backoff(1), Duration::from_secs(2));
+    assert_eq!(policy.backoff(2), Duration::from_secs(4));
+    assert_eq!(policy.backoff(3), Duration::from_secs(5));
+    assert_eq!(policy.backoff(1000), Duration::from_secs(5));
+  }
+
+  /// Make sure that jittered back off times stay within bounds.
+  #[test]
+  fn jittered_backoff() {
+    let policy = RetryPolicy {
+      initial_backoff: Duration::from_secs(4),
+      ..Default::default()
+    };
+
+    for _ in 0..100 {
+      let backoff = policy.backoff(0);
+      assert!(backoff >= Duration::from_secs(2), "{backoff:?}");
+      assert!(backoff <= Duration::from_secs(4), "{backoff:?}");
+    }
+  }
+
+  /// Check that we only consider safe requests for retrying.
+  #[test]
+  fn retryable_requests() {
+    let get = request(Method::GET, "/v2/account", b"");
+    assert!(is_retryable_request(&get));
+    let delete = request(Method::DELETE, "/v2/orders", b"");
+    assert!(!is_retryable_request(&delete));
+    let post = request(Method::POST, "/v2/watchlists", b"{}");
+    assert!(!is_retryable_request(&post));
+
+    let body = br#"{"symbol":"SPY","client_order_id":null}"#;
+    let create = request(Method::POST, "/v2/orders", body);
+    assert!(!is_retryable_request(&create));
+    let body = br#"{"symbol":"SPY","client_order_id":"my-id"}"#;
+    let create = request(Method::POST, "/v2/orders", body);
+    assert!(is_retryable_request(&create));
+  }
+
+  /// Check that we honor server provided retry hints.
+  #[test]
+  fn server_provided_delay() {
+    let now = DateTime::parse_from_rfc3339("2024-01-02T10:00:00Z")
+      .unwrap()
+      .with_timezone(&Utc);
+
+    let mut headers = HeaderMap::new();
+    assert_eq!(server_delay(None, &headers, now), None);
+
+    let _ = headers.insert(HDR_RATE_LIMIT_RESET, HeaderValue::from(now.timestamp() + 7));
+    assert_eq!(server_delay(Some(StatusCode::OK), &headers, now), None);
+    assert_eq!(
+      server_delay(Some(StatusCode::TOO_MANY_REQUESTS), &headers, now),
+      Some(Duration::from_secs(7))
+    );
+
+    let _ = headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+    assert_eq!(
+      server_delay(Some(StatusCode::TOO_MANY_REQUESTS), &headers, now),
+      Some(Duration::from_secs(3))
+    );
+
+    let date = HeaderValue::from_static("Tue, 02 Jan 2024 10:00:12 GMT");
+    let _ = headers.insert(RETRY_AFTER, date);
+    assert_eq!(
+      server_delay(Some(StatusCode::SERVICE_UNAVAILABLE), &headers, now),
+      Some(Duration::from_secs(12))
+    );
+  }
+
+  /// Check that server provided delays are capped at the maximum back
+  /// off.
+  #[test]
+  fn server_provided_delay_capped() {
+    let policy = RetryPolicy {
+      max_backoff: Duration::from_secs(5),
+      ..Default::default()
+    };
+
+    let mut headers = HeaderMap::new();
+    let _ = headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+    let delay = policy.delay(0, Some(StatusCode::TOO_MANY_REQUESTS), &headers);
+    assert_eq!(delay, Duration::from_secs(3));
+
+    let _ = headers.insert(RETRY_AFTER, HeaderValue::from_static("86400"));
+    let delay = policy.delay(0, Some(StatusCode::TOO_MANY_REQUESTS), &headers);
+    assert_eq!(delay, Duration::from_secs(5));
+  }
+}
@@ -0,0 +1,362 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::TimeZone as _;
+use chrono::Utc;
+
+use num_decimal::Num;
+
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use super::state::Failure;
+
+
+/// The number of shares each trade is reported to cover.
+const TRADE_SIZE: u32 = 100;
+/// The number of items reported per page if the request did not ask
+/// for a specific limit.
+const DEFAULT_LIMIT: usize = 1000;
+
+
+/// The query of a market data request, covering the parameters of all
+/// supported endpoints.
+#[derive(Debug, Deserialize)]
+pub(crate) struct DataReq {
+  /// The comma separated list of symbols to report data for.
+  #[serde(rename = "symbols")]
+  symbols: String,
+  /// The time frame of bars to report.
+  #[serde(rename = "timeframe")]
+  timeframe: Option<String>,
+  /// Only report data at or after this time.
+  #[serde(rename = "start")]
+  start: Option<DateTime<Utc>>,
+  /// Only report data at or before this time.
+  #[serde(rename = "end")]
+  end: Option<DateTime<Utc>>,
+  /// The maximum number of items to report, across all symbols.
+  #[serde(rename = "limit")]
+  limit: Option<usize>,
+  /// The order in which to report bars.
+  #[serde(rename = "sort")]
+  sort: Option<String>,
+  /// The token of the page to report.
+  #[serde(rename = "page_token")]
+  page_token: Option<String>,
+}
+
+impl DataReq {
+  /// Retrieve the requested symbols.
+  fn symbols(&self) -> impl Iterator<Item = &str> {
+    self
+      .symbols
+      .split(',')
+      .map(str::trim)
+      .filter(|symbol| !symbol.is_empty())
+  }
+
+  /// Check whether the given time falls into the requested range.
+  fn contains(&self, time: &DateTime<Utc>) -> bool {
+    self.start.map(|start| time >= &start).unwrap_or(true)
+      && self.end.map(|end| time <= &end).unwrap_or(true)
+  }
+}
+
+
+/// A trade, as recorded for each price change.
+#[derive(Debug)]
+struct Tick {
+  /// The ID of the trade.
+  id: u64,
+  /// The time at which the trade happened.
+  time: DateTime<Utc>,
+  /// The price the trade happened at.
+  price: Num,
+}
+
+
+/// Parse a time frame as used by the bars endpoint into a duration.
+fn timeframe(timeframe: &str) -> Result<Duration, Failure> {
+  let unit = timeframe.trim_start_matches(|c: char| c.is_ascii_digit());
+  let count = timeframe[..timeframe.len() - unit.len()]
+    .parse::<i64>()
+    .ok()
+    .filter(|count| *count > 0);
+
+  match (count, unit) {
+    (Some(count), "Min") => Ok(Duration::minutes(count)),
+    (Some(count), "Hour") => Ok(Duration::hours(count)),
+    (Some(1), "Day") => Ok(Duration::days(1)),
+    (Some(1), "Week") => Ok(Duration::weeks(1)),
+    _ => Err(Failure::bad_request(format!(
+      "unsupported time frame: {timeframe}"
+    ))),
+  }
+}
+
+
+/// Aggregate a non-empty set of ticks into a bar starting at `time`.
+fn bar(time: DateTime<Utc>, ticks: &[&Tick]) -> Value {
+  let prices = ticks.iter().map(|tick| &tick.price);
+  let high = prices.clone().max().unwrap();
+  let low = prices.clone().min().unwrap();
+  let sum = prices.fold(Num::from(0), |sum, price| sum + price);
+  let count = ticks.len() as u64;
+
+  json!({
+    "t": time,
+    "o": ticks[0].price,
+    "h": high,
+    "l": low,
+    "c": ticks[ticks.len() - 1].price,
+    "v": u64::from(TRADE_SIZE) * count,
+    "n": count,
+    // All trades are of the same size, so the volume weighted average
+    // price is the plain average.
+    "vw": sum / Num::from(count),
+  })
+}
+
+
+/// Aggregate ticks into bars of the given length, in chronological
+/// order.
+fn bars<'t, I>(ticks: I, length: Duration) -> Vec<Value>
+where
+  I: IntoIterator<Item = &'t Tick>,
+{
+  let length = length.num_seconds();
+  let mut buckets = BTreeMap::<i64, Vec<&Tick>>::new();
+  for tick in ticks {
+    let secs = tick.time.timestamp();
+    let () = buckets
+      .entry(secs - secs.rem_euclid(length))
+      .or_default()
+      .push(tick);
+  }
+
+  buckets
+    .into_iter()
+    .map(|(secs, ticks)| bar(Utc.timestamp_opt(secs, 0).unwrap(), &ticks))
+    .collect()
+}
+
+
+/// Render a tick as a trade in the form reported by the API.
+fn trade(tick: &Tick) -> Value {
+  json!({
+    "t": tick.time,
+    "x": "V",
+    "p": tick.price,
+    "s": TRADE_SIZE,
+    "c": ["@"],
+    "i": tick.id,
+    "z": "C",
+  })
+}
+
+
+/// Render a tick as a quote in the form reported by the API.
+///
+/// Both sides are quoted at the price of the trade.
+fn quote(tick: &Tick) -> Value {
+  json!({
+    "t": tick.time,
+    "ax": "V",
+    "ap": tick.price,
+    "as": 1,
+    "bx": "V",
+    "bp": tick.price,
+    "bs": 1,
+    "c": ["R"],
+    "z": "C",
+  })
+}
+
+
+/// Report a page of per-symbol items under the given key.
+fn page(key: &str, items: Vec<(&str, Value)>, request: &DataReq) -> Result<Value, Failure> {
+  let offset = match &request.page_token {
+    Some(token) => token
+      .parse::<usize>()
+      .map_err(|_| Failure::bad_request("invalid page token"))?,
+    None => 0,
+  };
+  let limit = request.limit.unwrap_or(DEFAULT_LIMIT);
+  let next_page_token =
+    (items.len() > offset.saturating_add(limit)).then(|| (offset + limit).to_string());
+
+  let mut map = Map::new();
+  for (symbol, item) in items.into_iter().skip(offset).take(limit) {
+    let () = map
+      .entry(symbol)
+      .or_insert_with(|| Value::Array(Vec::new()))
+      .as_array_mut()
+      .unwrap()
+      .push(item);
+  }
+
+  Ok(json!({
+    key: map,
+    "next_page_token": next_page_token,
+  }))
+}
+
+
+/// The historic market data of all assets.
+#[derive(Debug, Default)]
+pub(crate) struct Market {
+  /// The trades of each asset, keyed by symbol, in chronological
+  /// order.
+  ticks: BTreeMap<String, Vec<Tick>>,
+  /// The ID to assign to the next trade.
+  next_id: u64,
+}
+
+impl Market {
+  /// Record a trade of an asset at the given price.
+  pub(crate) fn record(&mut self, symbol: &str, price: Num) {
+    self.next_id += 1;
+    let tick = Tick {
+      id: self.next_id,
+      time: Utc::now(),
+      price,
+    };
+    let () = self.ticks.entry(symbol.to_string()).or_default().push(tick);
+  }
+
+  /// Retrieve the trades of all requested symbols.
+  ///
+  /// Symbols for which no trades were ever recorded are considered
+  /// invalid.
+  fn ticks<'s>(&'s self, request: &'s DataReq) -> Result<Vec<(&'s str, &'s [Tick])>, Failure> {
+    request
+      .symbols()
+      .map(|symbol| {
+        self
+          .ticks
+          .get(symbol)
+          .map(|ticks| (symbol, ticks.as_slice()))
+          .ok_or_else(|| Failure::bad_request(format!("invalid symbol: {symbol}")))
+      })
+      .collect()
+  }
+
+  /// Report historic data, rendering each trade in the requested range
+  /// by means of `render`.
+  fn history<F>(&self, key: &str, request: &DataReq, render: F) -> Result<Value, Failure>
+  where
+    F: Fn(&Tick) -> Value,
+  {
+    let mut ticks = self.ticks(request)?;
+    let () = ticks.sort_by_key(|(symbol, _)| *symbol);
+
+    let items = ticks
+      .into_iter()
+      .flat_map(|(symbol, ticks)| {
+        ticks
+          .iter()
+          .filter(|tick| request.contains(&tick.time))
+          .map(|tick| (symbol, render(tick)))
+          .collect::<Vec<_>>()
+      })
+      .collect();
+    page(key, items, request)
+  }
+
+  /// Report historic trades.
+  pub(crate) fn trades(&self, request: &DataReq) -> Result<Value, Failure> {
+    self.history("trades", request, trade)
+  }
+
+  /// Report historic quotes.
+  pub(crate) fn quotes(&self, request: &DataReq) -> Result<Value, Failure> {
+    self.history("quotes", request, quote)
+  }
+
+  /// Report historic bars.
+  pub(crate) fn bars(&self, request: &DataReq) -> Result<Value, Failure> {
+    let length = timeframe(request.timeframe.as_deref().unwrap_or("1Day"))?;
+    let descending = request
+      .sort
+      .as_deref()
+      .map(|sort| sort.eq_ignore_ascii_case("desc"))
+      .unwrap_or(false);
+
+    let mut ticks = self.ticks(request)?;
+    let () = ticks.sort_by_key(|(symbol, _)| *symbol);
+
+    let items = ticks
+      .into_iter()
+      .flat_map(|(symbol, ticks)| {
+        let ticks = ticks.iter().filter(|tick| request.contains(&tick.time));
+        let mut bars = bars(ticks, length);
+        if descending {
+          let () = bars.reverse();
+        }
+        bars.into_iter().map(move |bar| (symbol, bar))
+      })
+      .collect();
+    page("bars", items, request)
+  }
+
+  /// Report the latest data of all requested symbols under the given
+  /// key, as rendered by `render`.
+  fn latest<F>(&self, key: &str, request: &DataReq, render: F) -> Result<Value, Failure>
+  where
+    F: Fn(&[Tick]) -> Value,
+  {
+    let latest = self
+      .ticks(request)?
+      .into_iter()
+      .map(|(symbol, ticks)| (symbol.to_string(), render(ticks)))
+      .collect::<Map<_, _>>();
+
+    Ok(json!({ key: latest }))
+  }
+
+  /// Report the latest trades.
+  pub(crate) fn latest_trades(&self, request: &DataReq) -> Result<Value, Failure> {
+    self.latest("trades", request, |ticks| trade(ticks.last().unwrap()))
+  }
+
+  /// Report the latest quotes.
+  pub(crate) fn latest_quotes(&self, request: &DataReq) -> Result<Value, Failure> {
+    self.latest("quotes", request, |ticks| quote(ticks.last().unwrap()))
+  }
+
+  /// Report the latest minute bars.
+  pub(crate) fn latest_bars(&self, request: &DataReq) -> Result<Value, Failure> {
+    self.latest("bars", request, |ticks| {
+      bars(ticks, Duration::minutes(1)).pop().unwrap()
+    })
+  }
+
+  /// Report snapshots of the market data of all requested symbols.
+  pub(crate) fn snapshots(&self, request: &DataReq) -> Result<Value, Failure> {
+    let snapshots = self
+      .ticks(request)?
+      .into_iter()
+      .map(|(symbol, ticks)| {
+        let latest = ticks.last().unwrap();
+        let mut daily = bars(ticks, Duration::days(1));
+        let snapshot = json!({
+          "latestTrade": trade(latest),
+          "latestQuote": quote(latest),
+          "minuteBar": bars(ticks, Duration::minutes(1)).pop(),
+          "dailyBar": daily.pop(),
+          "prevDailyBar": daily.pop(),
+        });
+        (symbol.to_string(), snapshot)
+      })
+      .collect::<Map<_, _>>();
+
+    Ok(Value::Object(snapshots))
+  }
+}
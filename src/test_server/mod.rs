@@ -0,0 +1,536 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A local stand-in for the Alpaca Trading and Market Data APIs, for
+//! testing purposes.
+//!
+//! The [`TestServer`] type serves the REST endpoints for the account,
+//! the market clock, assets, orders, positions, and watchlists from
+//! in-memory state, along with order updates over a websocket stream
+//! compatible with [`OrderUpdates`][crate::api::v2::updates::OrderUpdates].
+//! It also serves historic and latest stock bars, quotes, and trades
+//! as well as snapshots, derived from the prices assets traded at. As
+//! such, it allows for exercising code using this crate end-to-end
+//! without network access or Alpaca credentials.
+//!
+//! ```rust
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! use apca::api::v2::order;
+//! use apca::api::v2::positions;
+//! use apca::test_server::TestServer;
+//! use apca::Client;
+//!
+//! let server = TestServer::start().await.unwrap();
+//! let client = Client::new(server.api_info().clone());
+//!
+//! let request = order::CreateReqInit::default().init(
+//!   "AAPL",
+//!   order::Side::Buy,
+//!   order::Amount::quantity(10),
+//! );
+//! let order = client.issue::<order::Create>(&request).await.unwrap();
+//! assert_eq!(order.status, order::Status::Filled);
+//!
+//! let positions = client.issue::<positions::List>(&()).await.unwrap();
+//! assert_eq!(positions[0].symbol, "AAPL");
+//! # })
+//! ```
+
+mod market;
+mod routes;
+mod state;
+mod stream;
+
+use std::io::Result as IoResult;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+
+use num_decimal::Num;
+
+use tokio::net::TcpListener;
+use tokio::spawn;
+use tokio::sync::broadcast::channel;
+use tokio::sync::broadcast::Sender;
+use tokio::task::JoinHandle;
+
+use tracing::debug;
+
+use url::Url;
+
+use crate::api::v2::asset::Exchange;
+use crate::api::v2::order;
+use crate::api::v2::updates::OrderUpdate;
+use crate::ApiInfo;
+
+use self::state::State;
+
+
+/// The key ID clients authenticate with.
+const KEY_ID: &str = "TESTSERVERKEY";
+/// The secret clients authenticate with.
+const SECRET: &str = "testserversecret";
+
+
+/// The state shared between a [`TestServer`] and the tasks serving
+/// clients.
+#[derive(Debug)]
+struct Shared {
+  /// The simulated account and market.
+  state: Mutex<State>,
+  /// The channel through which order updates are distributed.
+  updates: Sender<OrderUpdate>,
+  /// The key ID clients have to authenticate with.
+  key_id: String,
+  /// The secret clients have to authenticate with.
+  secret: String,
+}
+
+impl Shared {
+  /// Distribute order updates to all listening clients.
+  fn publish(&self, updates: Vec<OrderUpdate>) {
+    for update in updates {
+      // Sending only fails if nobody is listening, which is fine.
+      let _result = self.updates.send(update);
+    }
+  }
+}
+
+
+/// Accept HTTP connections and serve the REST API over them.
+async fn serve(shared: Arc<Shared>, listener: TcpListener) {
+  loop {
+    match listener.accept().await {
+      Ok((stream, _addr)) => {
+        let shared = shared.clone();
+        let service = service_fn(move |request| routes::handle(shared.clone(), request));
+        let _handle = spawn(async move {
+          let result = Http::new()
+            .http1_only(true)
+            .serve_connection(stream, service)
+            .await;
+          if let Err(err) = result {
+            debug!(error = display(&err), "failed to serve connection");
+          }
+        });
+      },
+      Err(err) => debug!(error = display(&err), "failed to accept connection"),
+    }
+  }
+}
+
+
+/// A local server mimicking the Alpaca Trading and Market Data APIs.
+///
+/// The server starts out with an active account holding USD 100,000 in
+/// cash and knows about `AAPL`, `MSFT`, and `SPY`, all of which are
+/// tradable. The market is open.
+///
+/// Each price change, including the initial price an asset is
+/// registered with, is recorded as a trade of 100 shares, which is
+/// reported by the market data endpoints. Quotes are reported with
+/// both sides at the price of the trade and bars are aggregated from
+/// trades.
+///
+/// Orders are executed as soon as they become marketable, based on the
+/// prices set via [`TestServer::set_price`]. That is, market orders are
+/// filled right away, while limit and stop orders are only filled once
+/// the price crosses the respective threshold. Orders may also be
+/// filled explicitly using [`TestServer::fill`].
+///
+/// The server is shut down once the object is dropped.
+#[derive(Debug)]
+pub struct TestServer {
+  /// The information clients use for connecting to the server.
+  api_info: ApiInfo,
+  /// The state shared with the serving tasks.
+  shared: Arc<Shared>,
+  /// The tasks serving the REST API and the order update stream.
+  tasks: [JoinHandle<()>; 2],
+}
+
+impl TestServer {
+  /// Start a new server listening on a random port on localhost.
+  pub async fn start() -> IoResult<Self> {
+    let http = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let ws = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+
+    // The URLs are constructed from socket addresses and so they are
+    // known to be valid.
+    let api_base_url = Url::parse(&format!("http://{}/", http.local_addr()?)).unwrap();
+    let api_stream_url = Url::parse(&format!("ws://{}/stream", ws.local_addr()?)).unwrap();
+    let api_info = ApiInfo {
+      api_base_url: api_base_url.clone(),
+      api_stream_url: api_stream_url.clone(),
+      data_base_url: api_base_url,
+      data_stream_base_url: api_stream_url,
+      key_id: KEY_ID.to_string(),
+      secret: SECRET.to_string(),
+      proxy: None,
+    };
+
+    let (updates, _) = channel(1024);
+    let shared = Arc::new(Shared {
+      state: Mutex::new(State::new()),
+      updates,
+      key_id: KEY_ID.to_string(),
+      secret: SECRET.to_string(),
+    });
+
+    let tasks = [
+      spawn(serve(shared.clone(), http)),
+      spawn(stream::serve(shared.clone(), ws)),
+    ];
+
+    Ok(Self {
+      api_info,
+      shared,
+      tasks,
+    })
+  }
+
+  /// Retrieve the `ApiInfo` object to use for connecting to the server.
+  #[inline]
+  pub fn api_info(&self) -> &ApiInfo {
+    &self.api_info
+  }
+
+  /// Register an asset with the given symbol, traded at the provided
+  /// exchange for the given price.
+  ///
+  /// If the asset is known already, only its price is updated.
+  pub fn add_asset(&self, symbol: &str, exchange: Exchange, price: Num) {
+    let mut state = self.shared.state.lock().unwrap();
+    let updates = state.add_asset(symbol, exchange, price);
+    let () = self.shared.publish(updates);
+  }
+
+  /// Set the price of an asset, executing all orders that become
+  /// marketable as a result.
+  pub fn set_price(&self, symbol: &str, price: Num) {
+    let mut state = self.shared.state.lock().unwrap();
+    let updates = state.set_price(symbol, price);
+    let () = self.shared.publish(updates);
+  }
+
+  /// Set the cash balance of the account.
+  pub fn set_cash(&self, cash: Num) {
+    self.shared.state.lock().unwrap().set_cash(cash)
+  }
+
+  /// Open or close the market.
+  ///
+  /// While the market is closed, no orders are executed automatically.
+  pub fn set_market_open(&self, open: bool) {
+    let mut state = self.shared.state.lock().unwrap();
+    let updates = state.set_open(open);
+    let () = self.shared.publish(updates);
+  }
+
+  /// Fill the open order with the given ID, at its limit price, if any,
+  /// or the asset's current price otherwise.
+  ///
+  /// This method returns `false` if the order does not exist or is not
+  /// open.
+  pub fn fill(&self, id: order::Id) -> bool {
+    let mut state = self.shared.state.lock().unwrap();
+    match state.fill_order(id) {
+      Ok(updates) => {
+        let () = self.shared.publish(updates);
+        true
+      },
+      Err(..) => false,
+    }
+  }
+}
+
+impl Drop for TestServer {
+  fn drop(&mut self) {
+    for task in &self.tasks {
+      let () = task.abort();
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use chrono::Duration;
+  use chrono::Utc;
+
+  use futures::StreamExt as _;
+
+  use test_log::test;
+
+  use crate::api::v2::account;
+  use crate::api::v2::order::Amount;
+  use crate::api::v2::order::Side;
+  use crate::api::v2::order::Type;
+  use crate::api::v2::orders;
+  use crate::api::v2::position;
+  use crate::api::v2::positions;
+  use crate::api::v2::updates;
+  use crate::api::v2::watchlist;
+  use crate::data::v2::bars;
+  use crate::data::v2::last_trades;
+  use crate::data::v2::prefix::MarketPrefix;
+  use crate::data::v2::snapshots;
+  use crate::data::v2::trades;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  /// Create a limit order request for the given symbol.
+  fn limit_order(symbol: &str, side: Side, quantity: i64, limit: i64) -> order::CreateReq {
+    order::CreateReqInit {
+      type_: Type::Limit,
+      limit_price: Some(Num::from(limit)),
+      ..Default::default()
+    }
+    .init(symbol, side, Amount::quantity(quantity))
+  }
+
+  /// Check that we can submit an order, have it filled, and see the
+  /// resulting position.
+  #[test(tokio::test)]
+  async fn order_fill_position() {
+    let server = TestServer::start().await.unwrap();
+    let client = Client::new(server.api_info().clone());
+
+    let request = limit_order("SPY", Side::Buy, 10, 460);
+    let order = client.issue::<order::Create>(&request).await.unwrap();
+    assert_eq!(order.status, order::Status::New);
+
+    let list = orders::ListReq::default();
+    let open = client.issue::<orders::List>(&list).await.unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].id, order.id);
+
+    let () = server.set_price("SPY", Num::from(455));
+    let order = client.issue::<order::Get>(&order.id).await.unwrap();
+    assert_eq!(order.status, order::Status::Filled);
+    assert_eq!(order.filled_quantity, Num::from(10));
+    assert_eq!(order.average_fill_price, Some(Num::from(455)));
+
+    let positions = client.issue::<positions::List>(&()).await.unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].symbol, "SPY");
+    assert_eq!(positions[0].side, position::Side::Long);
+    assert_eq!(positions[0].quantity, Num::from(10));
+    assert_eq!(positions[0].average_entry_price, Num::from(455));
+
+    let account = client.issue::<account::Get>(&()).await.unwrap();
+    assert_eq!(account.cash, Num::from(100_000 - 4550));
+    assert_eq!(account.equity, Num::from(100_000));
+
+    let symbol = "SPY".parse().unwrap();
+    let order = client.issue::<position::Delete>(&symbol).await.unwrap();
+    assert_eq!(order.side, Side::Sell);
+    assert_eq!(order.status, order::Status::Filled);
+
+    let positions = client.issue::<positions::List>(&()).await.unwrap();
+    assert!(positions.is_empty());
+  }
+
+  /// Check that order changes and cancellations are handled properly.
+  #[test(tokio::test)]
+  async fn change_cancel_order() {
+    let server = TestServer::start().await.unwrap();
+    let client = Client::new(server.api_info().clone());
+
+    let request = limit_order("AAPL", Side::Buy, 1, 100);
+    let order = client.issue::<order::Create>(&request).await.unwrap();
+
+    let change = order::ChangeReq {
+      limit_price: Some(Num::from(101)),
+      ..Default::default()
+    };
+    let changed = client
+      .issue::<order::Change>(&(order.id, change))
+      .await
+      .unwrap();
+    assert_ne!(changed.id, order.id);
+    assert_eq!(changed.limit_price, Some(Num::from(101)));
+
+    let order = client.issue::<order::Get>(&order.id).await.unwrap();
+    assert_eq!(order.status, order::Status::Replaced);
+
+    let () = client.issue::<order::Delete>(&changed.id).await.unwrap();
+    let err = client
+      .issue::<order::Delete>(&changed.id)
+      .await
+      .unwrap_err();
+    assert!(
+      matches!(
+        err,
+        RequestError::Endpoint(order::DeleteError::NotCancelable(_))
+      ),
+      "{err:?}"
+    );
+  }
+
+  /// Make sure that order submission fails for invalid input.
+  #[test(tokio::test)]
+  async fn reject_invalid_orders() {
+    let server = TestServer::start().await.unwrap();
+    let client = Client::new(server.api_info().clone());
+
+    let request = limit_order("ABCDEFG", Side::Buy, 1, 100);
+    let err = client.issue::<order::Create>(&request).await.unwrap_err();
+    assert!(
+      matches!(
+        err,
+        RequestError::Endpoint(order::CreateError::InvalidInput(_))
+      ),
+      "{err:?}"
+    );
+
+    let request = limit_order("AAPL", Side::Buy, 1000, 190);
+    let err = client.issue::<order::Create>(&request).await.unwrap_err();
+    assert!(
+      matches!(
+        err,
+        RequestError::Endpoint(order::CreateError::NotPermitted(_))
+      ),
+      "{err:?}"
+    );
+
+    let mut api_info = server.api_info().clone();
+    api_info.secret = "invalid".to_string();
+    let client = Client::new(api_info);
+    let err = client.issue::<account::Get>(&()).await.unwrap_err();
+    assert!(
+      matches!(
+        err,
+        RequestError::Endpoint(account::GetError::NotPermitted(_))
+      ),
+      "{err:?}"
+    );
+  }
+
+  /// Check that we can manage watchlists.
+  #[test(tokio::test)]
+  async fn watchlist_management() {
+    let server = TestServer::start().await.unwrap();
+    let client = Client::new(server.api_info().clone());
+
+    let request = watchlist::CreateReqInit {
+      symbols: vec!["AAPL".to_string(), "SPY".to_string()],
+      ..Default::default()
+    }
+    .init("tech");
+    let created = client.issue::<watchlist::Create>(&request).await.unwrap();
+    assert_eq!(created.assets.len(), 2);
+
+    let watchlist = client.issue::<watchlist::Get>(&created.id).await.unwrap();
+    assert_eq!(watchlist, created);
+
+    let () = client
+      .issue::<watchlist::Delete>(&created.id)
+      .await
+      .unwrap();
+    let result = client.issue::<watchlist::Get>(&created.id).await;
+    assert!(result.is_err());
+  }
+
+  /// Check that market data reflect the prices assets traded at.
+  #[test(tokio::test)]
+  async fn market_data() {
+    let server = TestServer::start().await.unwrap();
+    let client = Client::new(server.api_info().clone());
+    let start = Utc::now() - Duration::hours(1);
+    let end = Utc::now() + Duration::hours(1);
+
+    let () = server.set_price("SPY", Num::from(471));
+    let () = server.set_price("SPY", Num::from(469));
+
+    let request = trades::ListReqInit::default().init("SPY", MarketPrefix::Stocks, start, end);
+    let trades = client.issue::<trades::List>(&request).await.unwrap();
+    assert_eq!(trades.symbol, "SPY");
+    let prices = trades
+      .trades
+      .iter()
+      .map(|trade| trade.price.clone())
+      .collect::<Vec<_>>();
+    assert_eq!(prices, vec![Num::from(470), Num::from(471), Num::from(469)]);
+
+    let request = trades::MultiListReqInit {
+      limit: Some(2),
+      ..Default::default()
+    }
+    .init(["SPY", "AAPL"], MarketPrefix::Stocks, start, end);
+    let trades = client
+      .paginate::<trades::MultiList>(request)
+      .collect_grouped()
+      .await
+      .unwrap();
+    assert_eq!(trades["AAPL"].len(), 1);
+    assert_eq!(trades["SPY"].len(), 3);
+
+    let request = bars::ListReqInit::default().init(
+      "SPY",
+      MarketPrefix::Stocks,
+      start,
+      end,
+      bars::TimeFrame::OneDay,
+      bars::Sort::Asc,
+    );
+    let bars = client.issue::<bars::List>(&request).await.unwrap().bars;
+    assert_eq!(bars.len(), 1);
+    assert_eq!(bars[0].open, Num::from(470));
+    assert_eq!(bars[0].high, Num::from(471));
+    assert_eq!(bars[0].low, Num::from(469));
+    assert_eq!(bars[0].close, Num::from(469));
+    assert_eq!(bars[0].trade_count, Some(3));
+
+    let request = last_trades::GetReqInit::default().init(["SPY"]);
+    let trades = client.issue::<last_trades::Get>(&request).await.unwrap();
+    assert_eq!(trades["SPY"].price, Num::from(469));
+
+    let request = snapshots::GetReqInit::default().init(["AAPL", "SPY"]);
+    let snapshots = client.issue::<snapshots::Get>(&request).await.unwrap();
+    let snapshot = &snapshots["SPY"];
+    assert_eq!(
+      snapshot.latest_quote.as_ref().unwrap().bid_price,
+      Num::from(469)
+    );
+    assert_eq!(snapshot.daily_bar.as_ref().unwrap().open, Num::from(470));
+    assert_eq!(snapshot.prev_daily_bar, None);
+
+    let request = snapshots::GetReqInit::default().init(["ABC123"]);
+    let err = client.issue::<snapshots::Get>(&request).await.unwrap_err();
+    assert!(
+      matches!(
+        err,
+        RequestError::Endpoint(snapshots::GetError::InvalidInput(_))
+      ),
+      "{err:?}"
+    );
+  }
+
+  /// Check that order updates are streamed to subscribed clients.
+  #[test(tokio::test)]
+  async fn stream_order_updates() {
+    let server = TestServer::start().await.unwrap();
+    let client = Client::new(server.api_info().clone());
+    let (mut stream, _subscription) = client.subscribe::<updates::OrderUpdates>().await.unwrap();
+
+    let request = limit_order("MSFT", Side::Buy, 2, 360);
+    let order = client.issue::<order::Create>(&request).await.unwrap();
+    assert!(server.fill(order.id));
+    assert!(!server.fill(order.id));
+
+    let update = stream.next().await.unwrap().unwrap().unwrap();
+    assert_eq!(update.event, updates::OrderStatus::New);
+    assert_eq!(update.order.id, order.id);
+
+    let update = stream.next().await.unwrap().unwrap().unwrap();
+    assert_eq!(update.event, updates::OrderStatus::Filled);
+    assert_eq!(update.order.average_fill_price, Some(Num::from(360)));
+  }
+}
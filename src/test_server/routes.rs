@@ -0,0 +1,206 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use http::header::CONTENT_TYPE;
+use http::request::Parts;
+use http::Method;
+use http::Request;
+use http::Response;
+use http::StatusCode;
+
+use hyper::body::to_bytes;
+use hyper::Body;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::from_slice as from_json;
+use serde_json::json;
+use serde_json::to_vec as to_json;
+use serde_urlencoded::from_str as from_query;
+
+use uuid::Uuid;
+
+use crate::api::v2::assets;
+use crate::api::v2::order;
+use crate::api::v2::orders;
+use crate::api::HDR_KEY_ID;
+use crate::api::HDR_REQUEST_ID;
+use crate::api::HDR_SECRET;
+
+use super::state::Failure;
+use super::Shared;
+
+
+/// The body of a request creating or updating a watchlist.
+#[derive(Deserialize)]
+struct WatchlistReq {
+  #[serde(rename = "name")]
+  name: String,
+  #[serde(rename = "symbols", default)]
+  symbols: Vec<String>,
+}
+
+/// The query of a request retrieving an order by its client order ID.
+#[derive(Deserialize)]
+struct ClientOrderId {
+  #[serde(rename = "client_order_id")]
+  client_order_id: String,
+}
+
+
+/// Create a response with the given status and JSON body.
+fn respond(status: StatusCode, body: Vec<u8>) -> Response<Body> {
+  let mut response = Response::new(Body::from(body));
+  *response.status_mut() = status;
+  let headers = response.headers_mut();
+  let _prev = headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+  let _prev = headers.insert(
+    HDR_REQUEST_ID,
+    Uuid::new_v4().as_simple().to_string().parse().unwrap(),
+  );
+  response
+}
+
+/// Create a successful response with the given object as JSON body.
+fn ok<T>(value: &T) -> Result<Response<Body>, Failure>
+where
+  T: Serialize,
+{
+  // Serialization of our own types is not expected to fail.
+  Ok(respond(StatusCode::OK, to_json(value).unwrap()))
+}
+
+/// Create a successful response without a body.
+fn no_content() -> Result<Response<Body>, Failure> {
+  Ok(respond(StatusCode::NO_CONTENT, Vec::new()))
+}
+
+/// Create a response representing the provided failure.
+fn fail(failure: Failure) -> Response<Body> {
+  let body = json!({
+    "code": u32::from(failure.status.as_u16()) * 100_000,
+    "message": failure.message,
+  });
+  respond(failure.status, to_json(&body).unwrap())
+}
+
+/// Parse a JSON request body.
+fn body<T>(body: &[u8]) -> Result<T, Failure>
+where
+  T: DeserializeOwned,
+{
+  from_json(body).map_err(|err| Failure::unprocessable(format!("invalid body: {err}")))
+}
+
+/// Parse a request's query.
+fn query<T>(parts: &Parts) -> Result<T, Failure>
+where
+  T: DeserializeOwned,
+{
+  from_query(parts.uri.query().unwrap_or_default())
+    .map_err(|err| Failure::unprocessable(format!("invalid query: {err}")))
+}
+
+/// Check that the request carries valid credentials.
+fn authorize(shared: &Shared, parts: &Parts) -> Result<(), Failure> {
+  let key_id = parts.headers.get(HDR_KEY_ID);
+  let secret = parts.headers.get(HDR_SECRET);
+  if key_id.map(|key_id| key_id == shared.key_id.as_str()) == Some(true)
+    && secret.map(|secret| secret == shared.secret.as_str()) == Some(true)
+  {
+    Ok(())
+  } else {
+    Err(Failure::forbidden("forbidden"))
+  }
+}
+
+
+/// Serve a request by dispatching it to the responsible handler.
+fn route(shared: &Shared, parts: &Parts, data: &[u8]) -> Result<Response<Body>, Failure> {
+  let () = authorize(shared, parts)?;
+
+  let path = parts.uri.path().trim_start_matches('/');
+  let segments = path.split('/').collect::<Vec<_>>();
+  let mut state = shared.state.lock().unwrap();
+
+  match (&parts.method, segments.as_slice()) {
+    (&Method::GET, ["v2", "account"]) => ok(&state.account()),
+    (&Method::GET, ["v2", "clock"]) => ok(&state.clock()),
+    (&Method::GET, ["v2", "assets"]) => ok(&state.assets(&query::<assets::ListReq>(parts)?)),
+    (&Method::GET, ["v2", "assets", symbol]) => ok(&state.asset(symbol)?),
+    (&Method::GET, ["v2", "orders"]) => ok(&state.orders(&query::<orders::ListReq>(parts)?)),
+    (&Method::POST, ["v2", "orders"]) => {
+      let (order, updates) = state.create_order(body::<order::CreateReq>(data)?)?;
+      let () = shared.publish(updates);
+      ok(&order)
+    },
+    (&Method::GET, ["v2", "orders:by_client_order_id"]) => {
+      let ClientOrderId { client_order_id } = query(parts)?;
+      ok(&state.order_by_client_id(&client_order_id)?)
+    },
+    (&Method::GET, ["v2", "orders", id]) => ok(&state.order(id)?),
+    (&Method::PATCH, ["v2", "orders", id]) => {
+      let (order, updates) = state.change_order(id, body::<order::ChangeReq>(data)?)?;
+      let () = shared.publish(updates);
+      ok(&order)
+    },
+    (&Method::DELETE, ["v2", "orders", id]) => {
+      let updates = state.cancel_order(id)?;
+      let () = shared.publish(updates);
+      no_content()
+    },
+    (&Method::GET, ["v2", "positions"]) => ok(&state.positions()),
+    (&Method::GET, ["v2", "positions", symbol]) => ok(&state.position(symbol)?),
+    (&Method::DELETE, ["v2", "positions", symbol]) => {
+      let (order, updates) = state.close_position(symbol)?;
+      let () = shared.publish(updates);
+      ok(&order)
+    },
+    (&Method::GET, ["v2", "watchlists"]) => ok(&state.watchlists()),
+    (&Method::POST, ["v2", "watchlists"]) => {
+      let WatchlistReq { name, symbols } = body(data)?;
+      ok(&state.create_watchlist(name, symbols)?)
+    },
+    (&Method::GET, ["v2", "watchlists", id]) => ok(&state.watchlist(id)?),
+    (&Method::PUT, ["v2", "watchlists", id]) => {
+      let WatchlistReq { name, symbols } = body(data)?;
+      ok(&state.update_watchlist(id, name, symbols)?)
+    },
+    (&Method::DELETE, ["v2", "watchlists", id]) => {
+      let () = state.delete_watchlist(id)?;
+      no_content()
+    },
+    (&Method::GET, ["v2", "stocks", "bars"]) => ok(&state.market().bars(&query(parts)?)?),
+    (&Method::GET, ["v2", "stocks", "bars", "latest"]) => {
+      ok(&state.market().latest_bars(&query(parts)?)?)
+    },
+    (&Method::GET, ["v2", "stocks", "quotes"]) => ok(&state.market().quotes(&query(parts)?)?),
+    (&Method::GET, ["v2", "stocks", "quotes", "latest"]) => {
+      ok(&state.market().latest_quotes(&query(parts)?)?)
+    },
+    (&Method::GET, ["v2", "stocks", "trades"]) => ok(&state.market().trades(&query(parts)?)?),
+    (&Method::GET, ["v2", "stocks", "trades", "latest"]) => {
+      ok(&state.market().latest_trades(&query(parts)?)?)
+    },
+    (&Method::GET, ["v2", "stocks", "snapshots"]) => ok(&state.market().snapshots(&query(parts)?)?),
+    _ => Err(Failure::not_found("endpoint not found")),
+  }
+}
+
+
+/// Handle an HTTP request.
+pub(super) async fn handle(
+  shared: Arc<Shared>,
+  request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+  let (parts, body) = request.into_parts();
+  let response = match to_bytes(body).await {
+    Ok(data) => route(&shared, &parts, &data).unwrap_or_else(fail),
+    Err(err) => fail(Failure::unprocessable(err.to_string())),
+  };
+  Ok(response)
+}
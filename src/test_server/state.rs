@@ -0,0 +1,832 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::str::FromStr as _;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use http::StatusCode;
+
+use num_decimal::Num;
+
+use serde_json::json;
+use serde_json::Value;
+
+use uuid::Uuid;
+
+use crate::api::v2::account;
+use crate::api::v2::account::Account;
+use crate::api::v2::asset;
+use crate::api::v2::asset::Asset;
+use crate::api::v2::asset::Exchange;
+use crate::api::v2::assets;
+use crate::api::v2::clock::Clock;
+use crate::api::v2::order;
+use crate::api::v2::order::Amount;
+use crate::api::v2::order::Order;
+use crate::api::v2::order::Side;
+use crate::api::v2::order::Type;
+use crate::api::v2::orders;
+use crate::api::v2::position;
+use crate::api::v2::position::Position;
+use crate::api::v2::updates::OrderStatus;
+use crate::api::v2::updates::OrderUpdate;
+
+use super::market::Market;
+
+
+/// The amount of cash an account starts out with.
+const INITIAL_CASH: u32 = 100_000;
+
+
+/// A failure to serve a request, along with the HTTP status to report.
+#[derive(Debug)]
+pub(crate) struct Failure {
+  pub(crate) status: StatusCode,
+  pub(crate) message: String,
+}
+
+impl Failure {
+  fn new(status: StatusCode, message: impl Into<String>) -> Self {
+    Self {
+      status,
+      message: message.into(),
+    }
+  }
+
+  /// Create a failure indicating that something was not found.
+  pub(crate) fn not_found(message: impl Into<String>) -> Self {
+    Self::new(StatusCode::NOT_FOUND, message)
+  }
+
+  /// Create a failure indicating a malformed request.
+  pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+    Self::new(StatusCode::BAD_REQUEST, message)
+  }
+
+  /// Create a failure indicating that the request is not permitted.
+  pub(crate) fn forbidden(message: impl Into<String>) -> Self {
+    Self::new(StatusCode::FORBIDDEN, message)
+  }
+
+  /// Create a failure indicating invalid input.
+  pub(crate) fn unprocessable(message: impl Into<String>) -> Self {
+    Self::new(StatusCode::UNPROCESSABLE_ENTITY, message)
+  }
+}
+
+
+/// A position held in an asset.
+#[derive(Debug)]
+struct Holding {
+  /// The number of shares held; negative for short positions.
+  quantity: Num,
+  /// The average price at which the position was entered.
+  average_entry_price: Num,
+}
+
+
+/// A watchlist as maintained by the server.
+#[derive(Debug)]
+struct Watchlist {
+  id: Uuid,
+  name: String,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+  symbols: Vec<String>,
+}
+
+
+/// The state of the simulated account and market.
+#[derive(Debug)]
+pub(crate) struct State {
+  /// The account, with derived values being calculated on demand.
+  account: Account,
+  /// Whether or not the market is open.
+  open: bool,
+  /// All assets known.
+  assets: Vec<Asset>,
+  /// The current price for each asset, keyed by symbol.
+  prices: BTreeMap<String, Num>,
+  /// All orders ever submitted, in order of submission.
+  orders: Vec<Order>,
+  /// The positions held, keyed by symbol.
+  holdings: BTreeMap<String, Holding>,
+  /// All watchlists.
+  watchlists: Vec<Watchlist>,
+  /// The historic market data, with a trade recorded for each price
+  /// change.
+  market: Market,
+}
+
+impl State {
+  /// Create the initial state, with an account holding cash only and a
+  /// small set of tradable assets.
+  pub(crate) fn new() -> Self {
+    let now = Utc::now();
+    let account = Account {
+      id: account::Id(Uuid::new_v4()),
+      status: account::Status::Active,
+      currency: "USD".to_string(),
+      cash: Num::from(INITIAL_CASH),
+      day_trader: false,
+      trading_suspended: false,
+      trading_blocked: false,
+      transfers_blocked: false,
+      account_blocked: false,
+      created_at: now,
+      shorting_enabled: true,
+      market_value_long: Num::from(0),
+      market_value_short: Num::from(0),
+      equity: Num::from(INITIAL_CASH),
+      last_equity: Num::from(INITIAL_CASH),
+      multiplier: Num::from(1),
+      buying_power: Num::from(INITIAL_CASH),
+      initial_margin: Num::from(0),
+      maintenance_margin: Num::from(0),
+      daytrade_count: 0,
+      _non_exhaustive: (),
+    };
+
+    let mut state = Self {
+      account,
+      open: true,
+      assets: Vec::new(),
+      prices: BTreeMap::new(),
+      orders: Vec::new(),
+      holdings: BTreeMap::new(),
+      watchlists: Vec::new(),
+      market: Market::default(),
+    };
+    let _updates = state.add_asset("AAPL", Exchange::Nasdaq, Num::from(190));
+    let _updates = state.add_asset("MSFT", Exchange::Nasdaq, Num::from(370));
+    let _updates = state.add_asset("SPY", Exchange::Arca, Num::from(470));
+    state
+  }
+
+  /// Register an asset, trading at the given price, executing all
+  /// orders that become marketable as a result.
+  pub(crate) fn add_asset(
+    &mut self,
+    symbol: &str,
+    exchange: Exchange,
+    price: Num,
+  ) -> Vec<OrderUpdate> {
+    if self.find_asset(symbol).is_none() {
+      self.assets.push(Asset {
+        id: asset::Id(Uuid::new_v4()),
+        class: asset::Class::UsEquity,
+        exchange,
+        symbol: symbol.to_string(),
+        status: asset::Status::Active,
+        tradable: true,
+        marginable: true,
+        shortable: true,
+        easy_to_borrow: true,
+        fractionable: true,
+        _non_exhaustive: (),
+      });
+    }
+    self.set_price(symbol, price)
+  }
+
+  /// Find an asset by its symbol or by any textual representation of
+  /// an [`asset::Symbol`].
+  fn find_asset(&self, symbol: &str) -> Option<&Asset> {
+    match asset::Symbol::from_str(symbol).ok()? {
+      asset::Symbol::Sym(sym) => self.assets.iter().find(|asset| asset.symbol == sym),
+      asset::Symbol::SymExchg(sym, exchg) | asset::Symbol::SymExchgCls(sym, exchg, _) => self
+        .assets
+        .iter()
+        .find(|asset| asset.symbol == sym && asset.exchange == exchg),
+      asset::Symbol::Id(id) => self.assets.iter().find(|asset| asset.id == id),
+    }
+  }
+
+  /// Retrieve the account, with all derived values being up-to-date.
+  pub(crate) fn account(&self) -> Account {
+    let (long, short) =
+      self
+        .positions()
+        .into_iter()
+        .fold((Num::from(0), Num::from(0)), |(long, short), position| {
+          let value = position.market_value.unwrap_or_default();
+          match position.side {
+            position::Side::Long => (long + value, short),
+            position::Side::Short => (long, short - value),
+          }
+        });
+
+    let mut account = self.account.clone();
+    account.equity = &account.cash + &long + &short;
+    account.buying_power = account.cash.clone();
+    account.market_value_long = long;
+    account.market_value_short = short;
+    account
+  }
+
+  /// Retrieve the market clock.
+  pub(crate) fn clock(&self) -> Clock {
+    let now = Utc::now();
+    let (next_open, next_close) = if self.open {
+      (now + Duration::hours(18), now + Duration::hours(6))
+    } else {
+      (now + Duration::hours(12), now + Duration::hours(18))
+    };
+
+    Clock {
+      open: self.open,
+      current: now,
+      next_open,
+      next_close,
+      _non_exhaustive: (),
+    }
+  }
+
+  /// Open or close the market.
+  pub(crate) fn set_open(&mut self, open: bool) -> Vec<OrderUpdate> {
+    self.open = open;
+    self.execute()
+  }
+
+  /// Set the cash balance of the account.
+  pub(crate) fn set_cash(&mut self, cash: Num) {
+    self.account.cash = cash;
+  }
+
+  /// Set the price of an asset, executing all orders that become
+  /// marketable as a result.
+  pub(crate) fn set_price(&mut self, symbol: &str, price: Num) -> Vec<OrderUpdate> {
+    let () = self.market.record(symbol, price.clone());
+    let _prev = self.prices.insert(symbol.to_string(), price);
+    self.execute()
+  }
+
+  /// Retrieve the historic market data.
+  pub(crate) fn market(&self) -> &Market {
+    &self.market
+  }
+
+  /// List assets matching the given request.
+  pub(crate) fn assets(&self, request: &assets::ListReq) -> Vec<Asset> {
+    self
+      .assets
+      .iter()
+      .filter(|asset| asset.status == request.status && asset.class == request.class)
+      .cloned()
+      .collect()
+  }
+
+  /// Retrieve an asset.
+  pub(crate) fn asset(&self, symbol: &str) -> Result<Asset, Failure> {
+    self
+      .find_asset(symbol)
+      .cloned()
+      .ok_or_else(|| Failure::not_found("asset not found"))
+  }
+
+  /// List orders matching the given request, most recent first.
+  pub(crate) fn orders(&self, request: &orders::ListReq) -> Vec<Order> {
+    let orders = self
+      .orders
+      .iter()
+      .rev()
+      .filter(|order| match request.status {
+        orders::Status::Open => !order.status.is_terminal(),
+        orders::Status::Closed => order.status.is_terminal(),
+        orders::Status::All => true,
+      })
+      .filter(|order| request.symbols.is_empty() || request.symbols.contains(&order.symbol))
+      .filter(|order| match (request.after, order.submitted_at) {
+        (Some(after), Some(submitted_at)) => submitted_at > after,
+        _ => true,
+      })
+      .filter(|order| match (request.until, order.submitted_at) {
+        (Some(until), Some(submitted_at)) => submitted_at < until,
+        _ => true,
+      })
+      .cloned();
+
+    orders.take(request.limit.unwrap_or(50)).collect()
+  }
+
+  /// Retrieve an order by its ID.
+  pub(crate) fn order(&self, id: &str) -> Result<Order, Failure> {
+    let id = Uuid::parse_str(id).map_err(|_| Failure::not_found("order not found"))?;
+    self
+      .orders
+      .iter()
+      .find(|order| order.id.0 == id)
+      .cloned()
+      .ok_or_else(|| Failure::not_found("order not found"))
+  }
+
+  /// Retrieve an order by its client order ID.
+  pub(crate) fn order_by_client_id(&self, client_order_id: &str) -> Result<Order, Failure> {
+    self
+      .orders
+      .iter()
+      .find(|order| order.client_order_id == client_order_id)
+      .cloned()
+      .ok_or_else(|| Failure::not_found("order not found"))
+  }
+
+  /// Submit an order.
+  pub(crate) fn create_order(
+    &mut self,
+    request: order::CreateReq,
+  ) -> Result<(Order, Vec<OrderUpdate>), Failure> {
+    let asset = self
+      .find_asset(&request.symbol.to_string())
+      .cloned()
+      .ok_or_else(|| Failure::unprocessable("asset not found"))?;
+
+    if request.class != order::Class::Simple {
+      return Err(Failure::unprocessable("order class is not supported"))
+    }
+    match request.type_ {
+      Type::Limit if request.limit_price.is_none() => {
+        return Err(Failure::unprocessable("limit orders require a limit price"))
+      },
+      Type::Stop if request.stop_price.is_none() => {
+        return Err(Failure::unprocessable("stop orders require a stop price"))
+      },
+      Type::StopLimit if request.limit_price.is_none() || request.stop_price.is_none() => {
+        return Err(Failure::unprocessable(
+          "stop limit orders require a stop and limit price",
+        ))
+      },
+      _ => (),
+    }
+
+    let client_order_id = request
+      .client_order_id
+      .unwrap_or_else(|| Uuid::new_v4().to_string());
+    if self
+      .orders
+      .iter()
+      .any(|order| order.client_order_id == client_order_id)
+    {
+      return Err(Failure::unprocessable("client_order_id must be unique"))
+    }
+
+    if request.side == Side::Buy {
+      let price = request
+        .limit_price
+        .as_ref()
+        .or_else(|| self.prices.get(&asset.symbol));
+      let cost = match (&request.amount, price) {
+        (Amount::Quantity { quantity }, Some(price)) => Some(quantity * price),
+        (Amount::Notional { notional }, _) => Some(notional.clone()),
+        (Amount::Quantity { .. }, None) => None,
+      };
+      if cost.map(|cost| cost > self.account.cash).unwrap_or(false) {
+        return Err(Failure::forbidden("insufficient buying power"))
+      }
+    }
+
+    let now = Utc::now();
+    let order = Order {
+      id: order::Id(Uuid::new_v4()),
+      client_order_id,
+      status: order::Status::New,
+      created_at: now,
+      updated_at: Some(now),
+      submitted_at: Some(now),
+      filled_at: None,
+      expired_at: None,
+      canceled_at: None,
+      asset_class: asset.class,
+      asset_id: asset.id,
+      symbol: asset.symbol,
+      amount: request.amount,
+      filled_quantity: Num::from(0),
+      type_: request.type_,
+      class: request.class,
+      side: request.side,
+      time_in_force: request.time_in_force,
+      limit_price: request.limit_price,
+      stop_price: request.stop_price,
+      trail_price: request.trail_price,
+      trail_percent: request.trail_percent,
+      average_fill_price: None,
+      extended_hours: request.extended_hours,
+      legs: Vec::new(),
+      position_intent: request.position_intent,
+      ratio_quantity: None,
+      _non_exhaustive: (),
+    };
+    let id = order.id;
+    self.orders.push(order.clone());
+
+    let mut updates = vec![OrderUpdate {
+      event: OrderStatus::New,
+      order,
+    }];
+    updates.extend(self.execute());
+
+    let order = self.orders.iter().find(|order| order.id == id).unwrap();
+    Ok((order.clone(), updates))
+  }
+
+  /// Change an open order, replacing it with a new one.
+  pub(crate) fn change_order(
+    &mut self,
+    id: &str,
+    request: order::ChangeReq,
+  ) -> Result<(Order, Vec<OrderUpdate>), Failure> {
+    let old = self.order(id)?;
+    if old.status.is_terminal() {
+      return Err(Failure::unprocessable("order is not open"))
+    }
+
+    let now = Utc::now();
+    let mut new = old.clone();
+    new.id = order::Id(Uuid::new_v4());
+    new.client_order_id = request
+      .client_order_id
+      .unwrap_or_else(|| Uuid::new_v4().to_string());
+    new.created_at = now;
+    new.updated_at = Some(now);
+    new.submitted_at = Some(now);
+    if let Some(quantity) = request.quantity {
+      new.amount = Amount::quantity(quantity);
+    }
+    if let Some(time_in_force) = request.time_in_force {
+      new.time_in_force = time_in_force;
+    }
+    if let Some(limit_price) = request.limit_price {
+      new.limit_price = Some(limit_price);
+    }
+    if let Some(stop_price) = request.stop_price {
+      new.stop_price = Some(stop_price);
+    }
+    if let Some(trail) = request.trail {
+      if new.trail_percent.is_some() {
+        new.trail_percent = Some(trail);
+      } else {
+        new.trail_price = Some(trail);
+      }
+    }
+
+    let old = self
+      .orders
+      .iter_mut()
+      .find(|order| order.id == old.id)
+      .unwrap();
+    old.status = order::Status::Replaced;
+    old.updated_at = Some(now);
+    let old = old.clone();
+
+    let id = new.id;
+    self.orders.push(new.clone());
+
+    let mut updates = vec![
+      OrderUpdate {
+        event: OrderStatus::Replaced,
+        order: old,
+      },
+      OrderUpdate {
+        event: OrderStatus::New,
+        order: new,
+      },
+    ];
+    updates.extend(self.execute());
+
+    let order = self.orders.iter().find(|order| order.id == id).unwrap();
+    Ok((order.clone(), updates))
+  }
+
+  /// Cancel an open order.
+  pub(crate) fn cancel_order(&mut self, id: &str) -> Result<Vec<OrderUpdate>, Failure> {
+    let id = self.order(id)?.id;
+    let order = self.orders.iter_mut().find(|order| order.id == id).unwrap();
+    if order.status.is_terminal() {
+      return Err(Failure::unprocessable("order is not cancelable"))
+    }
+
+    let now = Utc::now();
+    order.status = order::Status::Canceled;
+    order.canceled_at = Some(now);
+    order.updated_at = Some(now);
+
+    Ok(vec![OrderUpdate {
+      event: OrderStatus::Canceled,
+      order: order.clone(),
+    }])
+  }
+
+  /// Fill an open order at its limit price, if any, or the current
+  /// price of the asset otherwise, irrespective of market conditions.
+  pub(crate) fn fill_order(&mut self, id: order::Id) -> Result<Vec<OrderUpdate>, Failure> {
+    let index = self
+      .orders
+      .iter()
+      .position(|order| order.id == id)
+      .ok_or_else(|| Failure::not_found("order not found"))?;
+    let order = &self.orders[index];
+    if order.status.is_terminal() {
+      return Err(Failure::unprocessable("order is not open"))
+    }
+
+    let price = order
+      .limit_price
+      .clone()
+      .or_else(|| self.prices.get(&order.symbol).cloned())
+      .ok_or_else(|| Failure::unprocessable("no price available for asset"))?;
+
+    Ok(vec![self.fill(index, price)])
+  }
+
+  /// Execute all open orders that are marketable at current prices.
+  fn execute(&mut self) -> Vec<OrderUpdate> {
+    if !self.open {
+      return Vec::new()
+    }
+
+    let mut updates = Vec::new();
+    for index in 0..self.orders.len() {
+      let order = &self.orders[index];
+      if order.status.is_terminal() {
+        continue
+      }
+
+      let price = match self.prices.get(&order.symbol) {
+        Some(price) => price,
+        None => continue,
+      };
+      let buy = order.side == Side::Buy;
+      let limit_ok = |limit: &Option<Num>| match limit {
+        Some(limit) if buy => price <= limit,
+        Some(limit) => price >= limit,
+        None => false,
+      };
+      let stop_ok = |stop: &Option<Num>| match stop {
+        Some(stop) if buy => price >= stop,
+        Some(stop) => price <= stop,
+        None => false,
+      };
+
+      let marketable = match order.type_ {
+        Type::Market => true,
+        Type::Limit => limit_ok(&order.limit_price),
+        Type::Stop => stop_ok(&order.stop_price),
+        Type::StopLimit => stop_ok(&order.stop_price) && limit_ok(&order.limit_price),
+        // Trailing stop orders are never executed automatically, but
+        // they can be filled explicitly.
+        Type::TrailingStop => false,
+      };
+
+      if marketable {
+        let price = price.clone();
+        updates.push(self.fill(index, price));
+      }
+    }
+    updates
+  }
+
+  /// Fill the order at the given index at the provided price.
+  fn fill(&mut self, index: usize, price: Num) -> OrderUpdate {
+    let now = Utc::now();
+    let order = &mut self.orders[index];
+    let quantity = match &order.amount {
+      Amount::Quantity { quantity } => quantity.clone(),
+      Amount::Notional { notional } => notional / &price,
+    };
+
+    order.status = order::Status::Filled;
+    order.filled_quantity = quantity.clone();
+    order.average_fill_price = Some(price.clone());
+    order.filled_at = Some(now);
+    order.updated_at = Some(now);
+    let order = order.clone();
+
+    let (delta, cash) = match order.side {
+      Side::Buy => (quantity.clone(), -(&quantity * &price)),
+      Side::Sell => (-quantity.clone(), &quantity * &price),
+    };
+    self.account.cash += cash;
+
+    let holding = self
+      .holdings
+      .entry(order.symbol.clone())
+      .or_insert_with(|| Holding {
+        quantity: Num::from(0),
+        average_entry_price: price.clone(),
+      });
+    let quantity = &holding.quantity + &delta;
+    if holding.quantity.is_zero() || quantity.is_zero() {
+      holding.average_entry_price = price;
+    } else if holding.quantity.is_positive() == delta.is_positive() {
+      // The position grew, so the entry price is a weighted average.
+      let cost = &holding.quantity * &holding.average_entry_price + &delta * &price;
+      holding.average_entry_price = cost / &quantity;
+    } else if holding.quantity.is_positive() != quantity.is_positive() {
+      // The position flipped sides.
+      holding.average_entry_price = price;
+    }
+    holding.quantity = quantity;
+
+    if holding.quantity.is_zero() {
+      let _holding = self.holdings.remove(&order.symbol);
+    }
+
+    OrderUpdate {
+      event: OrderStatus::Filled,
+      order,
+    }
+  }
+
+  /// Retrieve all open positions.
+  pub(crate) fn positions(&self) -> Vec<Position> {
+    self
+      .holdings
+      .keys()
+      .filter_map(|symbol| self.position(symbol).ok())
+      .collect()
+  }
+
+  /// Retrieve the position in a given asset.
+  pub(crate) fn position(&self, symbol: &str) -> Result<Position, Failure> {
+    let not_found = || Failure::not_found("position does not exist");
+    let asset = self.find_asset(symbol).ok_or_else(not_found)?;
+    let holding = self.holdings.get(&asset.symbol).ok_or_else(not_found)?;
+    let price = self
+      .prices
+      .get(&asset.symbol)
+      .cloned()
+      .unwrap_or_else(|| holding.average_entry_price.clone());
+
+    let (side, quantity) = if holding.quantity.is_negative() {
+      (position::Side::Short, -&holding.quantity)
+    } else {
+      (position::Side::Long, holding.quantity.clone())
+    };
+    let cost_basis = &quantity * &holding.average_entry_price;
+    let market_value = &quantity * &price;
+    let gain = match side {
+      position::Side::Long => &market_value - &cost_basis,
+      position::Side::Short => &cost_basis - &market_value,
+    };
+    let gain_percent = &gain / &cost_basis;
+
+    Ok(Position {
+      asset_id: asset.id,
+      symbol: asset.symbol.clone(),
+      exchange: asset.exchange,
+      asset_class: asset.class,
+      average_entry_price: holding.average_entry_price.clone(),
+      quantity: quantity.clone(),
+      quantity_available: quantity,
+      side,
+      market_value: Some(market_value),
+      cost_basis,
+      unrealized_gain_total: Some(gain.clone()),
+      unrealized_gain_total_percent: Some(gain_percent.clone()),
+      unrealized_gain_today: Some(gain),
+      unrealized_gain_today_percent: Some(gain_percent),
+      current_price: Some(price.clone()),
+      last_day_price: Some(price),
+      change_today: Some(Num::from(0)),
+      _non_exhaustive: (),
+    })
+  }
+
+  /// Liquidate the position in a given asset by means of a market
+  /// order.
+  pub(crate) fn close_position(
+    &mut self,
+    symbol: &str,
+  ) -> Result<(Order, Vec<OrderUpdate>), Failure> {
+    let position = self.position(symbol)?;
+    let side = match position.side {
+      position::Side::Long => Side::Sell,
+      position::Side::Short => Side::Buy,
+    };
+    let request = order::CreateReqInit::default().init(
+      position.symbol,
+      side,
+      Amount::quantity(position.quantity),
+    );
+    self.create_order(request)
+  }
+
+  /// Render a watchlist in the form reported by the API.
+  fn watchlist_json(&self, watchlist: &Watchlist, assets: bool) -> Value {
+    let mut value = json!({
+      "id": watchlist.id,
+      "name": watchlist.name,
+      "account_id": self.account.id,
+      "created_at": watchlist.created_at,
+      "updated_at": watchlist.updated_at,
+    });
+
+    if assets {
+      let assets = watchlist
+        .symbols
+        .iter()
+        .filter_map(|symbol| self.find_asset(symbol))
+        .collect::<Vec<_>>();
+      value["assets"] = json!(assets);
+    }
+    value
+  }
+
+  /// Find the index of the watchlist with the given ID.
+  fn find_watchlist(&self, id: &str) -> Result<usize, Failure> {
+    let id = Uuid::parse_str(id).map_err(|_| Failure::not_found("watchlist not found"))?;
+    self
+      .watchlists
+      .iter()
+      .position(|watchlist| watchlist.id == id)
+      .ok_or_else(|| Failure::not_found("watchlist not found"))
+  }
+
+  /// Check that a watchlist name and the symbols it contains are valid.
+  fn check_watchlist(
+    &self,
+    name: &str,
+    symbols: &[String],
+    index: Option<usize>,
+  ) -> Result<(), Failure> {
+    let duplicate = self
+      .watchlists
+      .iter()
+      .enumerate()
+      .any(|(i, watchlist)| Some(i) != index && watchlist.name == name);
+    if duplicate {
+      return Err(Failure::unprocessable("watchlist name must be unique"))
+    }
+
+    if let Some(symbol) = symbols
+      .iter()
+      .find(|symbol| self.find_asset(symbol).is_none())
+    {
+      return Err(Failure::unprocessable(format!("asset {symbol} not found")))
+    }
+    Ok(())
+  }
+
+  /// List all watchlists.
+  pub(crate) fn watchlists(&self) -> Value {
+    self
+      .watchlists
+      .iter()
+      .map(|watchlist| self.watchlist_json(watchlist, false))
+      .collect()
+  }
+
+  /// Retrieve a watchlist.
+  pub(crate) fn watchlist(&self, id: &str) -> Result<Value, Failure> {
+    let index = self.find_watchlist(id)?;
+    Ok(self.watchlist_json(&self.watchlists[index], true))
+  }
+
+  /// Create a watchlist.
+  pub(crate) fn create_watchlist(
+    &mut self,
+    name: String,
+    symbols: Vec<String>,
+  ) -> Result<Value, Failure> {
+    let () = self.check_watchlist(&name, &symbols, None)?;
+
+    let now = Utc::now();
+    let watchlist = Watchlist {
+      id: Uuid::new_v4(),
+      name,
+      created_at: now,
+      updated_at: now,
+      symbols,
+    };
+    let value = self.watchlist_json(&watchlist, true);
+    self.watchlists.push(watchlist);
+    Ok(value)
+  }
+
+  /// Update a watchlist.
+  pub(crate) fn update_watchlist(
+    &mut self,
+    id: &str,
+    name: String,
+    symbols: Vec<String>,
+  ) -> Result<Value, Failure> {
+    let index = self.find_watchlist(id)?;
+    let () = self.check_watchlist(&name, &symbols, Some(index))?;
+
+    let watchlist = &mut self.watchlists[index];
+    watchlist.name = name;
+    watchlist.symbols = symbols;
+    watchlist.updated_at = Utc::now();
+    Ok(self.watchlist_json(&self.watchlists[index], true))
+  }
+
+  /// Delete a watchlist.
+  pub(crate) fn delete_watchlist(&mut self, id: &str) -> Result<(), Failure> {
+    let index = self.find_watchlist(id)?;
+    let _watchlist = self.watchlists.remove(index);
+    Ok(())
+  }
+}
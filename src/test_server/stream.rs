@@ -0,0 +1,143 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use futures::SinkExt as _;
+use futures::StreamExt as _;
+
+use serde_json::from_str as from_json;
+use serde_json::to_string as to_json;
+
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio::spawn;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+use tracing::debug;
+
+use tungstenite::accept_async;
+use tungstenite::tungstenite::Error as WebSocketError;
+use tungstenite::tungstenite::Message;
+use tungstenite::WebSocketStream;
+
+use crate::api::v2::updates::Authenticate;
+use crate::api::v2::updates::Authentication;
+use crate::api::v2::updates::AuthenticationStatus;
+use crate::api::v2::updates::Listen;
+use crate::api::v2::updates::OrderMessage;
+use crate::api::v2::updates::OrderUpdate;
+use crate::api::v2::updates::StreamType;
+use crate::api::v2::updates::Streams;
+
+use super::Shared;
+
+
+/// Send a message to the client.
+async fn send(
+  stream: &mut WebSocketStream<TcpStream>,
+  message: &OrderMessage,
+) -> Result<(), WebSocketError> {
+  // Serialization of our own types is not expected to fail.
+  let json = to_json(message).unwrap();
+  stream.send(Message::Text(json)).await
+}
+
+/// Receive the next order update, if we are subscribed to them.
+async fn recv(updates: &mut Option<Receiver<OrderUpdate>>) -> OrderUpdate {
+  loop {
+    match updates {
+      Some(receiver) => match receiver.recv().await {
+        Ok(update) => break update,
+        // If the client can't keep up we just skip updates, similar
+        // to what would happen on a real connection.
+        Err(RecvError::Lagged(_)) => continue,
+        Err(RecvError::Closed) => *updates = None,
+      },
+      None => futures::future::pending().await,
+    }
+  }
+}
+
+
+/// Serve an order update stream to a single client.
+async fn serve_client(shared: Arc<Shared>, stream: TcpStream) -> Result<(), WebSocketError> {
+  let mut stream = accept_async(stream).await?;
+
+  let authorized = match stream.next().await {
+    Some(Ok(Message::Text(text))) => match from_json::<Authenticate<'_>>(&text) {
+      Ok(Authenticate::Request { key_id, secret }) => {
+        key_id == shared.key_id && secret == shared.secret
+      },
+      Err(..) => false,
+    },
+    Some(Ok(..)) => false,
+    Some(Err(err)) => return Err(err),
+    None => return Ok(()),
+  };
+
+  let status = if authorized {
+    AuthenticationStatus::Authorized
+  } else {
+    AuthenticationStatus::Unauthorized
+  };
+  let message = OrderMessage::AuthenticationMessage(Authentication { status });
+  let () = send(&mut stream, &message).await?;
+
+  if !authorized {
+    return stream.close(None).await
+  }
+
+  let mut updates = None;
+  loop {
+    select! {
+      message = stream.next() => match message {
+        Some(Ok(Message::Text(text))) => {
+          if let Ok(Listen::Request(streams)) = from_json::<Listen<'_>>(&text) {
+            let listening = streams
+              .streams
+              .iter()
+              .filter(|stream| **stream == StreamType::OrderUpdates)
+              .copied()
+              .collect::<Vec<_>>();
+            if !listening.is_empty() && updates.is_none() {
+              updates = Some(shared.updates.subscribe());
+            }
+
+            let streams = Streams {
+              streams: Cow::Owned(listening),
+            };
+            let () = send(&mut stream, &OrderMessage::ListeningMessage(streams)).await?;
+          }
+        },
+        Some(Ok(Message::Close(..))) | None => break Ok(()),
+        Some(Ok(..)) => (),
+        Some(Err(err)) => break Err(err),
+      },
+      update = recv(&mut updates) => {
+        let () = send(&mut stream, &OrderMessage::OrderUpdate(update)).await?;
+      },
+    }
+  }
+}
+
+
+/// Accept websocket connections and serve order updates over them.
+pub(super) async fn serve(shared: Arc<Shared>, listener: TcpListener) {
+  loop {
+    match listener.accept().await {
+      Ok((stream, _addr)) => {
+        let shared = shared.clone();
+        let _handle = spawn(async move {
+          if let Err(err) = serve_client(shared, stream).await {
+            debug!(error = display(&err), "order update stream failed");
+          }
+        });
+      },
+      Err(err) => debug!(error = display(&err), "failed to accept connection"),
+    }
+  }
+}
@@ -0,0 +1,319 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use http::Uri;
+
+use hyper::service::Service;
+
+use thiserror::Error;
+
+use tokio::task_local;
+use tokio::time::timeout;
+
+use crate::transport::TransportError;
+
+
+/// Time limits applied to the individual phases of a request.
+///
+/// Each phase is bounded separately and a value of `None` means that
+/// the phase is not subject to a time limit (the default).
+///
+/// Timeouts are configured for all requests of a
+/// [`Client`][crate::Client] using `Builder::timeouts`, and they can be
+/// overridden for individual requests using
+/// [`Client::with_timeouts`][crate::Client::with_timeouts].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timeouts {
+  /// The maximum time to spend on establishing a connection to the
+  /// server, including the TLS handshake.
+  ///
+  /// This limit is only honored by the default `hyper` based
+  /// transport and it does not apply when an idle connection is
+  /// reused.
+  pub connect: Option<Duration>,
+  /// The maximum time to wait for the response headers to arrive,
+  /// starting at the time the request is handed to the transport.
+  ///
+  /// Note that this limit includes the time spent establishing a
+  /// connection, if any.
+  pub response: Option<Duration>,
+  /// The maximum time to spend on reading the response body, once the
+  /// headers have been received.
+  pub body: Option<Duration>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// The phase of a request during which a timeout occurred.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeoutPhase {
+  /// The connection to the server could not be established in time.
+  ///
+  /// The request was not sent.
+  Connect,
+  /// The response headers were not received in time.
+  ///
+  /// The request may or may not have been processed by the server.
+  Response,
+  /// The response body could not be read in time.
+  ///
+  /// The request may or may not have been processed by the server.
+  Body,
+}
+
+impl Display for TimeoutPhase {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> FmtResult {
+    let phase = match self {
+      Self::Connect => "connecting to the server",
+      Self::Response => "waiting for the response",
+      Self::Body => "reading the response body",
+    };
+    fmt.write_str(phase)
+  }
+}
+
+
+/// The error reported by a [`TimeoutConnector`] when the connection
+/// could not be established in time.
+#[derive(Clone, Copy, Debug, Error)]
+#[error("timed out establishing a connection")]
+pub(crate) struct ConnectTimeout;
+
+impl ConnectTimeout {
+  /// Check whether the provided error was caused by a connect timeout.
+  pub(crate) fn caused(err: &(dyn StdError + 'static)) -> bool {
+    let mut next = Some(err);
+    while let Some(err) = next {
+      if err.is::<Self>() {
+        return true
+      }
+      next = err.source();
+    }
+    false
+  }
+}
+
+
+task_local! {
+  /// The connect timeout to apply to connections established on
+  /// behalf of the current request.
+  static CONNECT_TIMEOUT: Option<Duration>;
+}
+
+
+/// Run the provided future, applying the given connect timeout to all
+/// connections established by a [`TimeoutConnector`] in the process.
+pub(crate) async fn with_connect_timeout<F>(connect: Option<Duration>, future: F) -> F::Output
+where
+  F: Future,
+{
+  CONNECT_TIMEOUT.scope(connect, future).await
+}
+
+
+/// A connector wrapping another one, bounding the time it may take to
+/// establish a connection.
+///
+/// The timeout in effect is the one set for the request on whose behalf
+/// the connection is established, using [`with_connect_timeout`].
+#[derive(Clone, Debug)]
+pub(crate) struct TimeoutConnector<C> {
+  inner: C,
+}
+
+impl<C> TimeoutConnector<C> {
+  /// Create a new `TimeoutConnector` wrapping the provided one.
+  pub(crate) fn new(inner: C) -> Self {
+    Self { inner }
+  }
+}
+
+impl<C> Service<Uri> for TimeoutConnector<C>
+where
+  C: Service<Uri>,
+  C::Error: Into<TransportError>,
+  C::Future: Send + 'static,
+{
+  type Response = C::Response;
+  type Error = TransportError;
+  type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_ready(cx).map_err(Into::into)
+  }
+
+  fn call(&mut self, uri: Uri) -> Self::Future {
+    // If the connection is established outside of the scope of a
+    // request (e.g., because `hyper` decided to finish it in the
+    // background) we do not enforce a timeout.
+    let connect = CONNECT_TIMEOUT.try_with(|connect| *connect).ok().flatten();
+    let future = self.inner.call(uri);
+
+    Box::pin(async move {
+      match connect {
+        Some(connect) => match timeout(connect, future).await {
+          Ok(result) => result.map_err(Into::into),
+          Err(..) => Err(Box::new(ConnectTimeout) as TransportError),
+        },
+        None => future.await.map_err(Into::into),
+      }
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::Mutex;
+
+  use async_trait::async_trait;
+
+  use http::Request;
+  use http::Response;
+  use http::StatusCode;
+
+  use hyper::body::Bytes;
+  use hyper::body::Sender;
+  use hyper::Body;
+
+  use test_log::test;
+
+  use tokio::net::TcpListener;
+  use tokio::time::sleep;
+
+  use crate::api::v2::clock;
+  use crate::transport::Transport;
+  use crate::ApiInfo;
+  use crate::Client;
+  use crate::RequestError;
+
+
+  /// A transport that takes a long time to respond and, once it does,
+  /// never finishes sending the body.
+  #[derive(Debug, Default)]
+  struct Stall {
+    /// The senders of bodies handed out, kept alive so that bodies
+    /// remain incomplete.
+    senders: Mutex<Vec<Sender>>,
+  }
+
+  #[async_trait]
+  impl Transport for Stall {
+    async fn send(&self, _request: Request<Bytes>) -> Result<Response<Body>, TransportError> {
+      let () = sleep(Duration::from_secs(10)).await;
+      let (sender, body) = Body::channel();
+      let () = self.senders.lock().unwrap().push(sender);
+      Ok(Response::new(body))
+    }
+  }
+
+
+  /// Create an `ApiInfo` object for a server that is never contacted.
+  fn api_info() -> ApiInfo {
+    ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap()
+  }
+
+
+  /// Check that a response timeout is reported as such.
+  #[test(tokio::test(start_paused = true))]
+  async fn response_timeout() {
+    let timeouts = Timeouts {
+      response: Some(Duration::from_secs(1)),
+      ..Default::default()
+    };
+    let client = Client::builder()
+      .transport(Stall::default())
+      .timeouts(timeouts)
+      .build(api_info());
+
+    let err = client.issue::<clock::Get>(&()).await.unwrap_err();
+    assert!(
+      matches!(err, RequestError::Timeout(TimeoutPhase::Response)),
+      "{err:?}"
+    );
+  }
+
+  /// Check that a timeout while reading the body is reported as such,
+  /// and that timeouts can be overridden for individual requests.
+  #[test(tokio::test(start_paused = true))]
+  async fn body_timeout() {
+    let client = Client::builder()
+      .transport(Stall::default())
+      .build(api_info());
+    let timeouts = Timeouts {
+      body: Some(Duration::from_secs(1)),
+      ..Default::default()
+    };
+
+    let err = client
+      .with_timeouts(timeouts)
+      .issue::<clock::Get>(&())
+      .await
+      .unwrap_err();
+    assert!(
+      matches!(err, RequestError::Timeout(TimeoutPhase::Body)),
+      "{err:?}"
+    );
+    assert_eq!(client.timeouts(), &Timeouts::default());
+
+    // The response's metadata are known by the time we read the body
+    // and so they should be reported.
+    let err = client
+      .with_timeouts(timeouts)
+      .issue_with_meta::<clock::Get>(&())
+      .await
+      .unwrap_err();
+    assert!(
+      matches!(err.error, RequestError::Timeout(TimeoutPhase::Body)),
+      "{err:?}"
+    );
+    assert_eq!(err.meta.unwrap().status, StatusCode::OK);
+  }
+
+  /// Check that failure to establish a connection in time is reported
+  /// as a connect timeout.
+  #[test(tokio::test)]
+  async fn connect_timeout() {
+    // We never accept the connection, meaning that the TLS handshake
+    // can't complete.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let api_info = ApiInfo::from_parts(format!("https://{addr}/"), "key", "secret").unwrap();
+
+    let timeouts = Timeouts {
+      connect: Some(Duration::from_millis(100)),
+      ..Default::default()
+    };
+    let client = Client::builder().timeouts(timeouts).build(api_info);
+
+    let err = client.issue::<clock::Get>(&()).await.unwrap_err();
+    assert!(
+      matches!(err, RequestError::Timeout(TimeoutPhase::Connect)),
+      "{err:?}"
+    );
+  }
+
+  /// Check that the phase is included in the error message.
+  #[test]
+  fn formatting() {
+    let err = RequestError::<()>::Timeout(TimeoutPhase::Body);
+    assert_eq!(
+      err.to_string(),
+      "the request timed out while reading the response body"
+    );
+  }
+}
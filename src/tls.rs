@@ -0,0 +1,146 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Selection of the TLS implementation used for talking to Alpaca.
+//!
+//! By default `native-tls` is used. If one of the `rustls-*` features
+//! is enabled, `rustls` takes precedence for HTTP as well as websocket
+//! connections.
+
+#[cfg(not(any(
+  feature = "native-tls",
+  feature = "rustls-native-roots",
+  feature = "rustls-webpki-roots"
+)))]
+compile_error!(
+  "one of the `native-tls`, `rustls-native-roots`, or `rustls-webpki-roots` features must be enabled"
+);
+
+#[cfg(not(any(feature = "rustls-native-roots", feature = "rustls-webpki-roots")))]
+mod imp {
+  use hyper_tls::HttpsConnector;
+
+  use tungstenite::Connector;
+
+  use crate::proxy::ProxyConnector;
+
+
+  /// Create the connector to use for HTTPS connections, establishing
+  /// the underlying connections using the provided connector.
+  pub(crate) fn https_connector(connector: ProxyConnector) -> HttpsConnector<ProxyConnector> {
+    HttpsConnector::new_with_connector(connector)
+  }
+
+  /// Retrieve the connector to use for websocket connections.
+  pub(crate) fn websocket_connector() -> Option<Connector> {
+    // `tungstenite` picks `native-tls` on its own.
+    None
+  }
+}
+
+#[cfg(any(feature = "rustls-native-roots", feature = "rustls-webpki-roots"))]
+mod imp {
+  use std::sync::Arc;
+
+  use hyper_rustls::HttpsConnector;
+  use hyper_rustls::HttpsConnectorBuilder;
+
+  use rustls::ClientConfig;
+  use rustls::RootCertStore;
+
+  use tungstenite::Connector;
+
+  use crate::proxy::ProxyConnector;
+
+
+  /// Add the root certificates of the operating system's certificate
+  /// store.
+  #[cfg(feature = "rustls-native-roots")]
+  fn add_native_roots(roots: &mut RootCertStore) {
+    use tracing::warn;
+
+    match rustls_native_certs::load_native_certs() {
+      Ok(certs) => {
+        let certs = certs.into_iter().map(|cert| cert.0).collect::<Vec<_>>();
+        let (_added, ignored) = roots.add_parsable_certificates(&certs);
+        if ignored > 0 {
+          warn!(ignored, "ignored unparsable native root certificates");
+        }
+      },
+      Err(err) => warn!(
+        error = display(&err),
+        "failed to load native root certificates"
+      ),
+    }
+  }
+
+  /// Add the root certificates curated by Mozilla, as provided by the
+  /// `webpki-roots` crate.
+  #[cfg(feature = "rustls-webpki-roots")]
+  fn add_webpki_roots(roots: &mut RootCertStore) {
+    use rustls::OwnedTrustAnchor;
+
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+      OwnedTrustAnchor::from_subject_spki_name_constraints(
+        anchor.subject,
+        anchor.spki,
+        anchor.name_constraints,
+      )
+    }));
+  }
+
+  /// Create the `rustls` configuration to use, trusting the root
+  /// certificates of all enabled root stores.
+  fn client_config() -> ClientConfig {
+    let mut roots = RootCertStore::empty();
+    #[cfg(feature = "rustls-native-roots")]
+    let () = add_native_roots(&mut roots);
+    #[cfg(feature = "rustls-webpki-roots")]
+    let () = add_webpki_roots(&mut roots);
+
+    ClientConfig::builder()
+      .with_safe_defaults()
+      .with_root_certificates(roots)
+      .with_no_client_auth()
+  }
+
+  /// Create the connector to use for HTTPS connections, establishing
+  /// the underlying connections using the provided connector.
+  pub(crate) fn https_connector(connector: ProxyConnector) -> HttpsConnector<ProxyConnector> {
+    HttpsConnectorBuilder::new()
+      .with_tls_config(client_config())
+      .https_or_http()
+      .enable_http1()
+      .wrap_connector(connector)
+  }
+
+  /// Retrieve the connector to use for websocket connections.
+  pub(crate) fn websocket_connector() -> Option<Connector> {
+    Some(Connector::Rustls(Arc::new(client_config())))
+  }
+}
+
+pub(crate) use imp::https_connector;
+pub(crate) use imp::websocket_connector;
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::proxy::ProxyConnector;
+
+
+  /// Check that we can create the connectors for the configured TLS
+  /// implementation.
+  #[test]
+  fn create_connectors() {
+    let _connector = https_connector(ProxyConnector::new(None));
+    let connector = websocket_connector();
+    let rustls = cfg!(any(
+      feature = "rustls-native-roots",
+      feature = "rustls-webpki-roots"
+    ));
+    assert_eq!(connector.is_some(), rustls);
+  }
+}
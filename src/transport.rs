@@ -0,0 +1,159 @@
+// Copyright (C) 2024 The apca Developers
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::error::Error as StdError;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use http::Request;
+use http::Response;
+
+use hyper::body::Bytes;
+use hyper::client::connect::Connect;
+use hyper::Body;
+use hyper::Client as HttpClient;
+
+
+/// The error type reported by a [`Transport`].
+pub type TransportError = Box<dyn StdError + Send + Sync + 'static>;
+
+
+/// A trait representing the mechanism by which a
+/// [`Client`][crate::Client] sends HTTP requests and receives the
+/// corresponding responses.
+///
+/// By default, a `Client` uses a `hyper` based transport that talks to
+/// the Alpaca API over the network. A custom transport can be set on
+/// the builder returned by [`Client::builder`][crate::Client::builder],
+/// for example to serve canned responses from memory in tests.
+///
+/// A transport is also implemented for functions mapping a request to a
+/// response, which is the most convenient way of providing an
+/// in-memory backend:
+/// ```rust
+/// # use apca::ApiInfo;
+/// # use apca::Client;
+/// use http::Response;
+/// use hyper::Body;
+///
+/// # let api_info = ApiInfo::from_parts("https://example.com/", "", "").unwrap();
+/// let client = Client::builder()
+///   .transport(|_request| Response::new(Body::from("{}")))
+///   .build(api_info);
+/// ```
+#[async_trait]
+pub trait Transport: Send + Sync {
+  /// Send the provided request and retrieve the response.
+  ///
+  /// The response body is consumed by the caller, which also takes
+  /// care of decoding it should it be compressed.
+  async fn send(&self, request: Request<Bytes>) -> Result<Response<Body>, TransportError>;
+}
+
+#[async_trait]
+impl<C> Transport for HttpClient<C, Body>
+where
+  C: Connect + Clone + Send + Sync + 'static,
+{
+  async fn send(&self, request: Request<Bytes>) -> Result<Response<Body>, TransportError> {
+    let response = self.request(request.map(Body::from)).await?;
+    Ok(response)
+  }
+}
+
+#[async_trait]
+impl<F> Transport for F
+where
+  F: Fn(Request<Bytes>) -> Response<Body> + Send + Sync,
+{
+  async fn send(&self, request: Request<Bytes>) -> Result<Response<Body>, TransportError> {
+    Ok(self(request))
+  }
+}
+
+
+/// A [`Transport`] shared between a [`Client`][crate::Client] and its
+/// clones.
+#[derive(Clone)]
+pub(crate) struct SharedTransport(pub(crate) Arc<dyn Transport>);
+
+impl Debug for SharedTransport {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_tuple("SharedTransport").finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http::StatusCode;
+
+  use test_log::test;
+
+  use crate::api::v2::clock;
+  use crate::ApiInfo;
+  use crate::Client;
+
+
+  /// Check that we can drive an endpoint through an in-memory
+  /// transport.
+  #[test(tokio::test)]
+  async fn in_memory_transport() {
+    let transport = |request: Request<Bytes>| {
+      assert_eq!(request.uri().path(), "/v2/clock");
+
+      let body = r#"{
+  "timestamp": "2024-01-02T10:00:00.000000000-05:00",
+  "is_open": true,
+  "next_open": "2024-01-03T09:30:00-05:00",
+  "next_close": "2024-01-02T16:00:00-05:00"
+}"#;
+      Response::new(Body::from(body))
+    };
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+    let clock = client.issue::<clock::Get>(&()).await.unwrap();
+    assert!(clock.open);
+  }
+
+  /// Make sure that errors reported by a transport are surfaced.
+  #[test(tokio::test)]
+  async fn failing_transport() {
+    struct Failing;
+
+    #[async_trait]
+    impl Transport for Failing {
+      async fn send(&self, _request: Request<Bytes>) -> Result<Response<Body>, TransportError> {
+        Err("the backend is unavailable".into())
+      }
+    }
+
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(Failing).build(api_info);
+    let err = client.issue_with_meta::<clock::Get>(&()).await.unwrap_err();
+    assert!(err.meta.is_none());
+    match err.error {
+      crate::RequestError::Transport(err) => {
+        assert_eq!(err.to_string(), "the backend is unavailable")
+      },
+      err => panic!("Received unexpected error: {err:?}"),
+    }
+
+    let transport = |_request| {
+      let mut response = Response::new(Body::from("{}"));
+      *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+      response
+    };
+    let api_info = ApiInfo::from_parts("https://example.com/", "key", "secret").unwrap();
+    let client = Client::builder().transport(transport).build(api_info);
+    let err = client.issue_with_meta::<clock::Get>(&()).await.unwrap_err();
+    assert_eq!(err.meta.unwrap().status, StatusCode::SERVICE_UNAVAILABLE);
+  }
+}
@@ -1,6 +1,8 @@
 // Copyright (C) 2019-2023 The apca Developers
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use http::HeaderMap;
+
 use url::Url;
 
 use tokio::net::TcpStream;
@@ -11,13 +13,18 @@ use tracing::trace;
 use tracing::Level;
 use tracing_futures::Instrument;
 
-use tungstenite::connect_async;
+use tungstenite::client_async_tls_with_config;
+use tungstenite::connect_async_tls_with_config;
+use tungstenite::tungstenite::client::IntoClientRequest as _;
+use tungstenite::tungstenite::Error as WebSocketError;
 use tungstenite::MaybeTlsStream;
 use tungstenite::WebSocketStream;
 
 use websocket_util::wrap::Wrapper;
 
+use crate::tls::websocket_connector;
 use crate::Error;
+use crate::Proxy;
 
 
 /// A custom [`Result`]-style type that we can implement a foreign trait
@@ -43,16 +50,36 @@ impl<T, E> From<Result<T, E>> for MessageResult<T, E> {
 
 
 /// Internal function to connect to websocket server.
-async fn connect_internal(url: &Url) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Error> {
+async fn connect_internal(
+  url: &Url,
+  proxy: Option<&Proxy>,
+  headers: HeaderMap,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Error> {
   let span = span!(Level::DEBUG, "stream");
 
   async move {
     debug!(message = "connecting", url = display(url));
 
+    let mut request = url.as_str().into_client_request()?;
+    let () = request.headers_mut().extend(headers);
+
+    let connector = websocket_connector();
+    let host = url.host_str().unwrap_or_default();
+    let proxy = proxy.filter(|proxy| proxy.applies_to(host));
     // We just ignore the response & headers that are sent along after
     // the connection is made. Alpaca does not seem to be using them,
     // really.
-    let (stream, response) = connect_async(url).await?;
+    let (stream, response) = match (proxy, url.port_or_known_default()) {
+      (Some(proxy), Some(port)) => {
+        debug!(proxy = debug(proxy), "connecting through proxy");
+        let stream = proxy
+          .connect(host, port)
+          .await
+          .map_err(WebSocketError::Io)?;
+        client_async_tls_with_config(request, stream, None, connector).await?
+      },
+      _ => connect_async_tls_with_config(request, None, false, connector).await?,
+    };
     debug!("connection successful");
     trace!(response = debug(&response));
 
@@ -66,8 +93,20 @@ async fn connect_internal(url: &Url) -> Result<WebSocketStream<MaybeTlsStream<Tc
 /// Connect to a websocket server.
 pub(crate) async fn connect(
   url: &Url,
+  proxy: Option<&Proxy>,
 ) -> Result<Wrapper<WebSocketStream<MaybeTlsStream<TcpStream>>>, Error> {
-  connect_internal(url)
+  connect_with_headers(url, proxy, HeaderMap::new()).await
+}
+
+
+/// Connect to a websocket server, sending the provided additional
+/// headers along with the handshake request.
+pub(crate) async fn connect_with_headers(
+  url: &Url,
+  proxy: Option<&Proxy>,
+  headers: HeaderMap,
+) -> Result<Wrapper<WebSocketStream<MaybeTlsStream<TcpStream>>>, Error> {
+  connect_internal(url, proxy, headers)
     .await
     .map(|stream| Wrapper::builder().build(stream))
 }
@@ -78,10 +117,20 @@ pub(crate) mod test {
   use super::*;
 
   use std::future::Future;
+  use std::net::SocketAddr;
+
+  use futures::SinkExt as _;
+  use futures::StreamExt as _;
+
+  use tokio::net::TcpListener;
+  use tokio::spawn;
+
+  use tungstenite::accept_async;
 
   use websocket_util::test::mock_server;
   use websocket_util::test::WebSocketStream;
   use websocket_util::tungstenite::Error as WebSocketError;
+  use websocket_util::tungstenite::Message;
 
   use crate::subscribable::Subscribable;
   use crate::ApiInfo;
@@ -91,31 +140,91 @@ pub(crate) mod test {
   pub(crate) const KEY_ID: &str = "USER12345678";
   /// The fake secret we use.
   pub(crate) const SECRET: &str = "justletmein";
-
-
-  /// Instantiate a dummy websocket server serving messages as per the
-  /// provided function `f` and attempt to connect to it to stream
-  /// messages.
-  pub(crate) async fn mock_stream<S, F, R>(f: F) -> Result<(S::Stream, S::Subscription), Error>
+  // TODO: Until we can interpolate more complex expressions using
+  //       `std::format` in a const context we have to hard code the
+  //       values of `KEY_ID` and `SECRET` here.
+  /// The authentication request clients send.
+  pub(crate) const AUTH_REQ: &str =
+    r#"{"action":"auth","key":"USER12345678","secret":"justletmein"}"#;
+  /// The message a market data stream server sends once a client
+  /// connected.
+  pub(crate) const DATA_CONN_RESP: &str = r#"[{"T":"success","msg":"connected"}]"#;
+  /// The response of a market data stream server to a successful
+  /// authentication request.
+  pub(crate) const DATA_AUTH_RESP: &str = r#"[{"T":"success","msg":"authenticated"}]"#;
+  /// The response of the order update stream server to a successful
+  /// authentication request.
+  pub(crate) const UPDATES_AUTH_RESP: &str =
+    r#"{"stream":"authorization","data":{"action":"authenticate","status":"authorized"}}"#;
+
+
+  /// Instantiate a dummy websocket server accepting any number of
+  /// connections, one after the other, and serving messages on each
+  /// as per the provided function `f`, which receives the zero based
+  /// index of the connection.
+  pub(crate) async fn mock_server_multi<F, R>(mut f: F) -> SocketAddr
   where
-    S: Subscribable<Input = ApiInfo>,
-    F: FnOnce(WebSocketStream) -> R + Send + Sync + 'static,
-    R: Future<Output = Result<(), WebSocketError>> + Send + Sync + 'static,
+    F: FnMut(usize, WebSocketStream) -> R + Send + 'static,
+    R: Future<Output = Result<(), WebSocketError>> + Send + 'static,
   {
-    let addr = mock_server(f).await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let future = async move {
+      for idx in 0.. {
+        let (stream, _addr) = listener.accept().await.unwrap();
+        let stream = accept_async(MaybeTlsStream::Plain(stream)).await.unwrap();
+        let _result = f(idx, stream).await;
+      }
+    };
+
+    let _handle = spawn(future);
+    addr
+  }
+
+  /// Perform the connection and authentication handshake of a market
+  /// data stream on the server side.
+  pub(crate) async fn data_handshake(stream: &mut WebSocketStream) -> Result<(), WebSocketError> {
+    stream
+      .send(Message::Text(DATA_CONN_RESP.to_string()))
+      .await?;
+    assert_eq!(
+      stream.next().await.unwrap()?,
+      Message::Text(AUTH_REQ.to_string()),
+    );
+    stream.send(Message::Text(DATA_AUTH_RESP.to_string())).await
+  }
+
+  /// Create an `ApiInfo` object with both stream URLs pointing to a
+  /// websocket server at the given address.
+  pub(crate) fn mock_api_info(addr: SocketAddr) -> ApiInfo {
     let stream_url = Url::parse(&format!("ws://{addr}")).unwrap();
 
     // We just set both the API stream URL and the data stream URL to
     // our websocket server. We don't know which one clients are trying
     // to mock, but currently it's only one or the other.
-    let api_info = ApiInfo {
+    ApiInfo {
       api_base_url: Url::parse("http://example.com").unwrap(),
       api_stream_url: stream_url.clone(),
       data_base_url: Url::parse("http://example.com").unwrap(),
       data_stream_base_url: stream_url.clone(),
       key_id: KEY_ID.to_string(),
       secret: SECRET.to_string(),
-    };
+      proxy: None,
+    }
+  }
+
+  /// Instantiate a dummy websocket server serving messages as per the
+  /// provided function `f` and attempt to connect to it to stream
+  /// messages.
+  pub(crate) async fn mock_stream<S, F, R>(f: F) -> Result<(S::Stream, S::Subscription), Error>
+  where
+    S: Subscribable<Input = ApiInfo>,
+    F: FnOnce(WebSocketStream) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<(), WebSocketError>> + Send + Sync + 'static,
+  {
+    let addr = mock_server(f).await;
+    let api_info = mock_api_info(addr);
 
     S::connect(&api_info).await
   }